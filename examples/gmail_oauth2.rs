@@ -1,33 +1,15 @@
-extern crate base64;
 extern crate imap;
 
-struct GmailOAuth2 {
-    user: String,
-    access_token: String,
-}
-
-impl imap::Authenticator for GmailOAuth2 {
-    type Response = String;
-    #[allow(unused_variables)]
-    fn process(&self, data: &[u8]) -> Self::Response {
-        format!(
-            "user={}\x01auth=Bearer {}\x01\x01",
-            self.user, self.access_token
-        )
-    }
-}
+use imap::sasl::XOAuth2;
 
 fn main() {
-    let gmail_auth = GmailOAuth2 {
-        user: String::from("sombody@gmail.com"),
-        access_token: String::from("<access_token>"),
-    };
+    let mut gmail_auth = XOAuth2::new("sombody@gmail.com", "<access_token>");
 
     let client = imap::ClientBuilder::new("imap.gmail.com", 993)
         .connect()
         .expect("Could not connect to imap.gmail.com");
 
-    let mut imap_session = match client.authenticate("XOAUTH2", &gmail_auth) {
+    let mut imap_session = match client.authenticate(gmail_auth.mechanism(), &mut gmail_auth) {
         Ok(c) => c,
         Err((e, _unauth_client)) => {
             println!("error authenticating: {}", e);
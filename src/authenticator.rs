@@ -1,5 +1,10 @@
+use crate::error::{AuthenticationError, Error};
+
 /// This trait allows for pluggable authentication schemes. It is used by `Client::authenticate` to
 /// [authenticate using SASL](https://tools.ietf.org/html/rfc3501#section-6.2.2).
+///
+/// Mechanisms that need to keep state between challenges, or that may have to abort the
+/// exchange, should implement [`AuthenticatorMut`] instead.
 pub trait Authenticator {
     /// The type of the response to the challenge. This will usually be a `Vec<u8>` or `String`.
     type Response: AsRef<[u8]>;
@@ -7,4 +12,77 @@ pub trait Authenticator {
     /// Each base64-decoded server challenge is passed to `process`.
     /// The returned byte-string is base64-encoded and then sent back to the server.
     fn process(&self, challenge: &[u8]) -> Self::Response;
+
+    /// Whether the mechanism is client-first, that is whether the client sends the first
+    /// response without waiting for a challenge. See [`AuthenticatorMut::client_first`].
+    fn client_first(&self) -> bool {
+        false
+    }
+}
+
+/// A stateful authentication scheme that can fail, for use with `Client::authenticate`.
+///
+/// Every [`Authenticator`] `a` can be used as an `AuthenticatorMut` by passing `&a`.
+pub trait AuthenticatorMut {
+    /// The type of the response to the challenge. This will usually be a `Vec<u8>` or `String`.
+    type Response: AsRef<[u8]>;
+
+    /// Each base64-decoded server challenge is passed to `process`.
+    /// The returned byte-string is base64-encoded and then sent back to the server.
+    ///
+    /// If this returns an error, the exchange is cancelled by sending `*` to the server, and
+    /// authenticating fails with that error.
+    fn process(&mut self, challenge: &[u8]) -> Result<Self::Response, AuthenticationError>;
+
+    /// Called once the server has ended the exchange, with `Ok(())` if it accepted the
+    /// credentials with a tagged `OK`, or with the error it responded with otherwise, such as
+    /// [`Error::No`].
+    ///
+    /// If this returns an error, authenticating fails with that error instead. This allows a
+    /// mechanism to reject a server that has not proven its identity, or to report the details
+    /// of a failure that the server sent in a challenge. If the server had accepted the
+    /// credentials, the client logs out before failing, so the server closes the connection and
+    /// the `Client` returned along with the error cannot be used any further.
+    fn finish(&mut self, outcome: Result<(), &Error>) -> Result<(), AuthenticationError> {
+        let _ = outcome;
+        Ok(())
+    }
+
+    /// Whether the mechanism is client-first, that is whether the client sends the first
+    /// response without waiting for a challenge.
+    ///
+    /// If so, and the server advertises the [`SASL-IR`](https://tools.ietf.org/html/rfc4959)
+    /// capability, the response to an empty challenge is sent along with the `AUTHENTICATE`
+    /// command, which saves a round trip.
+    fn client_first(&self) -> bool {
+        false
+    }
+}
+
+impl<A: Authenticator + ?Sized> AuthenticatorMut for &A {
+    type Response = A::Response;
+
+    fn process(&mut self, challenge: &[u8]) -> Result<Self::Response, AuthenticationError> {
+        Ok(Authenticator::process(*self, challenge))
+    }
+
+    fn client_first(&self) -> bool {
+        Authenticator::client_first(*self)
+    }
+}
+
+impl<A: AuthenticatorMut + ?Sized> AuthenticatorMut for &mut A {
+    type Response = A::Response;
+
+    fn process(&mut self, challenge: &[u8]) -> Result<Self::Response, AuthenticationError> {
+        (**self).process(challenge)
+    }
+
+    fn finish(&mut self, outcome: Result<(), &Error>) -> Result<(), AuthenticationError> {
+        (**self).finish(outcome)
+    }
+
+    fn client_first(&self) -> bool {
+        (**self).client_first()
+    }
 }
@@ -2,17 +2,24 @@ use base64::{engine::general_purpose, Engine as _};
 use bufstream::BufStream;
 use chrono::{DateTime, FixedOffset};
 use imap_proto::Response;
+use std::borrow::Cow;
 use std::collections::HashSet;
 use std::collections::VecDeque;
 use std::io::{Read, Write};
 use std::ops::{Deref, DerefMut};
 use std::str;
 
-use super::authenticator::Authenticator;
+use super::authenticator::AuthenticatorMut;
+use super::command::{self, Command};
+#[cfg(feature = "sasl")]
+use super::error::AuthenticationError;
 use super::error::{Bad, Bye, Error, No, ParseError, Result, TagMismatch, ValidateError};
 use super::extensions;
 use super::parse::*;
+#[cfg(feature = "sasl")]
+use super::sasl::{self, BestAuthenticator};
 use super::types::*;
+use super::utf7;
 use super::utils::*;
 
 #[cfg(doc)]
@@ -22,6 +29,7 @@ static TAG_PREFIX: &str = "a";
 const INITIAL_TAG: u32 = 0;
 const CR: u8 = 0x0d;
 const LF: u8 = 0x0a;
+const IMAP4REV2: &str = "IMAP4rev2";
 
 macro_rules! quote {
     ($x:expr) => {
@@ -76,7 +84,7 @@ pub(crate) fn validate_str(
 ///
 /// The arguments `synopsis` and `arg_name` are used to construct the error message of
 /// [ValidateError] in case validation fails.
-fn validate_str_noquote(
+pub(crate) fn validate_str_noquote(
     synopsis: impl Into<String>,
     arg_name: impl Into<String>,
     value: &str,
@@ -109,7 +117,11 @@ fn validate_str_noquote(
 ///
 /// Note the lack of reference to SP or any other such whitespace terminals.
 /// Per this grammar, in theory we ought to be even more restrictive than "no whitespace".
-fn validate_sequence_set(
+///
+/// [RFC 5182](https://tools.ietf.org/html/rfc5182#section-4) also allows `$` in place of a
+/// sequence set, to refer to a saved search result; see
+/// [`SavedSearch`](crate::extensions::searchres::SavedSearch).
+pub(crate) fn validate_sequence_set(
     synopsis: impl Into<String>,
     arg_name: impl Into<String>,
     value: &str,
@@ -129,6 +141,30 @@ fn validate_sequence_set(
     Ok(value)
 }
 
+/// If `line` ends with the announcement of a literal (`{n}`, `{n+}` or the `~{n}` of a literal8),
+/// return its length.
+fn trailing_literal_len(line: &[u8]) -> Option<usize> {
+    let line = line.strip_suffix(b"}\r\n")?;
+    let start = line.iter().rposition(|&b| b == b'{')?;
+    let len = &line[start + 1..];
+    let len = len.strip_suffix(b"+").unwrap_or(len);
+    if len.is_empty() || !len.iter().all(u8::is_ascii_digit) {
+        return None;
+    }
+    str::from_utf8(len).ok()?.parse().ok()
+}
+
+/// Formats the flag list and optional date-time that precede a message in an `APPEND` command.
+///
+/// [`Flag::Recent`] is not allowed in `APPEND`, so it is left out.
+pub(crate) fn append_args(flags: &[Flag<'_>], date: Option<DateTime<FixedOffset>>) -> String {
+    let flagstr = iter_join(flags.iter().filter(|f| **f != Flag::Recent), " ");
+    match date {
+        Some(date) => format!("({}) \"{}\"", flagstr, date.format("%d-%h-%Y %T %z")),
+        None => format!("({})", flagstr),
+    }
+}
+
 /// An authenticated IMAP session providing the usual IMAP commands. This type is what you get from
 /// a successful login attempt.
 ///
@@ -136,6 +172,11 @@ fn validate_sequence_set(
 /// a selected mailbox whose status has changed. See the note on [unilateral server responses
 /// in RFC 3501](https://tools.ietf.org/html/rfc3501#section-7). Any such messages are parsed out
 /// and sent on `Session::unsolicited_responses`.
+///
+/// Mailbox names are passed to and returned from a `Session` as regular UTF-8 strings. They are
+/// converted to and from the [modified UTF-7](crate::utf7) encoding used on the wire, unless
+/// `UTF8=ACCEPT` has been enabled with [`Session::enable`] or the session speaks IMAP4rev2 (see
+/// [`Session::is_imap4rev2`]), in which case the server accepts and returns UTF-8 directly.
 // Both `Client` and `Session` deref to [`Connection`](struct.Connection.html), the underlying
 // primitives type.
 #[derive(Debug)]
@@ -144,6 +185,12 @@ pub struct Session<T: Read + Write> {
     /// Server responses that are not related to the current command. See also the note on
     /// [unilateral server responses in RFC 3501](https://tools.ietf.org/html/rfc3501#section-7).
     pub(crate) unsolicited_responses: VecDeque<UnsolicitedResponse>,
+
+    /// Extensions enabled through [`Session::enable`].
+    pub(crate) enabled: extensions::enable::Enabled,
+
+    /// The currently selected mailbox, if any.
+    selected: Option<SelectedMailbox>,
 }
 
 /// An (unauthenticated) handle to talk to an IMAP server. This is what you get when first
@@ -156,12 +203,18 @@ pub struct Client<T: Read + Write> {
     conn: Connection<T>,
 }
 
+/// The stream underneath a connection's buffering, which can be switched to compressed traffic.
+#[cfg(feature = "compress")]
+type Transport<T> = extensions::compress::Compressible<T>;
+#[cfg(not(feature = "compress"))]
+type Transport<T> = T;
+
 /// The underlying primitives type. Both `Client`(unauthenticated) and `Session`(after successful
 /// login) use a `Connection` internally for the TCP stream primitives.
 #[derive(Debug)]
 #[doc(hidden)]
 pub struct Connection<T: Read + Write> {
-    pub(crate) stream: BufStream<T>,
+    pub(crate) stream: BufStream<Transport<T>>,
     tag: u32,
 
     /// Enable debug mode for this connection so that all client-server interactions are printed to
@@ -170,6 +223,9 @@ pub struct Connection<T: Read + Write> {
 
     /// Tracks if we have read a greeting.
     pub greeting_read: bool,
+
+    /// The capabilities last reported by the server, if they have been requested.
+    pub(crate) capabilities: Option<Capabilities>,
 }
 
 impl<T: Read + Write> Connection<T> {
@@ -196,6 +252,7 @@ pub struct AppendCmd<'a, T: Read + Write> {
     mailbox: &'a str,
     flags: Vec<Flag<'a>>,
     date: Option<DateTime<FixedOffset>>,
+    binary: bool,
 }
 
 impl<'a, T: Read + Write> AppendCmd<'a, T> {
@@ -230,36 +287,36 @@ impl<'a, T: Read + Write> AppendCmd<'a, T> {
         self
     }
 
+    /// Send the message unchanged as binary content, that is, as a
+    /// [`literal8`](https://tools.ietf.org/html/rfc3516#section-4.2).
+    ///
+    /// The message may then contain any octet, including NUL, and use the `binary` content
+    /// transfer encoding. The server may convert it to a 7-bit encoding, or refuse it with
+    /// [`Error::No`] and the `UNKNOWN-CTE` response code if it cannot.
+    ///
+    /// This requires that the server advertises the `BINARY` capability.
+    pub fn binary(&mut self) -> &mut Self {
+        self.binary = true;
+        self
+    }
+
     /// Finishes up the command and executes it.
     ///
     /// Note: be sure to set flags and optional date before you
     /// finish the command.
     pub fn finish(&mut self) -> Result<Appended> {
-        let flagstr = iter_join(self.flags.iter().filter(|f| **f != Flag::Recent), " ");
-
-        let datestr = if let Some(date) = self.date {
-            format!(" \"{}\"", date.format("%d-%h-%Y %T %z"))
+        let mailbox = self.session.encode_mailbox_name(self.mailbox);
+        let mut command = Command::new("APPEND ");
+        command
+            .push_string("APPEND", "mailbox", &mailbox)?
+            .push_str(&format!(" {} ", append_args(&self.flags, self.date)));
+        if self.binary {
+            command.push_literal8(self.content);
         } else {
-            "".to_string()
-        };
-
-        self.session.run_command(&format!(
-            "APPEND \"{}\" ({}){} {{{}}}",
-            self.mailbox,
-            flagstr,
-            datestr,
-            self.content.len()
-        ))?;
-        let mut v = Vec::new();
-        self.session.readline(&mut v)?;
-        if !v.starts_with(b"+") {
-            return Err(Error::Append);
+            command.push_literal(self.content);
         }
-        self.session.stream.write_all(self.content)?;
-        self.session.stream.write_all(b"\r\n")?;
-        self.session.stream.flush()?;
         self.session
-            .read_response()
+            .execute(&command)
             .and_then(|(lines, _)| parse_append(&lines, &mut self.session.unsolicited_responses))
     }
 }
@@ -343,12 +400,15 @@ impl<T: Read + Write> Client<T> {
     /// # }
     /// ```
     pub fn new(stream: T) -> Client<T> {
+        #[cfg(feature = "compress")]
+        let stream = extensions::compress::Compressible::new(stream);
         Client {
             conn: Connection {
                 stream: BufStream::new(stream),
                 tag: INITIAL_TAG,
                 debug: false,
                 greeting_read: false,
+                capabilities: None,
             },
         }
     }
@@ -359,6 +419,8 @@ impl<T: Read + Write> Client<T> {
     /// an underlying transport.
     pub fn into_inner(self) -> Result<T> {
         let res = self.conn.stream.into_inner()?;
+        #[cfg(feature = "compress")]
+        let res = res.into_inner();
         Ok(res)
     }
 
@@ -370,8 +432,10 @@ impl<T: Read + Write> Client<T> {
     pub fn capabilities(&mut self) -> Result<Capabilities> {
         // Create a temporary vec deque as we do not care about out of band responses before login
         let mut unsolicited_responses = VecDeque::new();
-        self.run_command_and_read_response("CAPABILITY")
-            .and_then(|lines| Capabilities::parse(lines, &mut unsolicited_responses))
+        let lines = self.run_command_and_read_response("CAPABILITY")?;
+        let capabilities = Capabilities::parse(lines, &mut unsolicited_responses)?;
+        self.conn.capabilities = Some(capabilities.clone());
+        Ok(capabilities)
     }
 
     /// Log in to the IMAP server. Upon success a [`Session`](struct.Session.html) instance is
@@ -404,21 +468,39 @@ impl<T: Read + Write> Client<T> {
         password: impl AsRef<str>,
     ) -> ::std::result::Result<Session<T>, (Error, Client<T>)> {
         let synopsis = "LOGIN";
-        let u =
-            ok_or_unauth_client_err!(validate_str(synopsis, "username", username.as_ref()), self);
-        let p =
-            ok_or_unauth_client_err!(validate_str(synopsis, "password", password.as_ref()), self);
+        let mut command = Command::new("LOGIN ");
+        ok_or_unauth_client_err!(
+            command.push_string(synopsis, "username", username.as_ref()),
+            self
+        );
+        command.push_str(" ");
         ok_or_unauth_client_err!(
-            self.run_command_and_check_ok(&format!("LOGIN {} {}", u, p)),
+            command.push_string(synopsis, "password", password.as_ref()),
             self
         );
+        let (lines, ok) = ok_or_unauth_client_err!(self.execute(&command), self);
 
-        Ok(Session::new(self.conn))
+        let mut session = Session::new(self.conn);
+        session.cache_capabilities(&lines[ok..]);
+        Ok(session)
     }
 
     /// Authenticate with the server using the given custom `authenticator` to handle the server's
     /// challenge.
     ///
+    /// The `authenticator` is either a reference to an [`Authenticator`](crate::Authenticator),
+    /// or an [`AuthenticatorMut`], usually passed as `&mut`. If an `AuthenticatorMut` fails, the
+    /// exchange is cancelled and this returns its error as [`Error::Authentication`]. If it only
+    /// fails once the server has accepted the credentials, as SCRAM does when the server does not
+    /// prove that it knows the password, the client is logged out instead. The server then closes
+    /// the connection, so the returned `Client` cannot be used any further, and a new connection
+    /// is needed to try again. Ready-made authenticators for common mechanisms are available in
+    /// [`sasl`].
+    ///
+    /// If the mechanism is [client-first](AuthenticatorMut::client_first) and the cached
+    /// capabilities include [`SASL-IR`](https://tools.ietf.org/html/rfc4959), the initial response
+    /// is sent along with the `AUTHENTICATE` command instead of waiting for an empty challenge.
+    ///
     /// ```no_run
     /// struct OAuth2 {
     ///     user: String,
@@ -448,6 +530,11 @@ impl<T: Read + Write> Client<T> {
     ///         Ok(session) => {
     ///             // you are successfully authenticated!
     ///         },
+    ///         Err((imap::Error::Authentication(e), _)) => {
+    ///             eprintln!("error authenticating: {}", e);
+    ///             // the client may have been logged out, so connect again to retry
+    ///             return;
+    ///         }
     ///         Err((e, orig_client)) => {
     ///             eprintln!("error authenticating: {}", e);
     ///             // prompt user and try again with orig_client here
@@ -456,22 +543,82 @@ impl<T: Read + Write> Client<T> {
     ///     };
     /// }
     /// ```
-    pub fn authenticate<A: Authenticator>(
+    pub fn authenticate<A: AuthenticatorMut>(
         mut self,
         auth_type: impl AsRef<str>,
-        authenticator: &A,
+        mut authenticator: A,
     ) -> ::std::result::Result<Session<T>, (Error, Client<T>)> {
-        ok_or_unauth_client_err!(
-            self.run_command(&format!("AUTHENTICATE {}", auth_type.as_ref())),
-            self
-        );
+        let mut command = format!("AUTHENTICATE {}", auth_type.as_ref());
+        if authenticator.client_first()
+            && matches!(&self.conn.capabilities, Some(caps) if caps.has_str("SASL-IR"))
+        {
+            // Send the initial response inline, where an empty one is encoded as "=".
+            let initial_response = match authenticator.process(b"") {
+                Ok(initial_response) => initial_response,
+                Err(e) => return Err((Error::Authentication(e), self)),
+            };
+            let initial_response = initial_response.as_ref();
+            if initial_response.is_empty() {
+                command.push_str(" =");
+            } else {
+                command.push(' ');
+                command.push_str(&general_purpose::STANDARD.encode(initial_response));
+            }
+        }
+        ok_or_unauth_client_err!(self.run_command(&command), self);
         self.do_auth_handshake(authenticator)
     }
 
+    /// Authenticate with the strongest [SASL](https://tools.ietf.org/html/rfc4422) mechanism that
+    /// the server advertises as an `AUTH=<mechanism>` capability and that can be used with the
+    /// given [`Credentials`](sasl::Credentials), using the [`sasl`] authenticators.
+    ///
+    /// The capabilities are requested from the server if they are not known yet. If none of the
+    /// advertised mechanisms fit the credentials, this fails with
+    /// [`AuthenticationError::NoMechanism`](crate::error::AuthenticationError::NoMechanism)
+    /// without sending anything else. When SCRAM is used, the server has to prove that it knows
+    /// the password too, otherwise this fails with
+    /// [`AuthenticationError::ServerNotVerified`](crate::error::AuthenticationError::ServerNotVerified),
+    /// after logging out if the server has already accepted the credentials.
+    ///
+    /// See [`Client::authenticate`] for how to retry with the returned `Client` on error.
+    #[cfg(feature = "sasl")]
+    pub fn authenticate_best(
+        mut self,
+        credentials: sasl::Credentials<'_>,
+    ) -> ::std::result::Result<Session<T>, (Error, Client<T>)> {
+        if self.conn.capabilities.is_none() {
+            ok_or_unauth_client_err!(self.capabilities(), self);
+        }
+        let authenticator = match self
+            .conn
+            .capabilities
+            .as_ref()
+            .and_then(|caps| credentials.best_authenticator(caps))
+        {
+            Some(authenticator) => authenticator,
+            None => {
+                return Err((
+                    Error::Authentication(AuthenticationError::NoMechanism),
+                    self,
+                ))
+            }
+        };
+
+        match authenticator {
+            BestAuthenticator::Scram(mut a) => self.authenticate(a.mechanism(), &mut a),
+            BestAuthenticator::CramMd5(a) => self.authenticate(a.mechanism(), &a),
+            BestAuthenticator::Plain(a) => self.authenticate(a.mechanism(), &a),
+            BestAuthenticator::Login(mut a) => self.authenticate(a.mechanism(), &mut a),
+            BestAuthenticator::OAuthBearer(mut a) => self.authenticate(a.mechanism(), &mut a),
+            BestAuthenticator::XOAuth2(mut a) => self.authenticate(a.mechanism(), &mut a),
+        }
+    }
+
     /// This func does the handshake process once the authenticate command is made.
-    fn do_auth_handshake<A: Authenticator>(
+    fn do_auth_handshake<A: AuthenticatorMut>(
         mut self,
-        authenticator: &A,
+        mut authenticator: A,
     ) -> ::std::result::Result<Session<T>, (Error, Client<T>)> {
         // TODO Clean up this code
         loop {
@@ -511,15 +658,48 @@ impl<T: Read + Write> Client<T> {
                     )
                 };
 
-                let raw_response = &authenticator.process(&challenge);
+                let raw_response = match authenticator.process(&challenge) {
+                    Ok(raw_response) => raw_response,
+                    Err(e) => {
+                        // Cancel the exchange, which the server confirms with a tagged BAD.
+                        ok_or_unauth_client_err!(self.write_line(b"*"), self);
+                        match self.read_response() {
+                            Ok(_) | Err(Error::Bad(_)) | Err(Error::No(_)) => {}
+                            Err(err) => return Err((err, self)),
+                        }
+                        return Err((Error::Authentication(e), self));
+                    }
+                };
                 let auth_response = general_purpose::STANDARD.encode(raw_response);
                 ok_or_unauth_client_err!(
                     self.write_line(auth_response.into_bytes().as_slice()),
                     self
                 );
             } else {
-                ok_or_unauth_client_err!(self.read_response_onto(&mut line), self);
-                return Ok(Session::new(self.conn));
+                return match self.read_response_onto(&mut line) {
+                    Ok(ok) => match authenticator.finish(Ok(())) {
+                        Ok(()) => {
+                            let mut session = Session::new(self.conn);
+                            session.cache_capabilities(&line[ok..]);
+                            Ok(session)
+                        }
+                        // The server considers the client logged in, so log out rather than leave
+                        // an authenticated connection behind. The authentication error is the
+                        // one to report, so a failure to log out only shows up in debug mode.
+                        Err(e) => {
+                            match self.run_command_and_check_ok("LOGOUT") {
+                                Ok(()) | Err(Error::Bye(_)) => {}
+                                Err(err) if self.debug => eprintln!("LOGOUT failed: {}", err),
+                                Err(_) => {}
+                            }
+                            Err((Error::Authentication(e), self))
+                        }
+                    },
+                    Err(err) => match authenticator.finish(Err(&err)) {
+                        Ok(()) => Err((err, self)),
+                        Err(e) => Err((Error::Authentication(e), self)),
+                    },
+                };
             }
         }
     }
@@ -527,16 +707,31 @@ impl<T: Read + Write> Client<T> {
 
 impl<T: Read + Write> Session<T> {
     // not public, just to avoid duplicating the channel creation code
-    fn new(conn: Connection<T>) -> Self {
+    fn new(mut conn: Connection<T>) -> Self {
+        // The server may advertise different capabilities once the client is authenticated.
+        conn.capabilities = None;
         Session {
             conn,
             unsolicited_responses: VecDeque::new(),
+            enabled: Default::default(),
+            selected: None,
         }
     }
 
     /// Takes all the unsolicited responses received thus far.
+    ///
+    /// The mailbox names in the responses are converted into UTF-8, like those passed to and
+    /// returned from the other methods of `Session`.
     pub fn take_all_unsolicited(&mut self) -> impl ExactSizeIterator<Item = UnsolicitedResponse> {
-        std::mem::take(&mut self.unsolicited_responses).into_iter()
+        let decode = !self.utf8_accepted();
+        std::mem::take(&mut self.unsolicited_responses)
+            .into_iter()
+            .map(move |mut response| {
+                if decode {
+                    response.decode_utf7();
+                }
+                response
+            })
     }
 
     /// Selects a mailbox
@@ -558,11 +753,15 @@ impl<T: Read + Write> Session<T> {
     /// `EXISTS`, `FETCH`, and `EXPUNGE` responses. You can get them from the
     /// `unsolicited_responses` channel of the [`Session`](struct.Session.html).
     pub fn select(&mut self, mailbox_name: impl AsRef<str>) -> Result<Mailbox> {
-        self.run(&format!(
-            "SELECT {}",
-            validate_str("SELECT", "mailbox", mailbox_name.as_ref())?
-        ))
-        .and_then(|(lines, _)| parse_mailbox(&lines[..], &mut self.unsolicited_responses))
+        let mailbox_name = mailbox_name.as_ref();
+        let result = self
+            .run(&format!(
+                "SELECT {}",
+                validate_str("SELECT", "mailbox", &self.encode_mailbox_name(mailbox_name))?
+            ))
+            .and_then(|(lines, _)| parse_mailbox(&lines[..], &mut self.unsolicited_responses));
+        self.track_selected(mailbox_name, false, result.as_ref());
+        result
     }
 
     /// The `EXAMINE` command is identical to [`Session::select`] and returns the same output;
@@ -570,11 +769,65 @@ impl<T: Read + Write> Session<T> {
     /// of the mailbox, including per-user state, will happen in a mailbox opened with `examine`;
     /// in particular, messagess cannot lose [`Flag::Recent`] in an examined mailbox.
     pub fn examine(&mut self, mailbox_name: impl AsRef<str>) -> Result<Mailbox> {
-        self.run(&format!(
-            "EXAMINE {}",
-            validate_str("EXAMINE", "mailbox", mailbox_name.as_ref())?
-        ))
-        .and_then(|(lines, _)| parse_mailbox(&lines[..], &mut self.unsolicited_responses))
+        let mailbox_name = mailbox_name.as_ref();
+        let result = self
+            .run(&format!(
+                "EXAMINE {}",
+                validate_str(
+                    "EXAMINE",
+                    "mailbox",
+                    &self.encode_mailbox_name(mailbox_name)
+                )?
+            ))
+            .and_then(|(lines, _)| parse_mailbox(&lines[..], &mut self.unsolicited_responses));
+        self.track_selected(mailbox_name, true, result.as_ref());
+        result
+    }
+
+    /// Records the outcome of a `SELECT` (or, if `examine` is set, `EXAMINE`) of `mailbox_name`.
+    pub(crate) fn track_selected(
+        &mut self,
+        mailbox_name: &str,
+        examine: bool,
+        result: std::result::Result<&Mailbox, &Error>,
+    ) {
+        match result {
+            Ok(mailbox) => {
+                self.selected = Some(SelectedMailbox {
+                    name: mailbox_name.to_string(),
+                    is_read_only: examine || mailbox.is_read_only,
+                })
+            }
+            // A failed `SELECT` or `EXAMINE` still deselects the previously selected mailbox.
+            Err(Error::No(_)) => self.selected = None,
+            Err(_) => {}
+        }
+    }
+
+    /// The mailbox that is currently selected, as of the last successful [`Session::select`] or
+    /// [`Session::examine`], or `None` if no mailbox is selected.
+    ///
+    /// A mailbox is deselected by [`Session::close`], [`Session::unselect`], by a failed
+    /// attempt to select another mailbox, and by [`Session::delete`]. [`Session::rename`] updates
+    /// its name.
+    pub fn selected(&self) -> Option<&SelectedMailbox> {
+        self.selected.as_ref()
+    }
+
+    /// Returns true if the mailbox with the given name is the selected one.
+    fn is_selected(&self, mailbox_name: &str) -> bool {
+        match &self.selected {
+            Some(selected) if selected.name.eq_ignore_ascii_case("INBOX") => {
+                mailbox_name.eq_ignore_ascii_case("INBOX")
+            }
+            Some(selected) => selected.name == mailbox_name,
+            None => false,
+        }
+    }
+
+    /// Forgets about the selected mailbox after the server has left the selected state.
+    pub(crate) fn clear_selected(&mut self) {
+        self.selected = None;
     }
 
     /// Fetch retrieves data associated with a set of messages in the mailbox.
@@ -717,11 +970,20 @@ impl<T: Read + Write> Session<T> {
     /// the mailbox UNLESS the new incarnation has a different unique identifier validity value.
     /// See the description of the [`UID`
     /// command](https://tools.ietf.org/html/rfc3501#section-6.4.8) for more detail.
-    pub fn create(&mut self, mailbox_name: impl AsRef<str>) -> Result<()> {
-        self.run_command_and_check_ok(&format!(
+    ///
+    /// If the server supports the [OBJECTID](https://tools.ietf.org/html/rfc8474) extension, the
+    /// unique identifier it assigned to the new mailbox is returned, see
+    /// [`Mailbox::mailbox_id`].
+    pub fn create(&mut self, mailbox_name: impl AsRef<str>) -> Result<Option<String>> {
+        self.run(format!(
             "CREATE {}",
-            validate_str("CREATE", "mailbox", mailbox_name.as_ref())?
+            validate_str(
+                "CREATE",
+                "mailbox",
+                &self.encode_mailbox_name(mailbox_name.as_ref())
+            )?
         ))
+        .and_then(|(lines, _)| parse_create(&lines, &mut self.unsolicited_responses))
     }
 
     /// The [`DELETE` command](https://tools.ietf.org/html/rfc3501#section-6.3.4) permanently
@@ -743,11 +1005,22 @@ impl<T: Read + Write> Session<T> {
     /// incarnation, UNLESS the new incarnation has a different unique identifier validity value.
     /// See the description of the [`UID`
     /// command](https://tools.ietf.org/html/rfc3501#section-6.4.8) for more detail.
+    ///
+    /// If the deleted mailbox is the selected one, [`Session::selected`] returns `None`
+    /// afterwards.
     pub fn delete(&mut self, mailbox_name: impl AsRef<str>) -> Result<()> {
         self.run_command_and_check_ok(&format!(
             "DELETE {}",
-            validate_str("DELETE", "mailbox", mailbox_name.as_ref())?
-        ))
+            validate_str(
+                "DELETE",
+                "mailbox",
+                &self.encode_mailbox_name(mailbox_name.as_ref())
+            )?
+        ))?;
+        if self.is_selected(mailbox_name.as_ref()) {
+            self.selected = None;
+        }
+        Ok(())
     }
 
     /// The [`RENAME` command](https://tools.ietf.org/html/rfc3501#section-6.3.5) changes the name
@@ -775,12 +1048,23 @@ impl<T: Read + Write> Session<T> {
     /// to a new mailbox with the given name, leaving `INBOX` empty.  If the server implementation
     /// supports inferior hierarchical names of `INBOX`, these are unaffected by a rename of
     /// `INBOX`.
+    ///
+    /// If the renamed mailbox is the selected one, [`Session::selected`] returns its new name
+    /// afterwards. The names of selected inferior mailboxes are not updated, since their
+    /// hierarchy delimiter is not known.
     pub fn rename(&mut self, from: impl AsRef<str>, to: impl AsRef<str>) -> Result<()> {
         self.run_command_and_check_ok(&format!(
             "RENAME {} {}",
-            quote!(from.as_ref()),
-            quote!(to.as_ref())
-        ))
+            quote!(self.encode_mailbox_name(from.as_ref())),
+            quote!(self.encode_mailbox_name(to.as_ref()))
+        ))?;
+        // Renaming INBOX moves its messages, but INBOX itself stays selected.
+        if self.is_selected(from.as_ref()) && !from.as_ref().eq_ignore_ascii_case("INBOX") {
+            if let Some(selected) = &mut self.selected {
+                selected.name = to.as_ref().to_string();
+            }
+        }
+        Ok(())
     }
 
     /// The [`SUBSCRIBE` command](https://tools.ietf.org/html/rfc3501#section-6.3.6) adds the
@@ -792,7 +1076,10 @@ impl<T: Read + Write> Session<T> {
     /// However, it will not unilaterally remove an existing mailbox name from the subscription
     /// list even if a mailbox by that name no longer exists.
     pub fn subscribe(&mut self, mailbox: impl AsRef<str>) -> Result<()> {
-        self.run_command_and_check_ok(&format!("SUBSCRIBE {}", quote!(mailbox.as_ref())))
+        self.run_command_and_check_ok(&format!(
+            "SUBSCRIBE {}",
+            quote!(self.encode_mailbox_name(mailbox.as_ref()))
+        ))
     }
 
     /// The [`UNSUBSCRIBE` command](https://tools.ietf.org/html/rfc3501#section-6.3.7) removes the
@@ -800,15 +1087,101 @@ impl<T: Read + Write> Session<T> {
     /// returned by [`Session::lsub`].  This command returns `Ok` only if the unsubscription is
     /// successful.
     pub fn unsubscribe(&mut self, mailbox: impl AsRef<str>) -> Result<()> {
-        self.run_command_and_check_ok(&format!("UNSUBSCRIBE {}", quote!(mailbox.as_ref())))
+        self.run_command_and_check_ok(&format!(
+            "UNSUBSCRIBE {}",
+            quote!(self.encode_mailbox_name(mailbox.as_ref()))
+        ))
     }
 
     /// The [`CAPABILITY` command](https://tools.ietf.org/html/rfc3501#section-6.1.1) requests a
     /// listing of capabilities that the server supports.  The server will include "IMAP4rev1" as
     /// one of the listed capabilities. See [`Capabilities`] for further details.
     pub fn capabilities(&mut self) -> Result<Capabilities> {
-        self.run_command_and_read_response("CAPABILITY")
-            .and_then(|lines| Capabilities::parse(lines, &mut self.unsolicited_responses))
+        let lines = self.run_command_and_read_response("CAPABILITY")?;
+        let capabilities = Capabilities::parse(lines, &mut self.unsolicited_responses)?;
+        self.conn.capabilities = Some(capabilities.clone());
+        Ok(capabilities)
+    }
+
+    /// Check if the server advertises the given capability. The capabilities are only requested
+    /// from the server if they are not known yet since logging in.
+    pub(crate) fn has_capability(&mut self, cap: &str) -> Result<bool> {
+        if self.conn.capabilities.is_none() {
+            self.capabilities()?;
+        }
+        Ok(matches!(&self.conn.capabilities, Some(caps) if caps.has_str(cap)))
+    }
+
+    /// Returns true if the server speaks [IMAP4rev2](https://tools.ietf.org/html/rfc9051) in this
+    /// session, based on the capabilities it last reported (see [`Session::capabilities`]).
+    ///
+    /// The capabilities are not requested here. If the server did not include them in its
+    /// response to logging in, this only returns true once `IMAP4rev2` has been enabled, or once
+    /// [`Session::capabilities`] has been called.
+    ///
+    /// A server that only advertises `IMAP4rev2` always does so. A server that advertises both
+    /// `IMAP4rev1` and `IMAP4rev2` keeps to IMAP4rev1 until the client opts in by enabling
+    /// `IMAP4rev2` with [`Session::enable`]:
+    ///
+    /// ```no_run
+    /// # use imap_proto::types::Capability;
+    /// # fn example(session: &mut imap::Session<std::net::TcpStream>) -> imap::Result<()> {
+    /// session.enable(&[Capability::Atom("IMAP4rev2".into())])?;
+    /// assert!(session.is_imap4rev2());
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// In IMAP4rev2, mailbox names are exchanged as UTF-8 and there is no `\Recent` flag, so
+    /// [`Mailbox::recent`] is always 0. [`Session::search`] and [`Session::uid_search`] accept
+    /// the `ESEARCH` responses that IMAP4rev2 servers send instead of `SEARCH` responses.
+    pub fn is_imap4rev2(&self) -> bool {
+        self.enabled.has_str(IMAP4REV2)
+            || matches!(
+                &self.conn.capabilities,
+                Some(caps) if caps.has_str(IMAP4REV2) && !caps.has_str("IMAP4rev1")
+            )
+    }
+
+    /// Returns true if mailbox names and other strings are exchanged as UTF-8 rather than in
+    /// modified UTF-7, because [`UTF8=ACCEPT`](https://tools.ietf.org/html/rfc6855) has been
+    /// enabled or the session speaks IMAP4rev2.
+    pub(crate) fn utf8_accepted(&self) -> bool {
+        self.enabled.has_str("UTF8=ACCEPT") || self.is_imap4rev2()
+    }
+
+    /// Converts a mailbox name into the form in which it is sent to the server.
+    ///
+    /// Names containing a line break are left as they are, so that argument validation still
+    /// rejects them rather than sending them encoded.
+    pub(crate) fn encode_mailbox_name<'n>(&self, name: &'n str) -> Cow<'n, str> {
+        if self.utf8_accepted() || name.contains(['\r', '\n']) {
+            Cow::Borrowed(name)
+        } else {
+            Cow::Owned(utf7::encode(name))
+        }
+    }
+
+    /// Converts the names returned by the server into UTF-8.
+    pub(crate) fn decode_names(&self, names: &mut Names) {
+        if !self.utf8_accepted() {
+            names.with_names_mut(|names| {
+                for name in names {
+                    name.decode_utf7();
+                }
+            });
+        }
+    }
+
+    /// Converts the names returned by the server for an extended `LIST` command into UTF-8.
+    pub(crate) fn decode_extended_names(&self, names: &mut extensions::list_status::ExtendedNames) {
+        if !self.utf8_accepted() {
+            names.with_extended_names_mut(|names| {
+                for (name, _) in names {
+                    name.decode_utf7();
+                }
+            });
+        }
     }
 
     /// The [`EXPUNGE` command](https://tools.ietf.org/html/rfc3501#section-6.4.3) permanently
@@ -877,8 +1250,13 @@ impl<T: Read + Write> Session<T> {
     /// deleted, a `CLOSE-LOGOUT` or `CLOSE-SELECT` sequence is considerably faster than an
     /// `EXPUNGE-LOGOUT` or `EXPUNGE-SELECT` because no `EXPUNGE` responses (which the client would
     /// probably ignore) are sent.
+    ///
+    /// To leave the selected state without removing any messages, use [`Session::unselect`]
+    /// instead.
     pub fn close(&mut self) -> Result<()> {
-        self.run_command_and_check_ok("CLOSE")
+        self.run_command_and_check_ok("CLOSE")?;
+        self.selected = None;
+        Ok(())
     }
 
     /// The [`STORE` command](https://tools.ietf.org/html/rfc3501#section-6.4.6) alters data
@@ -960,16 +1338,20 @@ impl<T: Read + Write> Session<T> {
     ///
     /// If the `COPY` command is unsuccessful for any reason, the server restores the destination
     /// mailbox to its state before the `COPY` attempt.
+    ///
+    /// If the server supports [`UIDPLUS`](https://tools.ietf.org/html/rfc4315), the returned
+    /// [`Copied`] tells the [`Uid`] of each copy, see [`Copied::uid_map`].
     pub fn copy(
         &mut self,
         sequence_set: impl AsRef<str>,
         mailbox_name: impl AsRef<str>,
-    ) -> Result<()> {
-        self.run_command_and_check_ok(&format!(
+    ) -> Result<Copied> {
+        self.run(format!(
             "COPY {} {}",
             sequence_set.as_ref(),
-            mailbox_name.as_ref()
+            self.encode_mailbox_name(mailbox_name.as_ref())
         ))
+        .and_then(|(lines, _)| parse_copy(&lines, &mut self.unsolicited_responses))
     }
 
     /// Equivalent to [`Session::copy`], except that all identifiers in `sequence_set` are
@@ -978,12 +1360,13 @@ impl<T: Read + Write> Session<T> {
         &mut self,
         uid_set: impl AsRef<str>,
         mailbox_name: impl AsRef<str>,
-    ) -> Result<()> {
-        self.run_command_and_check_ok(&format!(
+    ) -> Result<Copied> {
+        self.run(format!(
             "UID COPY {} {}",
             uid_set.as_ref(),
-            mailbox_name.as_ref()
+            self.encode_mailbox_name(mailbox_name.as_ref())
         ))
+        .and_then(|(lines, _)| parse_copy(&lines, &mut self.unsolicited_responses))
     }
 
     /// The [`MOVE` command](https://tools.ietf.org/html/rfc6851#section-3.1) takes two
@@ -1016,16 +1399,24 @@ impl<T: Read + Write> Session<T> {
     /// orphaned).  The server will generally not leave any message in both mailboxes (it would be
     /// bad for a partial failure to result in a bunch of duplicate messages).  This is true even
     /// if the server returns with [`Error::No`].
+    ///
+    /// As for [`Session::copy`], the returned [`Copied`] tells the new [`Uid`] of each message
+    /// if the server supports [`UIDPLUS`](https://tools.ietf.org/html/rfc4315).
     pub fn mv(
         &mut self,
         sequence_set: impl AsRef<str>,
         mailbox_name: impl AsRef<str>,
-    ) -> Result<()> {
-        self.run_command_and_check_ok(&format!(
+    ) -> Result<Copied> {
+        self.run(format!(
             "MOVE {} {}",
             sequence_set.as_ref(),
-            validate_str("MOVE", "mailbox", mailbox_name.as_ref())?
+            validate_str(
+                "MOVE",
+                "mailbox",
+                &self.encode_mailbox_name(mailbox_name.as_ref())
+            )?
         ))
+        .and_then(|(lines, _)| parse_copy(&lines, &mut self.unsolicited_responses))
     }
 
     /// Equivalent to [`Session::mv`], except that all identifiers in `sequence_set` are
@@ -1036,12 +1427,17 @@ impl<T: Read + Write> Session<T> {
         &mut self,
         uid_set: impl AsRef<str>,
         mailbox_name: impl AsRef<str>,
-    ) -> Result<()> {
-        self.run_command_and_check_ok(&format!(
+    ) -> Result<Copied> {
+        self.run(format!(
             "UID MOVE {} {}",
             uid_set.as_ref(),
-            validate_str("UID MOVE", "mailbox", mailbox_name.as_ref())?
+            validate_str(
+                "UID MOVE",
+                "mailbox",
+                &self.encode_mailbox_name(mailbox_name.as_ref())
+            )?
         ))
+        .and_then(|(lines, _)| parse_copy(&lines, &mut self.unsolicited_responses))
     }
 
     /// The [`LIST` command](https://tools.ietf.org/html/rfc3501#section-6.3.8) returns a subset of
@@ -1080,12 +1476,15 @@ impl<T: Read + Write> Session<T> {
         reference_name: Option<&str>,
         mailbox_pattern: Option<&str>,
     ) -> Result<Names> {
-        self.run_command_and_read_response(&format!(
-            "LIST {} {}",
-            quote!(reference_name.unwrap_or("")),
-            mailbox_pattern.unwrap_or("\"\"")
-        ))
-        .and_then(|lines| Names::parse(lines, &mut self.unsolicited_responses))
+        let mut names = self
+            .run_command_and_read_response(&format!(
+                "LIST {} {}",
+                quote!(self.encode_mailbox_name(reference_name.unwrap_or(""))),
+                self.encode_mailbox_name(mailbox_pattern.unwrap_or("\"\""))
+            ))
+            .and_then(|lines| Names::parse(lines, &mut self.unsolicited_responses))?;
+        self.decode_names(&mut names);
+        Ok(names)
     }
 
     /// The [`LSUB` command](https://tools.ietf.org/html/rfc3501#section-6.3.9) returns a subset of
@@ -1108,12 +1507,15 @@ impl<T: Read + Write> Session<T> {
         reference_name: Option<&str>,
         mailbox_pattern: Option<&str>,
     ) -> Result<Names> {
-        self.run_command_and_read_response(&format!(
-            "LSUB {} {}",
-            quote!(reference_name.unwrap_or("")),
-            mailbox_pattern.unwrap_or("")
-        ))
-        .and_then(|lines| Names::parse(lines, &mut self.unsolicited_responses))
+        let mut names = self
+            .run_command_and_read_response(&format!(
+                "LSUB {} {}",
+                quote!(self.encode_mailbox_name(reference_name.unwrap_or(""))),
+                self.encode_mailbox_name(mailbox_pattern.unwrap_or(""))
+            ))
+            .and_then(|lines| Names::parse(lines, &mut self.unsolicited_responses))?;
+        self.decode_names(&mut names);
+        Ok(names)
     }
 
     /// The [`STATUS` command](https://tools.ietf.org/html/rfc3501#section-6.3.10) requests the
@@ -1149,6 +1551,11 @@ impl<T: Read + Write> Session<T> {
     ///  - `UIDVALIDITY`: The unique identifier validity value of the mailbox (see [`Uid`]).
     ///  - `UNSEEN`: The number of messages which do not have [`Flag::Seen`] set.
     ///  - `HIGHESTMODSEQ`: The highest mod sequence of the mailbox, counting modifications made to it.
+    ///  - `SIZE`: The total size of the mailbox in octets (requires the `STATUS=SIZE` capability).
+    ///  - `DELETED`: The number of messages with [`Flag::Deleted`] set (requires the `QUOTA`
+    ///    capability, or IMAP4rev2).
+    ///  - `DELETED-STORAGE`: The amount of storage that would be freed by expunging, in units of
+    ///    1024 octets (requires the `QUOTA=RES-STORAGE` capability).
     ///
     /// `data_items` is a space-separated list enclosed in parentheses.
     pub fn status(
@@ -1156,13 +1563,13 @@ impl<T: Read + Write> Session<T> {
         mailbox_name: impl AsRef<str>,
         data_items: impl AsRef<str>,
     ) -> Result<Mailbox> {
-        let mailbox_name = mailbox_name.as_ref();
+        let mailbox_name = self.encode_mailbox_name(mailbox_name.as_ref());
         self.run_command_and_read_response(&format!(
             "STATUS {} {}",
-            validate_str("STATUS", "mailbox", mailbox_name)?,
+            validate_str("STATUS", "mailbox", &mailbox_name)?,
             data_items.as_ref()
         ))
-        .and_then(|lines| parse_status(&lines[..], mailbox_name, &mut self.unsolicited_responses))
+        .and_then(|lines| parse_status(&lines[..], &mailbox_name, &mut self.unsolicited_responses))
     }
 
     /// This method returns a handle that lets you use the [`IDLE`
@@ -1214,6 +1621,7 @@ impl<T: Read + Write> Session<T> {
             mailbox,
             flags: Vec::new(),
             date: None,
+            binary: false,
         }
     }
 
@@ -1261,8 +1669,14 @@ impl<T: Read + Write> Session<T> {
     ///
     ///  - `BEFORE <date>`: Messages whose internal date (disregarding time and timezone) is earlier than the specified date.
     ///  - `SINCE <date>`: Messages whose internal date (disregarding time and timezone) is within or later than the specified date.
+    ///
+    /// Quoted strings in `query` that cannot be sent as such, for example because they contain
+    /// non-ASCII text, are sent as literals instead. Remember to specify `CHARSET UTF-8` first in
+    /// that case.
     pub fn search(&mut self, query: impl AsRef<str>) -> Result<HashSet<Seq>> {
-        self.run_command_and_read_response(&format!("SEARCH {}", query.as_ref()))
+        let mut command = Command::new("SEARCH ");
+        command.push_criteria(query.as_ref());
+        self.execute_and_read_response(&command)
             .and_then(|lines| parse_id_set(&lines, &mut self.unsolicited_responses))
     }
 
@@ -1270,7 +1684,9 @@ impl<T: Read + Write> Session<T> {
     /// are [`Uid`] instead of [`Seq`]. See also the [`UID`
     /// command](https://tools.ietf.org/html/rfc3501#section-6.4.8).
     pub fn uid_search(&mut self, query: impl AsRef<str>) -> Result<HashSet<Uid>> {
-        self.run_command_and_read_response(&format!("UID SEARCH {}", query.as_ref()))
+        let mut command = Command::new("UID SEARCH ");
+        command.push_criteria(query.as_ref());
+        self.execute_and_read_response(&command)
             .and_then(|lines| parse_id_set(&lines, &mut self.unsolicited_responses))
     }
 
@@ -1334,7 +1750,11 @@ impl<T: Read + Write> Session<T> {
 
         self.run_command_and_check_ok(&format!(
             "SETACL {} {} {}{}",
-            validate_str("SETACL", "mailbox", mailbox_name.as_ref())?,
+            validate_str(
+                "SETACL",
+                "mailbox",
+                &self.encode_mailbox_name(mailbox_name.as_ref())
+            )?,
             validate_str("SETACL", "identifier", identifier.as_ref())?,
             mod_str,
             rights,
@@ -1355,7 +1775,11 @@ impl<T: Read + Write> Session<T> {
     ) -> Result<()> {
         self.run_command_and_check_ok(&format!(
             "DELETEACL {} {}",
-            validate_str("DELETEACL", "mailbox", mailbox_name.as_ref())?,
+            validate_str(
+                "DELETEACL",
+                "mailbox",
+                &self.encode_mailbox_name(mailbox_name.as_ref())
+            )?,
             validate_str("DELETEACL", "identifier", identifier.as_ref())?,
         ))
     }
@@ -1370,7 +1794,11 @@ impl<T: Read + Write> Session<T> {
     pub fn get_acl(&mut self, mailbox_name: impl AsRef<str>) -> Result<AclResponse> {
         self.run_command_and_read_response(&format!(
             "GETACL {}",
-            validate_str("GETACL", "mailbox", mailbox_name.as_ref())?
+            validate_str(
+                "GETACL",
+                "mailbox",
+                &self.encode_mailbox_name(mailbox_name.as_ref())
+            )?
         ))
         .and_then(|lines| AclResponse::parse(lines, &mut self.unsolicited_responses))
     }
@@ -1390,7 +1818,11 @@ impl<T: Read + Write> Session<T> {
     ) -> Result<ListRightsResponse> {
         self.run_command_and_read_response(&format!(
             "LISTRIGHTS {} {}",
-            validate_str("LISTRIGHTS", "mailbox", mailbox_name.as_ref())?,
+            validate_str(
+                "LISTRIGHTS",
+                "mailbox",
+                &self.encode_mailbox_name(mailbox_name.as_ref())
+            )?,
             validate_str("LISTRIGHTS", "identifier", identifier.as_ref())?
         ))
         .and_then(|lines| ListRightsResponse::parse(lines, &mut self.unsolicited_responses))
@@ -1405,7 +1837,11 @@ impl<T: Read + Write> Session<T> {
     pub fn my_rights(&mut self, mailbox_name: impl AsRef<str>) -> Result<MyRightsResponse> {
         self.run_command_and_read_response(&format!(
             "MYRIGHTS {}",
-            validate_str("MYRIGHTS", "mailbox", mailbox_name.as_ref())?,
+            validate_str(
+                "MYRIGHTS",
+                "mailbox",
+                &self.encode_mailbox_name(mailbox_name.as_ref())
+            )?,
         ))
         .and_then(|lines| MyRightsResponse::parse(lines, &mut self.unsolicited_responses))
     }
@@ -1416,6 +1852,10 @@ impl<T: Read + Write> Session<T> {
     /// are discarded.
     ///
     /// Returns the updated quota.
+    ///
+    /// Servers that follow [RFC 9208](https://datatracker.ietf.org/doc/html/rfc9208) only support
+    /// this command if they advertise the `QUOTASET` capability, see
+    /// [`Capabilities::has_quota_set`].
     pub fn set_quota(
         &mut self,
         quota_root: impl AsRef<str>,
@@ -1447,7 +1887,11 @@ impl<T: Read + Write> Session<T> {
     pub fn get_quota_root(&mut self, mailbox_name: impl AsRef<str>) -> Result<QuotaRootResponse> {
         self.run_command_and_read_response(&format!(
             "GETQUOTAROOT {}",
-            validate_str("GETQUOTAROOT", "mailbox", mailbox_name.as_ref())?
+            validate_str(
+                "GETQUOTAROOT",
+                "mailbox",
+                &self.encode_mailbox_name(mailbox_name.as_ref())
+            )?
         ))
         .and_then(|lines| QuotaRootResponse::parse(lines, &mut self.unsolicited_responses))
     }
@@ -1503,6 +1947,7 @@ impl<T: Read + Write> Connection<T> {
         let mut v = Vec::new();
         self.readline(&mut v)?;
         self.greeting_read = true;
+        self.cache_capabilities(&v);
 
         Ok(v)
     }
@@ -1521,12 +1966,97 @@ impl<T: Read + Write> Connection<T> {
         result
     }
 
-    fn run_command_and_read_response(&mut self, untagged_command: &str) -> Result<Vec<u8>> {
+    pub(crate) fn run_command_and_read_response(
+        &mut self,
+        untagged_command: &str,
+    ) -> Result<Vec<u8>> {
         let (mut data, ok) = self.run(untagged_command)?;
         data.truncate(ok);
         Ok(data)
     }
 
+    /// Run a command that may contain literals, and read back its response.
+    ///
+    /// Literals are sent without waiting for the server's continuation if the server is known to
+    /// accept non-synchronizing literals of that size.
+    pub(crate) fn execute(&mut self, command: &Command<'_>) -> Result<(Vec<u8>, usize)> {
+        let non_sync_limit = command::non_sync_literal_limit(self.capabilities.as_ref());
+        self.tag += 1;
+        let mut data = Vec::new();
+        let mut sent_any = false;
+        let mut line = format!("{}{} ", TAG_PREFIX, self.tag);
+        for (text, literal) in command.parts() {
+            line.push_str(text);
+            let literal = match literal {
+                Some(literal) => literal,
+                None => break,
+            };
+            let sync = literal.data.len() > non_sync_limit;
+            line.push_str(&format!(
+                "{}{{{}{}}}",
+                if literal.binary { "~" } else { "" },
+                literal.data.len(),
+                if sync { "" } else { "+" }
+            ));
+            let written = self.write_line(line.as_bytes());
+            if written.is_err() && !sent_any {
+                // nothing reached the server, so the tag can be reused
+                self.tag -= 1;
+            }
+            written?;
+            sent_any = true;
+            line.clear();
+
+            if sync {
+                loop {
+                    let start = data.len();
+                    self.readline(&mut data)?;
+                    if data[start..].starts_with(b"+") {
+                        data.truncate(start);
+                        break;
+                    }
+                    if !data[start..].starts_with(b"* ") {
+                        // The server rejected the command before getting the literal.
+                        let mut response = data.split_off(start);
+                        let ok = self.read_response_onto(&mut response)?;
+                        data.append(&mut response);
+                        return Ok((data, start + ok));
+                    }
+                }
+            }
+            self.stream.write_all(&literal.data)?;
+            if self.debug {
+                eprintln!("C: <literal of {} bytes>", literal.data.len());
+            }
+        }
+        let written = self.write_line(line.as_bytes());
+        if written.is_err() && !sent_any {
+            self.tag -= 1;
+        }
+        written?;
+
+        let mut response = Vec::new();
+        let ok = self.read_response_onto(&mut response)?;
+        let start = data.len();
+        data.append(&mut response);
+        Ok((data, start + ok))
+    }
+
+    /// Like [`Connection::execute`], but without the final [`Response::Done`].
+    pub(crate) fn execute_and_read_response(&mut self, command: &Command<'_>) -> Result<Vec<u8>> {
+        let (mut data, ok) = self.execute(command)?;
+        data.truncate(ok);
+        Ok(data)
+    }
+
+    /// Remember the capabilities the server listed in the `[CAPABILITY ...]` response code of the
+    /// given response, if any.
+    pub(crate) fn cache_capabilities(&mut self, response: &[u8]) {
+        if let Some(capabilities) = Capabilities::parse_response_code(response.to_vec()) {
+            self.capabilities = Some(capabilities);
+        }
+    }
+
     fn run(&mut self, untagged_command: &str) -> Result<(Vec<u8>, usize)> {
         self.run_command(untagged_command)?;
         self.read_response()
@@ -1544,6 +2074,8 @@ impl<T: Read + Write> Connection<T> {
     pub(crate) fn read_response_onto(&mut self, data: &mut Vec<u8>) -> Result<usize> {
         let mut continue_from = None;
         let mut try_first = !data.is_empty();
+        let mut segment_start = 0;
+        let mut pending_literal = None;
         let match_tag = format!("{}{}", TAG_PREFIX, self.tag);
         loop {
             let line_start = if try_first {
@@ -1551,6 +2083,7 @@ impl<T: Read + Write> Connection<T> {
                 0
             } else {
                 let start_new = data.len();
+                segment_start = start_new;
                 self.readline(data)?;
                 continue_from.take().unwrap_or(start_new)
             };
@@ -1596,10 +2129,28 @@ impl<T: Read + Write> Connection<T> {
                         continue_from = Some(line_start);
                         None
                     }
+                    // imap-proto does not know about the responses of every extension. Rather
+                    // than failing the whole command, unknown untagged responses are kept in the
+                    // returned lines and left to the command-specific parsers, which either
+                    // understand them or report them as `ParseError::Invalid`. Any literal they
+                    // announce still has to be read as part of the response.
+                    Err(_) if line.starts_with(b"* ") => {
+                        pending_literal = trailing_literal_len(&data[segment_start..]);
+                        if pending_literal.is_some() {
+                            continue_from = Some(line_start);
+                        }
+                        None
+                    }
                     _ => Some(Err((Status::Bye, None, None))),
                 }
             };
 
+            if let Some(len) = pending_literal.take() {
+                let start = data.len();
+                data.resize(start + len, 0);
+                self.stream.read_exact(&mut data[start..])?;
+            }
+
             match break_with {
                 Some(Ok(_)) => {
                     break Ok(line_start);
@@ -1741,6 +2292,7 @@ pub(crate) mod testutils {
 
 #[cfg(test)]
 mod tests {
+    use super::super::authenticator::Authenticator;
     use super::super::mock_stream::MockStream;
     use super::*;
     use imap_proto::types::Capability;
@@ -1775,6 +2327,71 @@ mod tests {
         session.read_response().unwrap();
     }
 
+    #[test]
+    fn trailing_literal_len_test() {
+        assert_eq!(trailing_literal_len(b"* X-FOO {5}\r\n"), Some(5));
+        assert_eq!(trailing_literal_len(b"* X-FOO {12+}\r\n"), Some(12));
+        assert_eq!(trailing_literal_len(b"* X-FOO ~{3}\r\n"), Some(3));
+        assert_eq!(trailing_literal_len(b"* X-FOO {}\r\n"), None);
+        assert_eq!(trailing_literal_len(b"* X-FOO {a}\r\n"), None);
+        assert_eq!(trailing_literal_len(b"* X-FOO {5}"), None);
+        assert_eq!(trailing_literal_len(b"* X-FOO bar\r\n"), None);
+    }
+
+    #[test]
+    fn read_response_unknown_untagged() {
+        let response = "a0 OK Logged in.\r\n\
+                        * X-UNKNOWN foo (bar)\r\n\
+                        a0 OK NOOP completed\r\n";
+        let mock_stream = MockStream::new(response.as_bytes().to_vec());
+        let mut session = mock_session!(mock_stream);
+        session.read_response().unwrap();
+        let (lines, ok) = session.read_response().unwrap();
+        assert_eq!(&lines[..ok], b"* X-UNKNOWN foo (bar)\r\n".as_slice());
+    }
+
+    #[test]
+    fn read_response_unknown_with_literal8() {
+        let response = "a0 OK Logged in.\r\n\
+                        * X-UNKNOWN ~{3}\r\n\r\n\0 {2+}\r\nab\r\n\
+                        a0 OK X-UNKNOWN completed\r\n";
+        let mock_stream = MockStream::new(response.as_bytes().to_vec());
+        let mut session = mock_session!(mock_stream);
+        session.read_response().unwrap();
+        let (lines, ok) = session.read_response().unwrap();
+        assert_eq!(
+            &lines[..ok],
+            b"* X-UNKNOWN ~{3}\r\n\r\n\0 {2+}\r\nab\r\n".as_slice()
+        );
+    }
+
+    #[test]
+    fn read_response_invalid_line() {
+        // Only untagged responses are skipped when imap-proto does not understand them.
+        let response = "a0 OK Logged in.\r\n\
+                        X-GARBAGE\r\n\
+                        a0 OK NOOP completed\r\n";
+        let mock_stream = MockStream::new(response.as_bytes().to_vec());
+        let mut session = mock_session!(mock_stream);
+        session.read_response().unwrap();
+        assert!(matches!(session.read_response(), Err(Error::Bye(_))));
+    }
+
+    #[test]
+    fn read_response_unknown_with_literal() {
+        let response = "a0 OK Logged in.\r\n\
+                        * X-UNKNOWN (\"key\" {5}\r\na\r\nb) FOO\r\n\
+                        a0 OK X-UNKNOWN completed\r\n";
+        let mock_stream = MockStream::new(response.as_bytes().to_vec());
+        let mut session = mock_session!(mock_stream);
+        session.read_response().unwrap();
+        let (lines, ok) = session.read_response().unwrap();
+        assert_eq!(
+            &lines[..ok],
+            b"* X-UNKNOWN (\"key\" {5}\r\na\r\nb) FOO\r\n".as_slice()
+        );
+    }
+
     #[test]
     fn read_greeting() {
         let greeting = "* OK Dovecot ready.\r\n";
@@ -1865,6 +2482,57 @@ mod tests {
         );
     }
 
+    #[test]
+    fn authenticate_sasl_ir() {
+        struct Anonymous(&'static [u8]);
+        impl Authenticator for Anonymous {
+            type Response = &'static [u8];
+            fn process(&self, challenge: &[u8]) -> Self::Response {
+                assert!(challenge.is_empty(), "Invalid authenticate challenge");
+                self.0
+            }
+            fn client_first(&self) -> bool {
+                true
+            }
+        }
+
+        let response = b"a1 OK Logged in\r\n".to_vec();
+        let mut client = Client::new(MockStream::new(response));
+        client.cache_capabilities(b"* OK [CAPABILITY IMAP4rev1 SASL-IR AUTH=ANONYMOUS] ready\r\n");
+        let session = client
+            .authenticate("ANONYMOUS", &Anonymous(b"foo"))
+            .unwrap();
+        assert_eq!(
+            session.stream.get_ref().written_buf,
+            b"a1 AUTHENTICATE ANONYMOUS Zm9v\r\n".to_vec(),
+            "Invalid authenticate command"
+        );
+
+        // An empty initial response is sent as "=".
+        let response = b"a1 OK Logged in\r\n".to_vec();
+        let mut client = Client::new(MockStream::new(response));
+        client.cache_capabilities(b"* OK [CAPABILITY IMAP4rev1 SASL-IR AUTH=ANONYMOUS] ready\r\n");
+        let session = client.authenticate("ANONYMOUS", &Anonymous(b"")).unwrap();
+        assert_eq!(
+            session.stream.get_ref().written_buf,
+            b"a1 AUTHENTICATE ANONYMOUS =\r\n".to_vec(),
+            "Invalid authenticate command"
+        );
+
+        // Without SASL-IR, the client waits for the server's empty challenge.
+        let response = b"+ \r\na1 OK Logged in\r\n".to_vec();
+        let mut client = Client::new(MockStream::new(response));
+        client.cache_capabilities(b"* OK [CAPABILITY IMAP4rev1 AUTH=ANONYMOUS] ready\r\n");
+        let session = client
+            .authenticate("ANONYMOUS", &Anonymous(b"foo"))
+            .unwrap();
+        assert_eq!(
+            session.stream.get_ref().written_buf,
+            b"a1 AUTHENTICATE ANONYMOUS\r\nZm9v\r\n".to_vec(),
+            "Invalid authenticate command"
+        );
+    }
+
     #[test]
     fn pre_login_capability() {
         let response = b"* CAPABILITY IMAP4rev1 STARTTLS AUTH=GSSAPI LOGINDISABLED\r\n\
@@ -1929,6 +2597,159 @@ mod tests {
         );
     }
 
+    #[test]
+    fn login_literal() {
+        let response = b"+ Ready for literal data\r\n\
+            a1 OK Logged in\r\n"
+            .to_vec();
+        let mock_stream = MockStream::new(response);
+        let client = Client::new(mock_stream);
+        let session = client.login("username", "pässword").unwrap();
+        assert_eq!(
+            session.stream.get_ref().written_buf,
+            "a1 LOGIN \"username\" {9}\r\npässword\r\n".as_bytes()
+        );
+    }
+
+    #[test]
+    fn login_literal_plus_from_greeting() {
+        let response = b"* OK [CAPABILITY IMAP4rev1 LITERAL+] Ready\r\n\
+            a1 OK Logged in\r\n"
+            .to_vec();
+        let mock_stream = MockStream::new(response);
+        let mut client = Client::new(mock_stream);
+        client.read_greeting().unwrap();
+        let session = client.login("username", "pässword").unwrap();
+        assert_eq!(
+            session.stream.get_ref().written_buf,
+            "a1 LOGIN \"username\" {9+}\r\npässword\r\n".as_bytes()
+        );
+    }
+
+    #[test]
+    fn append() {
+        let response = b"a1 OK Logged in\r\n\
+            + Ready for literal data\r\n\
+            a2 OK [APPENDUID 1725 3] APPEND completed\r\n"
+            .to_vec();
+        let mock_stream = MockStream::new(response);
+        let client = Client::new(mock_stream);
+        let mut session = client.login("username", "password").unwrap();
+        let appended = session
+            .append("INBOX", b"hello")
+            .flag(Flag::Seen)
+            .finish()
+            .unwrap();
+        assert!(session
+            .stream
+            .get_ref()
+            .written_buf
+            .ends_with(b"a2 APPEND \"INBOX\" (\\Seen) {5}\r\nhello\r\n"));
+        assert_eq!(appended.uid_validity, Some(1725));
+    }
+
+    #[test]
+    fn append_rejected() {
+        let response = b"a1 OK Logged in\r\n\
+            a2 NO [TRYCREATE] No such mailbox\r\n"
+            .to_vec();
+        let mock_stream = MockStream::new(response);
+        let client = Client::new(mock_stream);
+        let mut session = client.login("username", "password").unwrap();
+        match session.append("Missing", b"hello").finish() {
+            Err(Error::No(no)) => assert_eq!(no.code, Some(imap_proto::ResponseCode::TryCreate)),
+            other => panic!("unexpected result: {:?}", other.map(|_| ())),
+        }
+        assert!(session
+            .stream
+            .get_ref()
+            .written_buf
+            .ends_with(b"a2 APPEND \"Missing\" () {5}\r\n"));
+    }
+
+    #[test]
+    fn append_literal_plus() {
+        let response = b"a1 OK [CAPABILITY IMAP4rev1 LITERAL+] Logged in\r\n\
+            a2 OK APPEND completed\r\n"
+            .to_vec();
+        let mock_stream = MockStream::new(response);
+        let client = Client::new(mock_stream);
+        let mut session = client.login("username", "password").unwrap();
+        session.append("INBOX", b"hello").finish().unwrap();
+        assert!(session
+            .stream
+            .get_ref()
+            .written_buf
+            .ends_with(b"a2 APPEND \"INBOX\" () {5+}\r\nhello\r\n"));
+    }
+
+    #[test]
+    fn append_binary() {
+        let response = b"a1 OK [CAPABILITY IMAP4rev1 BINARY] Logged in\r\n\
+            + Ready for literal data\r\n\
+            a2 OK APPEND completed\r\n"
+            .to_vec();
+        let mock_stream = MockStream::new(response);
+        let client = Client::new(mock_stream);
+        let mut session = client.login("username", "password").unwrap();
+        session
+            .append("INBOX", b"he\0lo")
+            .binary()
+            .finish()
+            .unwrap();
+        assert!(session
+            .stream
+            .get_ref()
+            .written_buf
+            .ends_with(b"a2 APPEND \"INBOX\" () ~{5}\r\nhe\0lo\r\n"));
+    }
+
+    #[test]
+    fn append_literal_minus() {
+        let response = b"a1 OK [CAPABILITY IMAP4rev1 LITERAL-] Logged in\r\n\
+            a2 OK APPEND completed\r\n\
+            + Ready for literal data\r\n\
+            a3 OK APPEND completed\r\n"
+            .to_vec();
+        let mock_stream = MockStream::new(response);
+        let client = Client::new(mock_stream);
+        let mut session = client.login("username", "password").unwrap();
+        session.append("INBOX", b"hello").finish().unwrap();
+        assert!(session
+            .stream
+            .get_ref()
+            .written_buf
+            .ends_with(b"a2 APPEND \"INBOX\" () {5+}\r\nhello\r\n"));
+
+        // Larger literals still need to be synchronized.
+        let large = vec![b'x'; 4097];
+        session.append("INBOX", &large).finish().unwrap();
+        let mut expected = b"a3 APPEND \"INBOX\" () {4097}\r\n".to_vec();
+        expected.extend(&large);
+        expected.extend(b"\r\n");
+        assert!(session.stream.get_ref().written_buf.ends_with(&expected));
+    }
+
+    #[test]
+    fn search_literal_plus() {
+        let response = b"a1 OK [CAPABILITY IMAP4rev1 LITERAL+] Logged in\r\n\
+            * SEARCH 2 3\r\n\
+            a2 OK SEARCH completed\r\n"
+            .to_vec();
+        let mock_stream = MockStream::new(response);
+        let client = Client::new(mock_stream);
+        let mut session = client.login("username", "password").unwrap();
+        let ids = session
+            .search("CHARSET UTF-8 SUBJECT \"café\" UNSEEN")
+            .unwrap();
+        assert!(session
+            .stream
+            .get_ref()
+            .written_buf
+            .ends_with("a2 SEARCH CHARSET UTF-8 SUBJECT {5+}\r\ncafé UNSEEN\r\n".as_bytes()));
+        assert_eq!(ids, [2, 3].iter().cloned().collect());
+    }
+
     #[test]
     fn logout() {
         let response = b"a1 OK Logout completed.\r\n".to_vec();
@@ -2083,6 +2904,10 @@ mod tests {
             uid_next: Some(2),
             uid_validity: Some(1257842737),
             highest_mod_seq: None,
+            size: None,
+            deleted: None,
+            deleted_storage: None,
+            mailbox_id: None,
             is_read_only: true,
         };
         let mailbox_name = "INBOX";
@@ -2141,6 +2966,10 @@ mod tests {
             uid_next: Some(2),
             uid_validity: Some(1257842737),
             highest_mod_seq: None,
+            size: None,
+            deleted: None,
+            deleted_storage: None,
+            mailbox_id: None,
             is_read_only: true,
         };
         let mailbox_name = "INBOX";
@@ -2165,6 +2994,138 @@ mod tests {
         )
     }
 
+    #[test]
+    fn select_utf7() {
+        let response = b"* 1 EXISTS\r\n\
+            a1 OK Select completed.\r\n"
+            .to_vec();
+        let mut session = mock_session!(MockStream::new(response));
+        session.select("Entwürfe").unwrap();
+        assert_eq!(
+            session.stream.get_ref().written_buf,
+            b"a1 SELECT \"Entw&APw-rfe\"\r\n"
+        );
+        assert_eq!(session.selected().unwrap().name, "Entwürfe");
+    }
+
+    #[test]
+    fn utf8_accept_mailbox_names() {
+        let response = "* ENABLED UTF8=ACCEPT\r\n\
+            a1 OK ENABLE completed\r\n\
+            * LIST () \"/\" {9}\r\nEntwürfe\r\n\
+            a2 OK LIST completed\r\n\
+            a3 OK RENAME completed\r\n"
+            .as_bytes()
+            .to_vec();
+        let mut session = mock_session!(MockStream::new(response));
+        session
+            .enable(&[Capability::Atom(Cow::Borrowed("UTF8=ACCEPT"))])
+            .unwrap();
+        let names = session.list(None, Some("Entw*")).unwrap();
+        assert_eq!(names.get(0).unwrap().name(), "Entwürfe");
+        session.rename("Entwürfe", "R&D").unwrap();
+        assert!(session
+            .stream
+            .get_ref()
+            .written_buf
+            .ends_with("a2 LIST \"\" Entw*\r\na3 RENAME \"Entwürfe\" \"R&D\"\r\n".as_bytes()));
+    }
+
+    #[test]
+    fn list_utf7() {
+        let response = b"* LIST (\\HasNoChildren) \"/\" \"Entw&APw-rfe\"\r\n\
+            * LIST (\\HasNoChildren) \"/\" \"R&-D\"\r\n\
+            * LIST (\\HasNoChildren) \"/\" \"Drafts & Templates\"\r\n\
+            * LIST () \"/\" \"Entw&APw-rfe 2\" (\"OLDNAME\" (\"Entw&APw-rfe\"))\r\n\
+            a1 OK LIST completed\r\n"
+            .to_vec();
+        let mut session = mock_session!(MockStream::new(response));
+        let names = session.list(Some("Bücher"), Some("*")).unwrap();
+        assert_eq!(
+            session.stream.get_ref().written_buf,
+            b"a1 LIST \"B&APw-cher\" *\r\n"
+        );
+        let renamed = names.get(3).unwrap();
+        assert_eq!(renamed.name(), "Entwürfe 2");
+        assert_eq!(renamed.old_name(), Some("Entwürfe"));
+        let names: Vec<_> = names.iter().map(|n| n.name()).collect();
+        assert_eq!(
+            names,
+            ["Entwürfe", "R&D", "Drafts & Templates", "Entwürfe 2"]
+        );
+    }
+
+    #[test]
+    fn selected() {
+        let response = b"* 1 EXISTS\r\n\
+            a1 OK [READ-WRITE] Select completed.\r\n\
+            * 2 EXISTS\r\n\
+            a2 OK [READ-ONLY] Examine completed.\r\n\
+            a3 NO Mailbox doesn't exist\r\n\
+            * 1 EXISTS\r\n\
+            a4 OK [READ-WRITE] Select completed.\r\n\
+            a5 OK CLOSE completed\r\n"
+            .to_vec();
+        let mock_stream = MockStream::new(response);
+        let mut session = mock_session!(mock_stream);
+        assert_eq!(session.selected(), None);
+
+        session.select("INBOX").unwrap();
+        assert_eq!(
+            session.selected(),
+            Some(&SelectedMailbox {
+                name: "INBOX".to_string(),
+                is_read_only: false,
+            })
+        );
+
+        session.examine("Archive").unwrap();
+        assert_eq!(
+            session.selected(),
+            Some(&SelectedMailbox {
+                name: "Archive".to_string(),
+                is_read_only: true,
+            })
+        );
+
+        session.select("Missing").unwrap_err();
+        assert_eq!(session.selected(), None);
+
+        session.select("INBOX").unwrap();
+        session.close().unwrap();
+        assert_eq!(session.selected(), None);
+    }
+
+    #[test]
+    fn selected_rename_delete() {
+        let response = b"a1 OK [READ-WRITE] Select completed.\r\n\
+            a2 OK RENAME completed\r\n\
+            a3 OK RENAME completed\r\n\
+            a4 OK DELETE completed\r\n\
+            a5 OK DELETE completed\r\n\
+            a6 OK [READ-WRITE] Select completed.\r\n\
+            a7 OK RENAME completed\r\n"
+            .to_vec();
+        let mock_stream = MockStream::new(response);
+        let mut session = mock_session!(mock_stream);
+
+        session.select("Drafts").unwrap();
+        session.rename("Drafts", "Entwürfe").unwrap();
+        assert_eq!(session.selected().unwrap().name, "Entwürfe");
+        session.rename("Sent", "Gesendet").unwrap();
+        assert_eq!(session.selected().unwrap().name, "Entwürfe");
+
+        session.delete("Gesendet").unwrap();
+        assert_eq!(session.selected().unwrap().name, "Entwürfe");
+        session.delete("Entwürfe").unwrap();
+        assert_eq!(session.selected(), None);
+
+        // Renaming INBOX moves its messages to a new mailbox, but leaves INBOX selected.
+        session.select("INBOX").unwrap();
+        session.rename("inbox", "Old Mail").unwrap();
+        assert_eq!(session.selected().unwrap().name, "INBOX");
+    }
+
     #[test]
     fn search() {
         let response = b"* SEARCH 1 2 3 4 5\r\n\
@@ -2197,6 +3158,69 @@ mod tests {
         assert_eq!(ids, [1, 2, 3, 4, 5].iter().cloned().collect());
     }
 
+    #[test]
+    fn imap4rev2_enable() {
+        let response = "a1 OK [CAPABILITY IMAP4rev1 IMAP4rev2 ENABLE] Logged in\r\n\
+            * ENABLED IMAP4rev2\r\n\
+            a2 OK Enabled\r\n\
+            * 3 EXISTS\r\n\
+            * LIST () \"/\" \"Entwürfe\" (\"OLDNAME\" (\"Drafts\"))\r\n\
+            * FLAGS (\\Answered \\Flagged \\Deleted \\Seen \\Draft)\r\n\
+            * OK [UIDVALIDITY 3857529045] Validity\r\n\
+            * OK [UIDNEXT 4392] Next\r\n\
+            a3 OK [READ-WRITE] Select completed\r\n\
+            * ESEARCH (TAG \"a4\") UID ALL 4:6,10\r\n\
+            a4 OK Search completed\r\n\
+            * LIST (\\HasNoChildren) \"/\" \"Entwürfe\"\r\n\
+            * LIST (\\Subscribed) \"/\" \"R&D\" (\"CHILDINFO\" (\"SUBSCRIBED\"))\r\n\
+            a5 OK List completed\r\n"
+            .as_bytes()
+            .to_vec();
+        let client = Client::new(MockStream::new(response));
+        let mut session = client.login("username", "password").unwrap();
+        assert!(!session.is_imap4rev2());
+
+        session
+            .enable(&[imap_proto::Capability::Atom(Cow::Borrowed("IMAP4rev2"))])
+            .unwrap();
+        assert!(session.is_imap4rev2());
+
+        let mailbox = session.select("Entwürfe").unwrap();
+        assert_eq!(mailbox.exists, 3);
+        assert_eq!(mailbox.recent, 0);
+        assert_eq!(mailbox.uid_next, Some(4392));
+        match session.unsolicited_responses.pop_front() {
+            Some(UnsolicitedResponse::List(name)) => {
+                assert_eq!(name.name(), "Entwürfe");
+                assert_eq!(name.old_name(), Some("Drafts"));
+            }
+            r => panic!("unexpected unsolicited response {:?}", r),
+        }
+        assert!(session.unsolicited_responses.is_empty());
+
+        let uids = session.uid_search("UNSEEN").unwrap();
+        assert_eq!(uids, [4, 5, 6, 10].iter().cloned().collect());
+
+        let names = session.list(None, Some("*")).unwrap();
+        let names: Vec<_> = names.iter().map(|n| n.name()).collect();
+        assert_eq!(names, ["Entwürfe", "R&D"]);
+        assert!(session.stream.get_ref().written_buf.ends_with(
+            "a3 SELECT \"Entwürfe\"\r\na4 UID SEARCH UNSEEN\r\na5 LIST \"\" *\r\n".as_bytes()
+        ));
+    }
+
+    #[test]
+    fn imap4rev2_only() {
+        let response = b"a1 OK [CAPABILITY IMAP4rev2 IDLE] Logged in\r\n\
+            * ESEARCH (TAG \"a2\")\r\n\
+            a2 OK Search completed\r\n"
+            .to_vec();
+        let client = Client::new(MockStream::new(response));
+        let mut session = client.login("username", "password").unwrap();
+        assert!(session.is_imap4rev2());
+        assert!(session.search("DELETED").unwrap().is_empty());
+    }
+
     #[test]
     fn sort() {
         use extensions::sort::{SortCharset, SortCriterion};
@@ -2943,11 +3967,20 @@ a1 OK completed\r
         let command = format!("a1 MOVE 1:2 {}\r\n", quote!(mailbox_name));
         let mock_stream = MockStream::new(response);
         let mut session = mock_session!(mock_stream);
-        session.mv("1:2", mailbox_name).unwrap();
+        let copied = session.mv("1:2", mailbox_name).unwrap();
         assert!(
             session.stream.get_ref().written_buf == command.as_bytes().to_vec(),
             "Invalid move command"
         );
+        assert_eq!(copied.uid_validity, Some(1511554416));
+        assert_eq!(
+            copied.uid_map().collect::<Vec<_>>(),
+            vec![(142, 41), (399, 42)]
+        );
+        assert_eq!(
+            session.unsolicited_responses.pop_front(),
+            Some(UnsolicitedResponse::Expunge(2))
+        );
     }
 
     #[test]
@@ -2971,11 +4004,35 @@ a1 OK completed\r
         let command = format!("a1 UID MOVE 41:42 {}\r\n", quote!(mailbox_name));
         let mock_stream = MockStream::new(response);
         let mut session = mock_session!(mock_stream);
-        session.uid_mv("41:42", mailbox_name).unwrap();
+        let copied = session.uid_mv("41:42", mailbox_name).unwrap();
         assert!(
             session.stream.get_ref().written_buf == command.as_bytes().to_vec(),
             "Invalid uid move command"
         );
+        assert_eq!(copied.uid_map().count(), 2);
+    }
+
+    #[test]
+    fn uid_copy_uidplus() {
+        let response = b"a1 OK [COPYUID 38505 304,319:320 3956:3958] Done\r\n".to_vec();
+        let mock_stream = MockStream::new(response);
+        let mut session = mock_session!(mock_stream);
+        let copied = session.uid_copy("304,319:320", "Archive").unwrap();
+        assert_eq!(copied.uid_validity, Some(38505));
+        assert_eq!(
+            copied.uid_map().collect::<Vec<_>>(),
+            vec![(304, 3956), (319, 3957), (320, 3958)]
+        );
+    }
+
+    #[test]
+    fn copy_without_uidplus() {
+        let response = b"a1 OK COPY completed\r\n".to_vec();
+        let mock_stream = MockStream::new(response);
+        let mut session = mock_session!(mock_stream);
+        let copied = session.copy("1:2", "Archive").unwrap();
+        assert_eq!(copied, Copied::default());
+        assert_eq!(copied.uid_map().count(), 0);
     }
 
     #[test]
@@ -3059,6 +4116,30 @@ a1 OK completed\r
         )
     }
 
+    #[test]
+    fn status_size_and_deleted() {
+        let response = b"a1 OK Logged in\r\n\
+            * STATUS inbox (MESSAGES 231 SIZE 44421 DELETED 3 DELETED-STORAGE 12 X-OTHER (a b))\r\n\
+            a2 OK Status completed\r\n"
+            .to_vec();
+        let mock_stream = MockStream::new(response);
+        let client = Client::new(mock_stream);
+        let mut session = client.login("username", "password").unwrap();
+        let mailbox = session
+            .status("INBOX", "(MESSAGES SIZE DELETED DELETED-STORAGE)")
+            .unwrap();
+        assert!(session
+            .stream
+            .get_ref()
+            .written_buf
+            .ends_with(b"a2 STATUS \"INBOX\" (MESSAGES SIZE DELETED DELETED-STORAGE)\r\n"));
+        assert_eq!(mailbox.exists, 231);
+        assert_eq!(mailbox.size, Some(44421));
+        assert_eq!(mailbox.deleted, Some(3));
+        assert_eq!(mailbox.deleted_storage, Some(12));
+        assert_eq!(mailbox.unseen, None);
+    }
+
     #[test]
     fn status_validation_mailbox() {
         assert_validation_error_session(
@@ -0,0 +1,221 @@
+//! Building commands whose arguments may have to be sent as literals.
+//!
+//! Most arguments can be sent as [quoted
+//! strings](https://tools.ietf.org/html/rfc3501#section-4.3), but those that contain 8-bit data or
+//! certain control characters have to be sent as literals instead. A literal is normally
+//! _synchronizing_: the client has to wait for the server's `+` continuation before sending it,
+//! which costs a round trip. Servers that advertise `LITERAL+` or `LITERAL-` ([RFC
+//! 7888](https://tools.ietf.org/html/rfc7888)) also accept _non-synchronizing_ literals, which
+//! are sent right away.
+
+use crate::client::validate_str;
+use crate::error::Result;
+use crate::types::Capabilities;
+use std::borrow::Cow;
+
+/// The largest literal that may be sent without synchronizing when the server only advertises
+/// `LITERAL-`, see [RFC 7888, section 5](https://tools.ietf.org/html/rfc7888#section-5).
+const LITERAL_MINUS_MAX: usize = 4096;
+
+/// A command that is sent in one or more pieces, with a literal between each two of them.
+#[derive(Debug)]
+pub(crate) struct Command<'a> {
+    /// The text of the command. `texts[i + 1]` follows `literals[i]`.
+    texts: Vec<String>,
+    literals: Vec<Literal<'a>>,
+}
+
+/// A literal in a [`Command`].
+#[derive(Debug)]
+pub(crate) struct Literal<'a> {
+    pub(crate) data: Cow<'a, [u8]>,
+
+    /// Whether this is a `literal8`, which may contain any octet including NUL, see [RFC 3516,
+    /// section 4.2](https://tools.ietf.org/html/rfc3516#section-4.2).
+    pub(crate) binary: bool,
+}
+
+impl<'a> Command<'a> {
+    /// Starts a new (untagged) command.
+    pub(crate) fn new(command: impl Into<String>) -> Self {
+        Command {
+            texts: vec![command.into()],
+            literals: Vec::new(),
+        }
+    }
+
+    /// Appends text to the command as is. The caller is responsible for validating it.
+    pub(crate) fn push_str(&mut self, text: &str) -> &mut Self {
+        self.texts
+            .last_mut()
+            .expect("a command always has text")
+            .push_str(text);
+        self
+    }
+
+    /// Appends a literal.
+    pub(crate) fn push_literal(&mut self, data: impl Into<Cow<'a, [u8]>>) -> &mut Self {
+        self.push(Literal {
+            data: data.into(),
+            binary: false,
+        })
+    }
+
+    /// Appends a `literal8`, which requires the `BINARY` capability.
+    pub(crate) fn push_literal8(&mut self, data: impl Into<Cow<'a, [u8]>>) -> &mut Self {
+        self.push(Literal {
+            data: data.into(),
+            binary: true,
+        })
+    }
+
+    fn push(&mut self, literal: Literal<'a>) -> &mut Self {
+        self.literals.push(literal);
+        self.texts.push(String::new());
+        self
+    }
+
+    /// Appends a string argument, as a quoted string if possible and as a literal otherwise.
+    ///
+    /// Like [`validate_str`], this rejects values containing a newline; the arguments `synopsis`
+    /// and `arg_name` are used in the resulting error.
+    pub(crate) fn push_string(
+        &mut self,
+        synopsis: &str,
+        arg_name: &str,
+        value: &'a str,
+    ) -> Result<&mut Self> {
+        let quoted = validate_str(synopsis, arg_name, value)?;
+        if needs_literal(value) {
+            Ok(self.push_literal(value.as_bytes()))
+        } else {
+            Ok(self.push_str(&quoted))
+        }
+    }
+
+    /// Appends search criteria given in the form of a raw string, such as `SUBJECT "foo"`.
+    ///
+    /// The criteria are sent as is, except for quoted strings that cannot actually be sent
+    /// quoted, such as ones containing non-ASCII text: those are sent as literals instead.
+    pub(crate) fn push_criteria(&mut self, criteria: &str) -> &mut Self {
+        let mut rest = criteria;
+        while let Some(start) = rest.find('"') {
+            let (value, len) = match unquote(&rest[start..]) {
+                Some(quoted) => quoted,
+                None => break,
+            };
+            if needs_literal(&value) {
+                self.push_str(&rest[..start]);
+                self.push_literal(value.into_bytes());
+            } else {
+                self.push_str(&rest[..start + len]);
+            }
+            rest = &rest[start + len..];
+        }
+        self.push_str(rest)
+    }
+
+    /// The pieces of text of the command, each followed by the literal at the same index, if any.
+    pub(crate) fn parts(&self) -> impl Iterator<Item = (&str, Option<&Literal<'a>>)> {
+        self.texts
+            .iter()
+            .enumerate()
+            .map(move |(i, text)| (text.as_str(), self.literals.get(i)))
+    }
+}
+
+/// Returns true if `value` cannot be sent as a quoted string.
+///
+/// A quoted string may only contain 7-bit characters other than NUL, CR and LF.
+fn needs_literal(value: &str) -> bool {
+    value
+        .bytes()
+        .any(|b| !b.is_ascii() || b == b'\0' || b == b'\r' || b == b'\n')
+}
+
+/// Parses the quoted string at the start of `quoted`, returning its unescaped content and the
+/// length of the quoted string, or `None` if it is not terminated.
+fn unquote(quoted: &str) -> Option<(String, usize)> {
+    let mut value = String::new();
+    let mut chars = quoted.char_indices().skip(1);
+    while let Some((i, c)) = chars.next() {
+        match c {
+            '"' => return Some((value, i + 1)),
+            '\\' => value.push(chars.next()?.1),
+            c => value.push(c),
+        }
+    }
+    None
+}
+
+/// Returns the size of the largest literal that the server accepts without synchronizing.
+pub(crate) fn non_sync_literal_limit(capabilities: Option<&Capabilities>) -> usize {
+    match capabilities {
+        Some(caps) if caps.has_str("LITERAL+") => usize::MAX,
+        Some(caps) if caps.has_str("LITERAL-") => LITERAL_MINUS_MAX,
+        _ => 0,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parts(command: &Command<'_>) -> Vec<(String, Option<Vec<u8>>)> {
+        command
+            .parts()
+            .map(|(text, literal)| (text.to_string(), literal.map(|l| l.data.to_vec())))
+            .collect()
+    }
+
+    #[test]
+    fn push_string() {
+        let mut command = Command::new("LOGIN ");
+        command
+            .push_string("LOGIN", "username", "user\"name")
+            .unwrap()
+            .push_str(" ")
+            .push_string("LOGIN", "password", "pässword")
+            .unwrap();
+        assert_eq!(
+            parts(&command),
+            vec![
+                (
+                    "LOGIN \"user\\\"name\" ".to_string(),
+                    Some("pässword".into())
+                ),
+                (String::new(), None),
+            ]
+        );
+    }
+
+    #[test]
+    fn push_string_validation() {
+        let mut command = Command::new("LOGIN ");
+        assert!(command.push_string("LOGIN", "username", "a\r\nb").is_err());
+    }
+
+    #[test]
+    fn push_criteria() {
+        let mut command = Command::new("SEARCH ");
+        command.push_criteria(r#"CHARSET UTF-8 SUBJECT "caf\"é" FROM "bob" BODY "naïve""#);
+        assert_eq!(
+            parts(&command),
+            vec![
+                (
+                    "SEARCH CHARSET UTF-8 SUBJECT ".to_string(),
+                    Some("caf\"é".into())
+                ),
+                (" FROM \"bob\" BODY ".to_string(), Some("naïve".into())),
+                (String::new(), None),
+            ]
+        );
+
+        let mut command = Command::new("SEARCH ");
+        command.push_criteria(r#"SUBJECT "unterminated"#);
+        assert_eq!(
+            parts(&command),
+            vec![(r#"SEARCH SUBJECT "unterminated"#.to_string(), None)]
+        );
+    }
+}
@@ -115,6 +115,11 @@ pub enum Error {
     StartTlsNotAvailable,
     /// Returns when Tls is not configured
     TlsNotConfigured,
+    /// SASL authentication failed on the client side.
+    Authentication(AuthenticationError),
+    /// The command needs the given extension to be enabled with
+    /// [`Session::enable`](crate::Session::enable) first.
+    NotEnabled(String),
 }
 
 impl From<IoError> for Error {
@@ -186,6 +191,8 @@ impl fmt::Display for Error {
             Error::TlsNotConfigured => {
                 write!(f, "TLS was requested, but no TLS features are enabled")
             }
+            Error::Authentication(ref e) => fmt::Display::fmt(e, f),
+            Error::NotEnabled(ref extension) => write!(f, "{} is not enabled", extension),
         }
     }
 }
@@ -213,6 +220,8 @@ impl StdError for Error {
             Error::TagMismatch(ref e) => e.description(),
             Error::StartTlsNotAvailable => "StartTls is not available on the server",
             Error::TlsNotConfigured => "TLS was requested, but no TLS features are enabled",
+            Error::Authentication(ref e) => e.description(),
+            Error::NotEnabled(_) => "Extension is not enabled",
         }
     }
 
@@ -227,6 +236,7 @@ impl StdError for Error {
             Error::TlsHandshake(ref e) => Some(e),
             Error::Parse(ParseError::DataNotUtf8(_, ref e)) => Some(e),
             Error::TagMismatch(ref e) => Some(e),
+            Error::Authentication(ref e) => Some(e),
             _ => None,
         }
     }
@@ -272,6 +282,61 @@ impl StdError for ParseError {
     }
 }
 
+/// SASL authentication could not be completed, see [`Client::authenticate`](crate::Client::authenticate).
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum AuthenticationError {
+    /// The server does not advertise any mechanism that can be used with the given credentials.
+    NoMechanism,
+    /// The server did not prove that it knows the credentials, so it may be an impostor.
+    ServerNotVerified,
+    /// The server sent a challenge that is not valid for the mechanism.
+    InvalidChallenge(Vec<u8>),
+    /// The server rejected the credentials, and explained why in a challenge, such as the JSON
+    /// error of `OAUTHBEARER` or the `e=` attribute of SCRAM.
+    ServerError(String),
+    /// A custom [`AuthenticatorMut`](crate::AuthenticatorMut) failed.
+    Other(Box<dyn StdError + Send + Sync>),
+}
+
+impl fmt::Display for AuthenticationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            AuthenticationError::NoMechanism => {
+                f.write_str("No supported authentication mechanism available")
+            }
+            AuthenticationError::ServerNotVerified => f.write_str("Server could not be verified"),
+            AuthenticationError::InvalidChallenge(_) => {
+                f.write_str("Invalid authentication challenge")
+            }
+            AuthenticationError::ServerError(ref e) => {
+                write!(f, "Authentication rejected by server: {}", e)
+            }
+            AuthenticationError::Other(ref e) => fmt::Display::fmt(e, f),
+        }
+    }
+}
+
+impl StdError for AuthenticationError {
+    #[allow(deprecated)]
+    fn description(&self) -> &str {
+        match *self {
+            AuthenticationError::NoMechanism => "No supported authentication mechanism available",
+            AuthenticationError::ServerNotVerified => "Server could not be verified",
+            AuthenticationError::InvalidChallenge(_) => "Invalid authentication challenge",
+            AuthenticationError::ServerError(_) => "Authentication rejected by server",
+            AuthenticationError::Other(ref e) => e.description(),
+        }
+    }
+
+    fn cause(&self) -> Option<&dyn StdError> {
+        match *self {
+            AuthenticationError::Other(ref e) => Some(&**e),
+            _ => None,
+        }
+    }
+}
+
 /// An [invalid character](https://tools.ietf.org/html/rfc3501#section-4.3) was found in a command
 /// argument.
 #[derive(Debug)]
@@ -0,0 +1,157 @@
+//! Adds support for the IMAP BINARY extension specified in [RFC
+//! 3516](https://tools.ietf.org/html/rfc3516).
+//!
+//! Message parts are usually stored with a content transfer encoding such as base64 or
+//! quoted-printable, which `BODY[<section>]` returns as is. With BINARY, the server decodes the
+//! part before returning it: fetching `BINARY[<part>]` or `BINARY.PEEK[<part>]` yields the raw
+//! bytes of the part, available through [`Fetch::binary`], and `BINARY.SIZE[<part>]` yields their
+//! size, available through [`Fetch::binary_size`]. In the other direction,
+//! [`AppendCmd::binary`](crate::AppendCmd::binary) uploads a message unchanged, even if it
+//! contains NUL octets.
+//!
+//! The decoded content may contain any octet, so the server may send it as a `literal8` of the
+//! form `~{<size>}`, which is also handled.
+
+use crate::types::{ExtensionAttribute, Fetch};
+use imap_proto::parser::core::{nstring, number};
+use nom::{
+    branch::alt,
+    bytes::complete::{tag, tag_no_case, take},
+    character::complete::char,
+    combinator::{map, opt},
+    multi::separated_list0,
+    sequence::{delimited, tuple},
+    IResult,
+};
+use std::borrow::Cow;
+
+impl<'a> Fetch<'a> {
+    /// The decoded bytes of the given body part, if `BINARY[<part>]` or `BINARY.PEEK[<part>]` was
+    /// included in the `query` argument to `FETCH`. Unlike [`Fetch::section`], the content
+    /// transfer encoding of the part has already been undone by the server.
+    ///
+    /// `part` is the part number, such as `&[2, 1]` for `BINARY[2.1]`, or `&[]` for the whole
+    /// message.
+    ///
+    /// See [section 4.1 of RFC 3516](https://tools.ietf.org/html/rfc3516#section-4.1) for details.
+    pub fn binary(&self, part: &[u32]) -> Option<&[u8]> {
+        self.extensions.iter().find_map(|attr| match attr {
+            ExtensionAttribute::BinarySection {
+                part: p,
+                data: Some(data),
+            } if p == part => Some(&**data),
+            _ => None,
+        })
+    }
+
+    /// The size of the decoded content of the given body part, if `BINARY.SIZE[<part>]` was
+    /// included in the `query` argument to `FETCH`.
+    ///
+    /// See [section 4.1 of RFC 3516](https://tools.ietf.org/html/rfc3516#section-4.1) for details.
+    pub fn binary_size(&self, part: &[u32]) -> Option<u32> {
+        self.extensions.iter().find_map(|attr| match attr {
+            ExtensionAttribute::BinarySize { part: p, size } if p == part => Some(*size),
+            _ => None,
+        })
+    }
+}
+
+// literal8 = "~{" number "}" CRLF *OCTET
+pub(crate) fn literal8(i: &[u8]) -> IResult<&[u8], &[u8]> {
+    let (i, (_, count, _)) = tuple((tag("~{"), number, tag("}\r\n")))(i)?;
+    take(count)(i)
+}
+
+// section-binary = "[" [section-part] "]"
+fn section_binary(i: &[u8]) -> IResult<&[u8], Vec<u32>> {
+    delimited(char('['), separated_list0(char('.'), number), char(']'))(i)
+}
+
+// "BINARY" section-binary ["<" number ">"] SP (nstring / literal8)
+// / "BINARY.SIZE" section-binary SP number
+pub(crate) fn binary_attribute(i: &[u8]) -> IResult<&[u8], ExtensionAttribute<'_>> {
+    alt((
+        map(
+            tuple((
+                tag_no_case("BINARY.SIZE"),
+                section_binary,
+                char(' '),
+                number,
+            )),
+            |(_, part, _, size)| ExtensionAttribute::BinarySize { part, size },
+        ),
+        map(
+            tuple((
+                tag_no_case("BINARY"),
+                section_binary,
+                opt(delimited(char('<'), number, char('>'))),
+                char(' '),
+                alt((map(literal8, Some), nstring)),
+            )),
+            |(_, part, _, _, data)| ExtensionAttribute::BinarySection {
+                part,
+                data: data.map(Cow::Borrowed),
+            },
+        ),
+    ))(i)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::mock_stream::MockStream;
+    use crate::types::{Fetches, Flag};
+    use crate::Client;
+    use std::collections::VecDeque;
+
+    #[test]
+    fn parse_binary_fetch() {
+        let lines = b"* 1 FETCH (UID 7 BINARY[1] ~{5}\r\nab\0cd BINARY.SIZE[2.1] 1024 \
+            FLAGS (\\Seen))\r\n\
+            * 2 FETCH (BINARY[] NIL)\r\n\
+            * 3 EXISTS\r\n\
+            * 3 FETCH (BINARY[1]<0> \"hello\" BODY[HEADER.FIELDS (DATE)] {8}\r\nDate: x\n)\r\n"
+            .to_vec();
+        let mut queue = VecDeque::new();
+        let fetches = Fetches::parse(lines, &mut queue).unwrap();
+        assert_eq!(
+            queue.pop_front(),
+            Some(crate::types::UnsolicitedResponse::Exists(3))
+        );
+        assert_eq!(fetches.len(), 3);
+
+        let first = fetches.get(0).unwrap();
+        assert_eq!(first.message, 1);
+        assert_eq!(first.uid, Some(7));
+        assert_eq!(first.flags(), &[Flag::Seen]);
+        assert_eq!(first.binary(&[1]), Some(&b"ab\0cd"[..]));
+        assert_eq!(first.binary_size(&[2, 1]), Some(1024));
+        assert_eq!(first.binary_size(&[2]), None);
+
+        let second = fetches.get(1).unwrap();
+        assert_eq!(second.binary(&[]), None);
+
+        let third = fetches.get(2).unwrap();
+        assert_eq!(third.binary(&[1]), Some(&b"hello"[..]));
+        assert_eq!(third.fetch.len(), 1);
+    }
+
+    #[test]
+    fn uid_fetch_binary() {
+        let response = b"a1 OK Logged in\r\n\
+            * 2 FETCH (UID 42 BINARY[2] ~{3}\r\n\x00\x01\x02)\r\n\
+            a2 OK Fetch completed\r\n"
+            .to_vec();
+        let mock_stream = MockStream::new(response);
+        let client = Client::new(mock_stream);
+        let mut session = client.login("username", "password").unwrap();
+        let fetches = session.uid_fetch("42", "BINARY.PEEK[2]").unwrap();
+        assert!(session
+            .stream
+            .get_ref()
+            .written_buf
+            .ends_with(b"a2 UID FETCH 42 BINARY.PEEK[2]\r\n"));
+        let fetch = fetches.get(0).unwrap();
+        assert_eq!(fetch.uid, Some(42));
+        assert_eq!(fetch.binary(&[2]), Some(&b"\x00\x01\x02"[..]));
+    }
+}
@@ -0,0 +1,327 @@
+//! Adds support for the IMAP COMPRESS extension specified in [RFC
+//! 4978](https://tools.ietf.org/html/rfc4978).
+//!
+//! After a successful [`Session::compress`], everything sent in either direction is compressed
+//! with raw DEFLATE ([RFC 1951](https://tools.ietf.org/html/rfc1951)). This is transparent to the
+//! rest of the session; in particular, [`Session::idle`] keeps working, as the underlying stream's
+//! [`SetReadTimeout`] is still used.
+
+use crate::client::Session;
+use crate::error::Result;
+use crate::extensions::idle::SetReadTimeout;
+use flate2::{Compress, Compression, Decompress, FlushCompress, FlushDecompress};
+use std::fmt;
+use std::io::{self, Read, Write};
+use std::ops::{Deref, DerefMut};
+use std::time::Duration;
+
+/// How many compressed bytes are read from the underlying stream at once.
+const INPUT_BUFFER_SIZE: usize = 8 * 1024;
+
+/// How many compressed bytes are kept around before they are written to the underlying stream,
+/// even if the stream was not flushed yet.
+const OUTPUT_BUFFER_SIZE: usize = 64 * 1024;
+
+/// The DEFLATE state of a compressed connection.
+struct Deflate {
+    compress: Compress,
+    decompress: Decompress,
+    /// Compressed bytes that have been read, but not decompressed yet.
+    input: Vec<u8>,
+    input_pos: usize,
+    /// Compressed bytes that have not been written yet.
+    output: Vec<u8>,
+}
+
+/// The transport underneath a connection's buffering, which compresses and decompresses all
+/// traffic once [`Session::compress`] has succeeded, and passes it through unchanged otherwise.
+pub(crate) struct Compressible<T> {
+    inner: T,
+    deflate: Option<Box<Deflate>>,
+    /// When set, reads return end-of-file without touching `inner`. Used to drain whatever the
+    /// buffering on top of this stream has read ahead, without blocking.
+    draining: bool,
+}
+
+impl<T> Compressible<T> {
+    pub(crate) fn new(inner: T) -> Self {
+        Compressible {
+            inner,
+            deflate: None,
+            draining: false,
+        }
+    }
+
+    /// Unwraps the underlying stream. If compression was active, any further traffic on it is
+    /// still compressed.
+    pub(crate) fn into_inner(self) -> T {
+        self.inner
+    }
+
+    pub(crate) fn set_draining(&mut self, draining: bool) {
+        self.draining = draining;
+    }
+
+    /// Compresses all further traffic. `input` holds any compressed bytes that were already
+    /// read from the underlying stream.
+    pub(crate) fn start_deflate(&mut self, input: Vec<u8>) {
+        self.deflate = Some(Box::new(Deflate {
+            // RFC 4978 uses raw DEFLATE, without the zlib header.
+            compress: Compress::new(Compression::default(), false),
+            decompress: Decompress::new(false),
+            input,
+            input_pos: 0,
+            output: Vec::new(),
+        }));
+    }
+}
+
+impl<T: Write> Compressible<T> {
+    /// Feeds `input` to the compressor, writing the output out whenever enough has accumulated.
+    fn deflate(&mut self, mut input: &[u8], flush: FlushCompress) -> io::Result<()> {
+        let deflate = match self.deflate.as_mut() {
+            Some(deflate) => deflate,
+            None => return Ok(()),
+        };
+        loop {
+            deflate.output.reserve(input.len().max(1024));
+            let before = deflate.compress.total_in();
+            deflate
+                .compress
+                .compress_vec(input, &mut deflate.output, flush)
+                .map_err(io::Error::other)?;
+            input = &input[(deflate.compress.total_in() - before) as usize..];
+            // The compressor is only done once it left some of the output space unused.
+            let done = input.is_empty() && deflate.output.len() < deflate.output.capacity();
+            if deflate.output.len() >= OUTPUT_BUFFER_SIZE || (done && flush != FlushCompress::None)
+            {
+                self.inner.write_all(&deflate.output)?;
+                deflate.output.clear();
+            }
+            if done {
+                return Ok(());
+            }
+        }
+    }
+}
+
+impl<T: Read> Read for Compressible<T> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if self.draining {
+            return Ok(0);
+        }
+        let deflate = match self.deflate.as_mut() {
+            Some(deflate) => deflate,
+            None => return self.inner.read(buf),
+        };
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        loop {
+            // The decompressor may still hold output from earlier input, so it is asked for more
+            // before reading from the underlying stream.
+            let (before_in, before_out) = (
+                deflate.decompress.total_in(),
+                deflate.decompress.total_out(),
+            );
+            deflate
+                .decompress
+                .decompress(
+                    &deflate.input[deflate.input_pos..],
+                    buf,
+                    FlushDecompress::None,
+                )
+                .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+            let consumed = (deflate.decompress.total_in() - before_in) as usize;
+            let produced = (deflate.decompress.total_out() - before_out) as usize;
+            deflate.input_pos += consumed;
+            if produced > 0 {
+                return Ok(produced);
+            }
+            if deflate.input_pos < deflate.input.len() {
+                if consumed == 0 {
+                    return Err(io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        "unexpected end of compressed stream",
+                    ));
+                }
+                continue;
+            }
+
+            deflate.input.resize(INPUT_BUFFER_SIZE, 0);
+            deflate.input_pos = 0;
+            let read = self.inner.read(&mut deflate.input);
+            deflate.input.truncate(*read.as_ref().unwrap_or(&0));
+            if read? == 0 {
+                return Ok(0);
+            }
+        }
+    }
+}
+
+impl<T: Write> Write for Compressible<T> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if self.deflate.is_none() {
+            return self.inner.write(buf);
+        }
+        self.deflate(buf, FlushCompress::None)?;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        // A sync flush makes sure the server can decompress everything written so far.
+        self.deflate(&[], FlushCompress::Sync)?;
+        self.inner.flush()
+    }
+}
+
+impl<T> Deref for Compressible<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.inner
+    }
+}
+
+impl<T> DerefMut for Compressible<T> {
+    fn deref_mut(&mut self) -> &mut T {
+        &mut self.inner
+    }
+}
+
+impl<T: fmt::Debug> fmt::Debug for Compressible<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Compressible")
+            .field("inner", &self.inner)
+            .field("compressed", &self.deflate.is_some())
+            .finish()
+    }
+}
+
+impl<T: SetReadTimeout> SetReadTimeout for Compressible<T> {
+    fn set_read_timeout(&mut self, timeout: Option<Duration>) -> Result<()> {
+        self.inner.set_read_timeout(timeout)
+    }
+}
+
+impl<T: Read + Write> Session<T> {
+    /// The [`COMPRESS` command](https://tools.ietf.org/html/rfc4978#section-3) turns on DEFLATE
+    /// compression for all further traffic on this connection, which considerably reduces the
+    /// bandwidth needed by commands such as [`Session::fetch`].
+    ///
+    /// Compression cannot be turned off again. If a security layer such as TLS is in use, it is
+    /// applied underneath the compression, as mandated by the RFC.
+    ///
+    /// This requires that the server advertises the `COMPRESS=DEFLATE` capability.
+    pub fn compress(&mut self) -> Result<()> {
+        self.run_command_and_check_ok("COMPRESS DEFLATE")?;
+
+        // Compression starts right after the tagged OK, so anything the server sent after it is
+        // compressed, and may already have been buffered.
+        self.stream.get_mut().set_draining(true);
+        let buffered = io::BufRead::fill_buf(&mut self.stream).map(<[u8]>::to_vec);
+        self.stream.get_mut().set_draining(false);
+        let buffered = buffered?;
+        io::BufRead::consume(&mut self.stream, buffered.len());
+        self.stream.get_mut().start_deflate(buffered);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::UnsolicitedResponse;
+    use crate::Client;
+    use std::io::{BufRead, BufReader};
+    use std::net::{TcpListener, TcpStream};
+    use std::thread;
+
+    /// A stand-in IMAP server speaking raw DEFLATE after `COMPRESS`.
+    struct Server {
+        stream: TcpStream,
+        reader: BufReader<flate2::read::DeflateDecoder<TcpStream>>,
+        writer: flate2::write::DeflateEncoder<Vec<u8>>,
+    }
+
+    impl Server {
+        /// Accepts the login and the `COMPRESS` command, and answers the latter with the tagged
+        /// OK immediately followed by the compressed `after_ok`, so that the client has to pick
+        /// up compressed data it may already have buffered.
+        fn start(stream: TcpStream, after_ok: &[u8]) -> Self {
+            let mut reader = BufReader::new(stream.try_clone().unwrap());
+            let mut line = String::new();
+            reader.read_line(&mut line).unwrap();
+            assert_eq!(line, "a1 LOGIN \"testuser\" \"pass\"\r\n");
+            reader.get_mut().write_all(b"a1 OK Logged in\r\n").unwrap();
+            line.clear();
+            reader.read_line(&mut line).unwrap();
+            assert_eq!(line, "a2 COMPRESS DEFLATE\r\n");
+
+            let mut server = Server {
+                stream,
+                reader: BufReader::new(flate2::read::DeflateDecoder::new(reader.into_inner())),
+                writer: flate2::write::DeflateEncoder::new(
+                    b"a2 OK DEFLATE active\r\n".to_vec(),
+                    flate2::Compression::default(),
+                ),
+            };
+            server.send(after_ok);
+            server
+        }
+
+        fn expect(&mut self, expected: &str) {
+            let mut line = String::new();
+            self.reader.read_line(&mut line).unwrap();
+            assert_eq!(line, expected);
+        }
+
+        fn send(&mut self, data: &[u8]) {
+            self.writer.write_all(data).unwrap();
+            self.writer.flush().unwrap();
+            self.stream.write_all(self.writer.get_ref()).unwrap();
+            self.writer.get_mut().clear();
+        }
+    }
+
+    #[test]
+    fn compress() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let server = thread::spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            let mut server = Server::start(stream, b"* 3 EXISTS\r\n");
+            server.expect("a3 NOOP\r\n");
+            server.send(b"* 2 RECENT\r\na3 OK NOOP completed\r\n");
+            server.expect("a4 IDLE\r\n");
+            server.send(b"+ idling\r\n");
+            server.send(b"* 4 EXISTS\r\n");
+            server.expect("DONE\r\n");
+            server.send(b"a4 OK IDLE terminated\r\n");
+        });
+
+        let client = Client::new(TcpStream::connect(addr).unwrap());
+        let mut session = client.login("testuser", "pass").unwrap();
+        session.compress().unwrap();
+        session.noop().unwrap();
+        assert_eq!(
+            session.take_all_unsolicited().collect::<Vec<_>>(),
+            vec![
+                UnsolicitedResponse::Exists(3),
+                UnsolicitedResponse::Recent(2)
+            ]
+        );
+
+        let mut responses = Vec::new();
+        session
+            .idle()
+            .timeout(Duration::from_secs(10))
+            .wait_while(|response| {
+                responses.push(response);
+                false
+            })
+            .unwrap();
+        assert_eq!(responses, vec![UnsolicitedResponse::Exists(4)]);
+        server.join().unwrap();
+    }
+}
@@ -0,0 +1,384 @@
+//! Adds support for the IMAP CONDSTORE extension specified in [RFC
+//! 7162](https://tools.ietf.org/html/rfc7162#section-3.1).
+//!
+//! CONDSTORE lets a client ask for only the messages whose metadata changed since a known
+//! mod-sequence (the `CHANGEDSINCE` [`FetchModifier`]), and make a `STORE` conditional on the
+//! messages not having changed since a known mod-sequence (the `UNCHANGEDSINCE`
+//! [`StoreModifier`]). Messages that fail that check are reported back in [`Stored::modified`].
+
+use crate::client::{validate_sequence_set, validate_str_noquote, Session};
+use crate::error::Result;
+use crate::types::{Fetches, SequenceSet, UnsolicitedResponse};
+use crate::utils::iter_join;
+use nom::{
+    bytes::complete::{tag, tag_no_case, take_till1},
+    combinator::map,
+    sequence::{preceded, tuple},
+    IResult,
+};
+use std::collections::VecDeque;
+use std::fmt;
+use std::io::{Read, Write};
+
+/// A modifier to the `FETCH` and `UID FETCH` commands.
+///
+/// Used by [`Session::fetch_with_modifiers`] and [`Session::uid_fetch_with_modifiers`].
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+#[non_exhaustive]
+pub enum FetchModifier {
+    /// Only return messages whose mod-sequence is greater than the given value.
+    ///
+    /// This implicitly adds `MODSEQ` to the fetched data items, see [RFC 7162, section
+    /// 3.1.4.1](https://tools.ietf.org/html/rfc7162#section-3.1.4.1).
+    ChangedSince(u64),
+}
+
+impl fmt::Display for FetchModifier {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FetchModifier::ChangedSince(modseq) => write!(f, "CHANGEDSINCE {}", modseq),
+        }
+    }
+}
+
+/// A modifier to the `STORE` and `UID STORE` commands.
+///
+/// Used by [`Session::store_with_modifiers`] and [`Session::uid_store_with_modifiers`].
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+#[non_exhaustive]
+pub enum StoreModifier {
+    /// Only alter messages whose mod-sequence is less than or equal to the given value. All
+    /// other messages are left alone and reported in [`Stored::modified`].
+    ///
+    /// See [RFC 7162, section 3.1.3](https://tools.ietf.org/html/rfc7162#section-3.1.3).
+    UnchangedSince(u64),
+}
+
+impl fmt::Display for StoreModifier {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StoreModifier::UnchangedSince(modseq) => write!(f, "UNCHANGEDSINCE {}", modseq),
+        }
+    }
+}
+
+/// Formats a list of modifiers as a parenthesized list, preceded by a space, or as nothing at
+/// all if the list is empty.
+struct Modifiers<'a, M>(&'a [M]);
+
+impl<M: fmt::Display> fmt::Display for Modifiers<'_, M> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.0.is_empty() {
+            Ok(())
+        } else {
+            write!(f, " ({})", iter_join(self.0.iter(), " "))
+        }
+    }
+}
+
+/// The result of a [`Session::store_with_modifiers`] or [`Session::uid_store_with_modifiers`].
+#[derive(Debug)]
+#[non_exhaustive]
+pub struct Stored {
+    /// The `FETCH` responses returned for the altered messages.
+    pub fetches: Fetches,
+    /// The messages that were *not* altered because they failed the `UNCHANGEDSINCE` check, as
+    /// given by the [`MODIFIED` response
+    /// code](https://tools.ietf.org/html/rfc7162#section-3.1.3).
+    ///
+    /// These are message sequence numbers for [`Session::store_with_modifiers`] and [`Uid`]s for
+    /// [`Session::uid_store_with_modifiers`]. The set is empty if every message passed the check.
+    ///
+    /// [`Uid`]: crate::types::Uid
+    pub modified: SequenceSet,
+}
+
+impl Stored {
+    fn parse(
+        mut lines: Vec<u8>,
+        ok: usize,
+        unsolicited: &mut VecDeque<UnsolicitedResponse>,
+    ) -> Result<Self> {
+        let modified = parse_modified(&lines[ok..]);
+        lines.truncate(ok);
+        Ok(Stored {
+            fetches: Fetches::parse(lines, unsolicited)?,
+            modified,
+        })
+    }
+}
+
+/// Extract the `MODIFIED` response code from the tagged response of a conditional store.
+///
+/// imap-proto does not know this response code, and rejects tagged responses whose text is not
+/// ASCII, so the code is looked for right after the tag and status.
+fn parse_modified(done: &[u8]) -> SequenceSet {
+    // response-tagged = tag SP resp-cond-state CRLF
+    // resp-cond-state = ("OK" / "NO" / "BAD") SP resp-text
+    let modified: IResult<&[u8], _> = preceded(
+        tuple((
+            take_till1(|c| c == b' '),
+            tag(" "),
+            take_till1(|c| c == b' '),
+            tag(" "),
+            tag_no_case("[MODIFIED "),
+        )),
+        map(imap_proto::parser::core::sequence_set, SequenceSet::from),
+    )(done);
+    modified.map(|(_, set)| set).unwrap_or_default()
+}
+
+impl<T: Read + Write> Session<T> {
+    /// Equivalent to [`Session::fetch`], except that the given `modifiers` are passed along with
+    /// the command.
+    ///
+    /// For example, [`FetchModifier::ChangedSince`] restricts the result to the messages whose
+    /// metadata changed since a previously seen mod-sequence:
+    ///
+    /// ```no_run
+    /// # use imap::extensions::condstore::FetchModifier;
+    /// # {} #[cfg(feature = "native-tls")]
+    /// # fn main() {
+    /// # let client = imap::ClientBuilder::new("imap.example.com", 993)
+    ///     .connect().unwrap();
+    /// # let mut session = client.login("name", "pw").unwrap();
+    /// let changed = session
+    ///     .fetch_with_modifiers("1:*", "(FLAGS)", &[FetchModifier::ChangedSince(12345)])
+    ///     .unwrap();
+    /// for fetch in changed.iter() {
+    ///     println!("{}: {:?} {:?}", fetch.message, fetch.flags(), fetch.mod_seq());
+    /// }
+    /// # }
+    /// ```
+    ///
+    /// This requires that the server supports the `CONDSTORE` capability.
+    pub fn fetch_with_modifiers(
+        &mut self,
+        sequence_set: impl AsRef<str>,
+        query: impl AsRef<str>,
+        modifiers: &[FetchModifier],
+    ) -> Result<Fetches> {
+        self.fetch_modified("FETCH", sequence_set.as_ref(), query.as_ref(), modifiers)
+    }
+
+    /// Equivalent to [`Session::fetch_with_modifiers`], except that all identifiers in `uid_set`
+    /// are [`Uid`](crate::types::Uid)s. See also [`Session::uid_fetch`].
+    pub fn uid_fetch_with_modifiers(
+        &mut self,
+        uid_set: impl AsRef<str>,
+        query: impl AsRef<str>,
+        modifiers: &[FetchModifier],
+    ) -> Result<Fetches> {
+        self.fetch_modified("UID FETCH", uid_set.as_ref(), query.as_ref(), modifiers)
+    }
+
+    fn fetch_modified(
+        &mut self,
+        synopsis: &str,
+        sequence_set: &str,
+        query: &str,
+        modifiers: &[FetchModifier],
+    ) -> Result<Fetches> {
+        if sequence_set.is_empty() {
+            return Fetches::parse(vec![], &mut self.unsolicited_responses);
+        }
+        let lines = self.run_command_and_read_response(format!(
+            "{} {} {}{}",
+            synopsis,
+            validate_sequence_set(synopsis, "seq", sequence_set)?,
+            validate_str_noquote(synopsis, "query", query)?,
+            Modifiers(modifiers)
+        ))?;
+        Fetches::parse(lines, &mut self.unsolicited_responses)
+    }
+
+    /// Equivalent to [`Session::store`], except that the given `modifiers` are passed along with
+    /// the command.
+    ///
+    /// With [`StoreModifier::UnchangedSince`], the store becomes conditional: only messages that
+    /// have not been modified since the given mod-sequence are altered. The ones that were
+    /// skipped are listed in [`Stored::modified`], so that the caller can re-fetch them and
+    /// decide what to do:
+    ///
+    /// ```no_run
+    /// # use imap::extensions::condstore::StoreModifier;
+    /// # {} #[cfg(feature = "native-tls")]
+    /// # fn main() {
+    /// # let client = imap::ClientBuilder::new("imap.example.com", 993)
+    ///     .connect().unwrap();
+    /// # let mut session = client.login("name", "pw").unwrap();
+    /// let stored = session
+    ///     .store_with_modifiers(
+    ///         "1:5",
+    ///         "+FLAGS.SILENT (\\Seen)",
+    ///         &[StoreModifier::UnchangedSince(12345)],
+    ///     )
+    ///     .unwrap();
+    /// if !stored.modified.is_empty() {
+    ///     println!("messages {} were changed by someone else", stored.modified);
+    /// }
+    /// # }
+    /// ```
+    ///
+    /// This requires that the server supports the `CONDSTORE` capability.
+    pub fn store_with_modifiers(
+        &mut self,
+        sequence_set: impl AsRef<str>,
+        query: impl AsRef<str>,
+        modifiers: &[StoreModifier],
+    ) -> Result<Stored> {
+        self.store_modified("STORE", sequence_set.as_ref(), query.as_ref(), modifiers)
+    }
+
+    /// Equivalent to [`Session::store_with_modifiers`], except that all identifiers in `uid_set`
+    /// are [`Uid`](crate::types::Uid)s. See also [`Session::uid_store`].
+    pub fn uid_store_with_modifiers(
+        &mut self,
+        uid_set: impl AsRef<str>,
+        query: impl AsRef<str>,
+        modifiers: &[StoreModifier],
+    ) -> Result<Stored> {
+        self.store_modified("UID STORE", uid_set.as_ref(), query.as_ref(), modifiers)
+    }
+
+    fn store_modified(
+        &mut self,
+        synopsis: &str,
+        sequence_set: &str,
+        query: &str,
+        modifiers: &[StoreModifier],
+    ) -> Result<Stored> {
+        let (lines, ok) = self.run(format!(
+            "{} {}{} {}",
+            synopsis,
+            validate_sequence_set(synopsis, "seq", sequence_set)?,
+            Modifiers(modifiers),
+            validate_str_noquote(synopsis, "query", query)?
+        ))?;
+        Stored::parse(lines, ok, &mut self.unsolicited_responses)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::client::testutils::assert_validation_error_session;
+    use crate::mock_stream::MockStream;
+    use crate::Client;
+
+    #[test]
+    fn fetch_changed_since() {
+        let response = b"a1 OK Logged in\r\n\
+            * 2 FETCH (UID 7 FLAGS (\\Seen) MODSEQ (12121231000))\r\n\
+            a2 OK FETCH completed\r\n"
+            .to_vec();
+        let mock_stream = MockStream::new(response);
+        let client = Client::new(mock_stream);
+        let mut session = client.login("testuser", "pass").unwrap();
+        let fetches = session
+            .uid_fetch_with_modifiers("1:*", "(FLAGS)", &[FetchModifier::ChangedSince(1200)])
+            .unwrap();
+        assert!(session
+            .stream
+            .get_ref()
+            .written_buf
+            .ends_with(b"a2 UID FETCH 1:* (FLAGS) (CHANGEDSINCE 1200)\r\n"));
+        assert_eq!(fetches.len(), 1);
+        let fetch = fetches.get(0).unwrap();
+        assert_eq!(fetch.uid, Some(7));
+        assert_eq!(fetch.mod_seq(), Some(12121231000));
+    }
+
+    #[test]
+    fn fetch_without_modifiers() {
+        let response = b"a1 OK Logged in\r\n\
+            a2 OK FETCH completed\r\n"
+            .to_vec();
+        let mock_stream = MockStream::new(response);
+        let client = Client::new(mock_stream);
+        let mut session = client.login("testuser", "pass").unwrap();
+        let fetches = session.fetch_with_modifiers("1", "FLAGS", &[]).unwrap();
+        assert!(session
+            .stream
+            .get_ref()
+            .written_buf
+            .ends_with(b"a2 FETCH 1 FLAGS\r\n"));
+        assert!(fetches.is_empty());
+    }
+
+    #[test]
+    fn store_unchanged_since() {
+        let response = b"a1 OK Logged in\r\n\
+            * 5 FETCH (MODSEQ (320162350))\r\n\
+            a2 OK [MODIFIED 7,9:11] Conditional STORE failed\r\n"
+            .to_vec();
+        let mock_stream = MockStream::new(response);
+        let client = Client::new(mock_stream);
+        let mut session = client.login("testuser", "pass").unwrap();
+        let stored = session
+            .store_with_modifiers(
+                "7,5,9:11",
+                "+FLAGS.SILENT (\\Deleted)",
+                &[StoreModifier::UnchangedSince(320162338)],
+            )
+            .unwrap();
+        assert!(session.stream.get_ref().written_buf.ends_with(
+            b"a2 STORE 7,5,9:11 (UNCHANGEDSINCE 320162338) +FLAGS.SILENT (\\Deleted)\r\n"
+        ));
+        assert_eq!(stored.fetches.len(), 1);
+        assert_eq!(stored.fetches.get(0).unwrap().mod_seq(), Some(320162350));
+        assert_eq!(stored.modified.ranges(), &[7..=7, 9..=11]);
+        assert_eq!(stored.modified.to_string(), "7,9:11");
+    }
+
+    #[test]
+    fn uid_store_all_unchanged() {
+        let response = b"a1 OK Logged in\r\n\
+            * 5 FETCH (UID 42 MODSEQ (320162350))\r\n\
+            a2 OK Conditional STORE completed\r\n"
+            .to_vec();
+        let mock_stream = MockStream::new(response);
+        let client = Client::new(mock_stream);
+        let mut session = client.login("testuser", "pass").unwrap();
+        let stored = session
+            .uid_store_with_modifiers(
+                "42",
+                "+FLAGS.SILENT (\\Seen)",
+                &[StoreModifier::UnchangedSince(320162338)],
+            )
+            .unwrap();
+        assert!(session
+            .stream
+            .get_ref()
+            .written_buf
+            .ends_with(b"a2 UID STORE 42 (UNCHANGEDSINCE 320162338) +FLAGS.SILENT (\\Seen)\r\n"));
+        assert_eq!(stored.fetches.len(), 1);
+        assert!(stored.modified.is_empty());
+    }
+
+    #[test]
+    fn parse_modified_non_ascii_text() {
+        assert!(parse_modified("a2 OK Done 日本語\r\n".as_bytes()).is_empty());
+        assert!(parse_modified("a2 OK 日本語\r\n".as_bytes()).is_empty());
+        assert_eq!(
+            parse_modified("a2 OK [MODIFIED 7] 日本語\r\n".as_bytes()).ranges(),
+            &[7..=7]
+        );
+    }
+
+    #[test]
+    fn store_validation_seq() {
+        assert_validation_error_session(
+            |mut session| {
+                session.store_with_modifiers(
+                    "1 2",
+                    "+FLAGS (\\Seen)",
+                    &[StoreModifier::UnchangedSince(1)],
+                )
+            },
+            "STORE",
+            "seq",
+            ' ',
+        );
+    }
+}
@@ -0,0 +1,271 @@
+//! Adds support for the IMAP ENABLE extension specified in [RFC
+//! 5161](https://tools.ietf.org/html/rfc5161).
+//!
+//! Some IMAP extensions change the server's behaviour in ways a client has to explicitly opt in
+//! to, such as `CONDSTORE`, `QRESYNC` or `UTF8=ACCEPT`. The client does so with
+//! [`Session::enable`], and the server replies with the subset of those extensions it actually
+//! enabled. The [`Session`] keeps track of everything enabled so far, which is available through
+//! [`Session::enabled`].
+
+use crate::client::Session;
+use crate::error::{Error, Result, ValidateError};
+use crate::parse::{parse_many_into, MapOrNot};
+use crate::types::UnsolicitedResponse;
+use imap_proto::{Capability, Response};
+use nom::combinator::fail;
+use std::collections::hash_set::Iter;
+use std::collections::{HashSet, VecDeque};
+use std::fmt;
+use std::io::{Read, Write};
+
+const CONDSTORE: &str = "CONDSTORE";
+const QRESYNC: &str = "QRESYNC";
+
+/// The set of extensions that the server has reported as enabled in response to the [`ENABLE`
+/// command](https://tools.ietf.org/html/rfc5161#section-3.1).
+///
+/// Capability names are compared case-insensitively.
+#[derive(Debug, Default, Eq, PartialEq)]
+pub struct Enabled {
+    capabilities: HashSet<Capability<'static>>,
+}
+
+impl Enabled {
+    /// Parse the capabilities listed in one or more untagged `ENABLED` responses.
+    pub(crate) fn parse(
+        lines: &[u8],
+        unsolicited: &mut VecDeque<UnsolicitedResponse>,
+    ) -> Result<Self> {
+        let mut caps = HashSet::new();
+        // imap-proto reports `ENABLED` responses as capability listings.
+        parse_many_into(
+            lines,
+            &mut caps,
+            unsolicited,
+            fail,
+            |response| match response {
+                Response::Capabilities(c) => Ok(MapOrNot::MapVec(c)),
+                resp => Ok(MapOrNot::Not(resp)),
+            },
+        )?;
+        Ok(Enabled {
+            capabilities: caps.into_iter().map(Capability::into_owned).collect(),
+        })
+    }
+
+    /// Check if the given extension has been enabled.
+    pub fn has(&self, cap: &Capability<'_>) -> bool {
+        match cap {
+            Capability::Atom(s) => self.has_str(s),
+            cap => self.capabilities.contains(cap),
+        }
+    }
+
+    /// Check if the given extension has been enabled via str.
+    pub fn has_str<S: AsRef<str>>(&self, cap: S) -> bool {
+        let s = cap.as_ref();
+        self.capabilities.iter().any(|c| match c {
+            Capability::Atom(a) => a.eq_ignore_ascii_case(s),
+            Capability::Imap4rev1 => s.eq_ignore_ascii_case("IMAP4rev1"),
+            Capability::Auth(_) => false,
+        })
+    }
+
+    /// Iterate over all the enabled extensions.
+    pub fn iter(&self) -> Iter<'_, Capability<'static>> {
+        self.capabilities.iter()
+    }
+
+    /// Returns how many extensions have been enabled.
+    pub fn len(&self) -> usize {
+        self.capabilities.len()
+    }
+
+    /// Returns true if no extensions have been enabled.
+    pub fn is_empty(&self) -> bool {
+        self.capabilities.is_empty()
+    }
+
+    /// Merge the extensions enabled by a later `ENABLE` command into this set.
+    pub(crate) fn extend(&mut self, other: &Enabled) {
+        self.capabilities
+            .extend(other.capabilities.iter().map(|c| match c {
+                Capability::Imap4rev1 => Capability::Imap4rev1,
+                Capability::Auth(a) => Capability::Auth(a.clone()),
+                Capability::Atom(a) => Capability::Atom(a.clone()),
+            }));
+        // Enabling QRESYNC implies enabling CONDSTORE, see
+        // [RFC 7162, section 3.2.3](https://tools.ietf.org/html/rfc7162#section-3.2.3).
+        if self.has_str(QRESYNC) && !self.has_str(CONDSTORE) {
+            self.capabilities.insert(Capability::Atom(CONDSTORE.into()));
+        }
+    }
+}
+
+/// Formats a capability the way it is sent as an argument of the `ENABLE` command.
+struct EnableArg<'a, 'c>(&'a Capability<'c>);
+
+impl fmt::Display for EnableArg<'_, '_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.0 {
+            Capability::Imap4rev1 => write!(f, "IMAP4rev1"),
+            Capability::Auth(a) => write!(f, "AUTH={}", a),
+            Capability::Atom(a) => write!(f, "{}", a),
+        }
+    }
+}
+
+impl<T: Read + Write> Session<T> {
+    /// The [`ENABLE` command](https://tools.ietf.org/html/rfc5161#section-3.1) asks the server
+    /// to turn on the given extensions for the remainder of this session.
+    ///
+    /// The server only enables the extensions it supports and knows how to enable; the returned
+    /// [`Enabled`] lists exactly the ones that were turned on by this command. Extensions that
+    /// the server ignored are simply absent from it. Every extension enabled so far is also
+    /// recorded on the session and can be queried through [`Session::enabled`].
+    ///
+    /// Note that [enabling `QRESYNC` implicitly enables
+    /// `CONDSTORE`](https://tools.ietf.org/html/rfc7162#section-3.2.3), which is reflected in
+    /// [`Session::enabled`].
+    ///
+    /// This command requires that the server advertises the `ENABLE` capability (see
+    /// [`Session::capabilities`]).
+    ///
+    /// If `capabilities` is empty, nothing is sent to the server and the returned [`Enabled`] is
+    /// empty.
+    pub fn enable(&mut self, capabilities: &[Capability<'_>]) -> Result<Enabled> {
+        if capabilities.is_empty() {
+            return Ok(Enabled::default());
+        }
+
+        let mut args = String::new();
+        for cap in capabilities {
+            let arg = EnableArg(cap).to_string();
+            if let Some(c) = arg
+                .chars()
+                .find(|c| c.is_ascii_whitespace() || c.is_ascii_control())
+            {
+                return Err(Error::Validate(ValidateError {
+                    command_synopsis: "ENABLE".to_string(),
+                    argument: "capabilities".to_string(),
+                    offending_char: c,
+                }));
+            }
+            args.push(' ');
+            args.push_str(&arg);
+        }
+
+        let lines = self.run_command_and_read_response(format!("ENABLE{}", args))?;
+        let enabled = Enabled::parse(&lines, &mut self.unsolicited_responses)?;
+        self.enabled.extend(&enabled);
+        Ok(enabled)
+    }
+
+    /// The set of extensions that have been enabled on this session through
+    /// [`Session::enable`].
+    pub fn enabled(&self) -> &Enabled {
+        &self.enabled
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock_stream::MockStream;
+    use crate::Client;
+    use std::borrow::Cow;
+
+    #[test]
+    fn enable() {
+        let response = b"a1 OK Logged in\r\n\
+            * ENABLED CONDSTORE X-GOOD-IDEA\r\n\
+            a2 OK ENABLE completed\r\n"
+            .to_vec();
+        let mock_stream = MockStream::new(response);
+        let client = Client::new(mock_stream);
+        let mut session = client.login("testuser", "pass").unwrap();
+        assert!(session.enabled().is_empty());
+        let enabled = session
+            .enable(&[
+                Capability::Atom(Cow::Borrowed("CONDSTORE")),
+                Capability::Atom(Cow::Borrowed("X-GOOD-IDEA")),
+                Capability::Atom(Cow::Borrowed("UTF8=ACCEPT")),
+            ])
+            .unwrap();
+        assert!(session
+            .stream
+            .get_ref()
+            .written_buf
+            .ends_with(b"a2 ENABLE CONDSTORE X-GOOD-IDEA UTF8=ACCEPT\r\n"));
+        assert_eq!(enabled.len(), 2);
+        assert!(enabled.has_str("condstore"));
+        assert!(enabled.has(&Capability::Atom(Cow::Borrowed("X-GOOD-IDEA"))));
+        assert!(!enabled.has_str("UTF8=ACCEPT"));
+        assert_eq!(session.enabled(), &enabled);
+    }
+
+    #[test]
+    fn enable_accumulates() {
+        let response = b"a1 OK Logged in\r\n\
+            * ENABLED UTF8=ACCEPT\r\n\
+            a2 OK ENABLE completed\r\n\
+            * ENABLED QRESYNC\r\n\
+            a3 OK ENABLE completed\r\n"
+            .to_vec();
+        let mock_stream = MockStream::new(response);
+        let client = Client::new(mock_stream);
+        let mut session = client.login("testuser", "pass").unwrap();
+        session
+            .enable(&[Capability::Atom(Cow::Borrowed("UTF8=ACCEPT"))])
+            .unwrap();
+        let enabled = session
+            .enable(&[Capability::Atom(Cow::Borrowed("QRESYNC"))])
+            .unwrap();
+        assert_eq!(enabled.len(), 1);
+        assert!(session.enabled().has_str("UTF8=ACCEPT"));
+        assert!(session.enabled().has_str("QRESYNC"));
+        // QRESYNC implies CONDSTORE
+        assert!(session.enabled().has_str("CONDSTORE"));
+        assert_eq!(session.enabled().len(), 3);
+    }
+
+    #[test]
+    fn enable_nothing_enabled() {
+        let response = b"a1 OK Logged in\r\n\
+            a2 OK ENABLE completed\r\n"
+            .to_vec();
+        let mock_stream = MockStream::new(response);
+        let client = Client::new(mock_stream);
+        let mut session = client.login("testuser", "pass").unwrap();
+        let enabled = session
+            .enable(&[Capability::Atom(Cow::Borrowed("X-UNKNOWN"))])
+            .unwrap();
+        assert!(enabled.is_empty());
+        assert!(session.enabled().is_empty());
+    }
+
+    #[test]
+    fn enable_empty() {
+        let response = b"a1 OK Logged in\r\n".to_vec();
+        let mock_stream = MockStream::new(response);
+        let client = Client::new(mock_stream);
+        let mut session = client.login("testuser", "pass").unwrap();
+        let enabled = session.enable(&[]).unwrap();
+        assert!(enabled.is_empty());
+        assert!(session
+            .stream
+            .get_ref()
+            .written_buf
+            .ends_with(b"a1 LOGIN \"testuser\" \"pass\"\r\n"));
+    }
+
+    #[test]
+    fn enable_validation() {
+        crate::client::testutils::assert_validation_error_session(
+            |mut session| session.enable(&[Capability::Atom(Cow::Borrowed("CONDSTORE QRESYNC"))]),
+            "ENABLE",
+            "capabilities",
+            ' ',
+        );
+    }
+}
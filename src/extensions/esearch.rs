@@ -0,0 +1,607 @@
+//! Adds support for the IMAP ESEARCH extension specified in [RFC
+//! 4731](https://tools.ietf.org/html/rfc4731).
+//!
+//! A plain SEARCH returns every matching message number, which is wasteful when the client only
+//! needs, say, how many messages match, or the most recent one. With ESEARCH, the client tells the
+//! server which [`SearchReturn`] data it wants, and the server answers with an `ESEARCH`
+//! response that holds just that, parsed into an [`ESearchResult`]. Even when all matching
+//! messages are asked for, they come back as a compact [`SequenceSet`].
+//!
+//! This module also adds support for the ESORT extension specified in [RFC
+//! 5267](https://tools.ietf.org/html/rfc5267#section-3), which does the same for SORT, and for
+//! the PARTIAL return option specified in [RFC 9394](https://tools.ietf.org/html/rfc9394),
+//! which returns a window of the results for paging through them.
+
+use crate::client::Session;
+use crate::command::Command;
+use crate::error::{ParseError, Result};
+use crate::extensions::sort::{SortCharset, SortCriteria, SortCriterion};
+use crate::parse::{parse_many_into, MapOrNot};
+use crate::types::{SequenceSet, UnsolicitedResponse};
+use imap_proto::parser::core::{atom, nil, number, number_64, sequence_set, string};
+use nom::{
+    branch::alt,
+    bytes::complete::{tag, tag_no_case, take_while1},
+    character::complete::char,
+    combinator::{map, opt, peek, verify},
+    multi::many0,
+    sequence::{delimited, preceded, terminated, tuple},
+    IResult,
+};
+use std::collections::VecDeque;
+use std::fmt;
+use std::io::{Read, Write};
+use std::str::FromStr;
+
+pub(crate) struct SearchReturns<'r>(pub(crate) &'r [SearchReturn]);
+
+impl fmt::Display for SearchReturns<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let returns: Vec<String> = self.0.iter().map(|r| r.to_string()).collect();
+        write!(f, "RETURN ({})", returns.join(" "))
+    }
+}
+
+/// The data [`Session::esearch`] and [`Session::uid_esearch`] ask the server to return, see [RFC
+/// 4731, section 3.1](https://tools.ietf.org/html/rfc4731#section-3.1).
+#[non_exhaustive]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SearchReturn {
+    /// The lowest matching message number or UID, see [`ESearchResult::min`].
+    Min,
+
+    /// The highest matching message number or UID, see [`ESearchResult::max`].
+    Max,
+
+    /// All matching message numbers or UIDs, see [`ESearchResult::all`].
+    All,
+
+    /// The number of matching messages, see [`ESearchResult::count`].
+    Count,
+
+    /// Have the server remember the matching messages, so that later commands can refer to them
+    /// as [`SavedSearch`](crate::extensions::searchres::SavedSearch) without sending them back.
+    /// This requires the `SEARCHRES` capability, see [RFC 5182](https://tools.ietf.org/html/rfc5182).
+    Save,
+
+    /// The matching messages in the given window of the results, see
+    /// [`ESearchResult::partial`]. This requires the `PARTIAL` capability.
+    Partial(PartialRange),
+}
+
+impl fmt::Display for SearchReturn {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        use SearchReturn::*;
+
+        match self {
+            Min => write!(f, "MIN"),
+            Max => write!(f, "MAX"),
+            All => write!(f, "ALL"),
+            Count => write!(f, "COUNT"),
+            Save => write!(f, "SAVE"),
+            Partial(range) => write!(f, "PARTIAL {}", range),
+        }
+    }
+}
+
+/// A window of search results to return with [`SearchReturn::Partial`], see [RFC 9394, section
+/// 3.1](https://tools.ietf.org/html/rfc9394#section-3.1).
+///
+/// Positions are 1-based and refer to the matching messages in the order of the results, that
+/// is, by ascending message number or UID for SEARCH, and in sort order for SORT. A
+/// `PartialRange` can be parsed from its IMAP form, such as `1:50` for the first 50 results or
+/// `-50:-1` for the last 50.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PartialRange {
+    /// The results from the first to the second position, counting from the first result.
+    First(u32, u32),
+
+    /// The results from the first to the second position, counting backwards from the last
+    /// result, which is at position 1. `Last(50, 1)` is written `-50:-1`.
+    Last(u32, u32),
+}
+
+impl fmt::Display for PartialRange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PartialRange::First(from, to) => write!(f, "{}:{}", from, to),
+            PartialRange::Last(from, to) => write!(f, "-{}:-{}", from, to),
+        }
+    }
+}
+
+impl FromStr for PartialRange {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        let invalid = || ParseError::Invalid(s.as_bytes().to_vec());
+        let (from, to) = s.split_once(':').ok_or_else(invalid)?;
+        // Positions are nz-numbers.
+        let number = |n: &str| match n.parse::<u32>() {
+            Ok(n) if n != 0 => Ok(n),
+            _ => Err(invalid()),
+        };
+        match (from.strip_prefix('-'), to.strip_prefix('-')) {
+            (None, None) => Ok(PartialRange::First(number(from)?, number(to)?)),
+            (Some(from), Some(to)) => Ok(PartialRange::Last(number(from)?, number(to)?)),
+            _ => Err(invalid()),
+        }
+    }
+}
+
+/// The window of results returned for [`SearchReturn::Partial`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct PartialResult {
+    /// The window that was asked for.
+    pub range: PartialRange,
+
+    /// The matching messages in that window, which is empty if the window is past the last
+    /// result. Ask for [`SearchReturn::Count`] as well to learn the total number of results.
+    pub messages: SequenceSet,
+}
+
+/// The result of [`Session::esearch`] or [`Session::uid_esearch`].
+///
+/// Only the data that was asked for with [`SearchReturn`] is set, and the server leaves out
+/// [`ESearchResult::min`], [`ESearchResult::max`] and [`ESearchResult::all`] if no messages
+/// match.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct ESearchResult {
+    /// Whether the numbers in this result are [`Uid`](crate::types::Uid)s rather than message
+    /// sequence numbers.
+    pub uid: bool,
+
+    /// The lowest matching message number or UID.
+    pub min: Option<u32>,
+
+    /// The highest matching message number or UID.
+    pub max: Option<u32>,
+
+    /// All matching message numbers or UIDs.
+    pub all: Option<SequenceSet>,
+
+    /// The number of matching messages.
+    pub count: Option<u32>,
+
+    /// The highest mod-sequence of the matching messages, returned if the search criteria
+    /// include `MODSEQ`, see [RFC 7162, section
+    /// 3.1.5](https://tools.ietf.org/html/rfc7162#section-3.1.5).
+    pub mod_seq: Option<u64>,
+
+    /// The matching messages in the window asked for with [`SearchReturn::Partial`].
+    pub partial: Option<PartialResult>,
+}
+
+impl ESearchResult {
+    /// The same result, with the numbers in every range of the returned sets in ascending order.
+    ///
+    /// The order of a sequence-set only matters in the results of SORT, so this is what SEARCH
+    /// results are turned into.
+    pub(crate) fn into_ascending(mut self) -> Self {
+        self.all = self.all.map(SequenceSet::into_ascending);
+        if let Some(partial) = &mut self.partial {
+            partial.messages = std::mem::take(&mut partial.messages).into_ascending();
+        }
+        self
+    }
+}
+
+enum ReturnData {
+    Min(u32),
+    Max(u32),
+    All(SequenceSet),
+    Count(u32),
+    ModSeq(u64),
+    Partial(PartialResult),
+    Other,
+}
+
+// nz-number = digit-nz *DIGIT
+fn nz_number(i: &[u8]) -> IResult<&[u8], u32> {
+    verify(number, |&n| n != 0)(i)
+}
+
+// partial-range-first = nz-number ":" nz-number
+// partial-range-last  = MINUS nz-number ":" MINUS nz-number
+fn partial_range(i: &[u8]) -> IResult<&[u8], PartialRange> {
+    alt((
+        map(
+            tuple((char('-'), nz_number, tag(":-"), nz_number)),
+            |(_, from, _, to)| PartialRange::Last(from, to),
+        ),
+        map(tuple((nz_number, char(':'), nz_number)), |(from, _, to)| {
+            PartialRange::First(from, to)
+        }),
+    ))(i)
+}
+
+// ret-data-partial = "PARTIAL" SP "(" partial-range SP partial-results ")"
+// partial-results = sequence-set / "NIL"
+fn partial_result(i: &[u8]) -> IResult<&[u8], PartialResult> {
+    map(
+        delimited(
+            char('('),
+            tuple((
+                terminated(partial_range, char(' ')),
+                alt((
+                    map(nil, |_| SequenceSet::default()),
+                    map(sequence_set, SequenceSet::in_order),
+                )),
+            )),
+            char(')'),
+        ),
+        |(range, messages)| PartialResult { range, messages },
+    )(i)
+}
+
+// tagged-ext-val = tagged-ext-simple / "(" [tagged-ext-comp] ")"
+//
+// Values of extension data this crate does not know about are skipped.
+pub(crate) fn tagged_ext_val(i: &[u8]) -> IResult<&[u8], ()> {
+    alt((
+        map(
+            delimited(
+                char('('),
+                many0(alt((
+                    map(string, |_| ()),
+                    tagged_ext_val,
+                    map(take_while1(|b| !b"()\"\r\n".contains(&b)), |_| ()),
+                ))),
+                char(')'),
+            ),
+            |_| (),
+        ),
+        map(take_while1(|b| !b" ()\r\n".contains(&b)), |_| ()),
+    ))(i)
+}
+
+// search-return-data = search-modifier-name SP search-return-value
+fn return_data(i: &[u8]) -> IResult<&[u8], ReturnData> {
+    let (i, name) = terminated(atom, char(' '))(i)?;
+    match name.to_ascii_uppercase().as_str() {
+        "MIN" => map(number, ReturnData::Min)(i),
+        "MAX" => map(number, ReturnData::Max)(i),
+        "ALL" => map(sequence_set, |set| {
+            ReturnData::All(SequenceSet::in_order(set))
+        })(i),
+        "COUNT" => map(number, ReturnData::Count)(i),
+        "MODSEQ" => map(number_64, ReturnData::ModSeq)(i),
+        "PARTIAL" => map(partial_result, ReturnData::Partial)(i),
+        _ => map(tagged_ext_val, |_| ReturnData::Other)(i),
+    }
+}
+
+// esearch-response = "ESEARCH" [search-correlator] [SP "UID"] *(SP search-return-data)
+// search-correlator = SP "(" "TAG" SP tag-string ")"
+pub(crate) fn esearch_response(i: &[u8]) -> IResult<&[u8], ESearchResult> {
+    let (i, _) = tag_no_case("* ESEARCH")(i)?;
+    let (i, _) = opt(tuple((tag_no_case(" (TAG "), string, char(')'))))(i)?;
+    let (i, uid) = opt(terminated(
+        tag_no_case(" UID"),
+        peek(alt((tag(" "), tag("\r\n")))),
+    ))(i)?;
+    let (i, data) = many0(preceded(char(' '), return_data))(i)?;
+    let (i, _) = tag("\r\n")(i)?;
+
+    let mut result = ESearchResult {
+        uid: uid.is_some(),
+        ..ESearchResult::default()
+    };
+    for d in data {
+        match d {
+            ReturnData::Min(n) => result.min = Some(n),
+            ReturnData::Max(n) => result.max = Some(n),
+            ReturnData::All(set) => result.all = Some(set),
+            ReturnData::Count(n) => result.count = Some(n),
+            ReturnData::ModSeq(n) => result.mod_seq = Some(n),
+            ReturnData::Partial(p) => result.partial = Some(p),
+            ReturnData::Other => {}
+        }
+    }
+    Ok((i, result))
+}
+
+/// Parse the `ESEARCH` response to a SEARCH or SORT command in a response buffer.
+///
+/// The server does not send one if [`SearchReturn::Save`] is the only return option, in which
+/// case the result is empty.
+pub(crate) fn parse_esearch(
+    lines: &[u8],
+    unsolicited: &mut VecDeque<UnsolicitedResponse>,
+) -> Result<ESearchResult> {
+    let mut results = Vec::new();
+    parse_many_into(
+        lines,
+        &mut results,
+        unsolicited,
+        map(esearch_response, MapOrNot::Map),
+        |response| Ok(MapOrNot::Not(response)),
+    )?;
+    Ok(results.pop().unwrap_or_default())
+}
+
+impl<T: Read + Write> Session<T> {
+    /// This issues the [SEARCH command](https://tools.ietf.org/html/rfc4731#section-3.1) with
+    /// the given return options, which returns only the requested data about the messages
+    /// matching the search criteria.
+    ///
+    /// This command is like [`Session::search`], except that the server does not list every
+    /// matching message. Instead, it returns what `returns` asks for, such as the number of
+    /// matching messages ([`SearchReturn::Count`]) or the highest matching message number
+    /// ([`SearchReturn::Max`]). If `returns` is empty, the server returns
+    /// [`ESearchResult::all`].
+    ///
+    /// This command requires that the server advertises the `ESEARCH` capability (see
+    /// [`Session::capabilities`]).
+    ///
+    /// ```no_run
+    /// # use imap::extensions::esearch::SearchReturn;
+    /// # fn example(session: &mut imap::Session<std::net::TcpStream>) -> imap::Result<()> {
+    /// let unseen = session.esearch(&[SearchReturn::Count], "UNSEEN")?;
+    /// println!("{} unread messages", unseen.count.unwrap_or(0));
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn esearch(
+        &mut self,
+        returns: &[SearchReturn],
+        query: impl AsRef<str>,
+    ) -> Result<ESearchResult> {
+        self.run_esearch(
+            format!("SEARCH {} ", SearchReturns(returns)),
+            query.as_ref(),
+        )
+        .map(ESearchResult::into_ascending)
+    }
+
+    /// Equivalent to [`Session::esearch`], except that the returned identifiers are
+    /// [`Uid`](crate::types::Uid)s.
+    ///
+    /// See also [`Session::uid_search`].
+    pub fn uid_esearch(
+        &mut self,
+        returns: &[SearchReturn],
+        query: impl AsRef<str>,
+    ) -> Result<ESearchResult> {
+        self.run_esearch(
+            format!("UID SEARCH {} ", SearchReturns(returns)),
+            query.as_ref(),
+        )
+        .map(ESearchResult::into_ascending)
+    }
+
+    /// This issues the [SORT command](https://tools.ietf.org/html/rfc5267#section-3.2) with the
+    /// given return options, which returns only the requested data about the sorted search
+    /// results.
+    ///
+    /// This command is to [`Session::sort`] what [`Session::esearch`] is to [`Session::search`].
+    /// [`SearchReturn::Min`] and [`SearchReturn::Max`] refer to the first and last message in
+    /// sort order, and [`ESearchResult::all`] lists the messages in sort order.
+    ///
+    /// This command requires that the server advertises the `ESORT` capability, and the
+    /// `PARTIAL` capability to use [`SearchReturn::Partial`] (see [`Session::capabilities`]).
+    ///
+    /// ```no_run
+    /// # use imap::extensions::esearch::{PartialRange, SearchReturn};
+    /// # use imap::extensions::sort::{SortCharset, SortCriterion};
+    /// # fn example(session: &mut imap::Session<std::net::TcpStream>) -> imap::Result<()> {
+    /// // The second page of 50 messages, newest first.
+    /// let page = session.uid_esort(
+    ///     &[SearchReturn::Partial(PartialRange::First(51, 100)), SearchReturn::Count],
+    ///     &[SortCriterion::Reverse(&SortCriterion::Arrival)],
+    ///     SortCharset::Utf8,
+    ///     "ALL",
+    /// )?;
+    /// let total = page.count.unwrap_or(0);
+    /// let uids = page.partial.map(|p| p.messages).unwrap_or_default();
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn esort(
+        &mut self,
+        returns: &[SearchReturn],
+        criteria: &[SortCriterion<'_>],
+        charset: SortCharset<'_>,
+        query: impl AsRef<str>,
+    ) -> Result<ESearchResult> {
+        self.run_esearch(
+            format!(
+                "SORT {} {} {} ",
+                SearchReturns(returns),
+                SortCriteria(criteria),
+                charset
+            ),
+            query.as_ref(),
+        )
+    }
+
+    /// Equivalent to [`Session::esort`], except that the returned identifiers are
+    /// [`Uid`](crate::types::Uid)s.
+    ///
+    /// See also [`Session::uid_sort`].
+    pub fn uid_esort(
+        &mut self,
+        returns: &[SearchReturn],
+        criteria: &[SortCriterion<'_>],
+        charset: SortCharset<'_>,
+        query: impl AsRef<str>,
+    ) -> Result<ESearchResult> {
+        self.run_esearch(
+            format!(
+                "UID SORT {} {} {} ",
+                SearchReturns(returns),
+                SortCriteria(criteria),
+                charset
+            ),
+            query.as_ref(),
+        )
+    }
+
+    fn run_esearch(&mut self, command: String, query: &str) -> Result<ESearchResult> {
+        let mut command = Command::new(command);
+        command.push_criteria(query);
+        self.execute_and_read_response(&command)
+            .and_then(|lines| parse_esearch(&lines, &mut self.unsolicited_responses))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::Error;
+    use crate::mock_stream::MockStream;
+    use crate::Client;
+
+    #[test]
+    fn parse_esearch_test() {
+        let lines = b"* ESEARCH (TAG \"A285\") UID MIN 7 MAX 3800\r\n";
+        let mut queue = VecDeque::new();
+        let result = parse_esearch(lines, &mut queue).unwrap();
+        assert_eq!(queue.pop_front(), None);
+        assert!(result.uid);
+        assert_eq!(result.min, Some(7));
+        assert_eq!(result.max, Some(3800));
+        assert_eq!(result.count, None);
+        assert_eq!(result.all, None);
+    }
+
+    #[test]
+    fn parse_esearch_all() {
+        let lines = b"* 3 EXPUNGE\r\n\
+            * ESEARCH (TAG \"A283\") ALL 2,10:11,17:15 COUNT 6 X-UNKNOWN (1 (\"a)\" b)) MODSEQ 917162500\r\n";
+        let mut queue = VecDeque::new();
+        let result = parse_esearch(lines, &mut queue).unwrap().into_ascending();
+        assert_eq!(queue.pop_front(), Some(UnsolicitedResponse::Expunge(3)));
+        assert!(!result.uid);
+        assert_eq!(result.count, Some(6));
+        assert_eq!(result.mod_seq, Some(917162500));
+        let all = result.all.unwrap();
+        assert_eq!(all.ranges(), &[2..=2, 10..=11, 15..=17]);
+        assert_eq!(all.to_string(), "2,10:11,15:17");
+        assert_eq!(all.len(), 6);
+        assert!(all.contains(16));
+        assert!(!all.contains(12));
+        assert_eq!(all.iter().collect::<Vec<_>>(), vec![2, 10, 11, 15, 16, 17]);
+    }
+
+    #[test]
+    fn parse_esearch_nothing_found() {
+        let mut queue = VecDeque::new();
+        let result = parse_esearch(b"* ESEARCH (TAG \"A284\")\r\n", &mut queue).unwrap();
+        assert_eq!(result, ESearchResult::default());
+        let result = parse_esearch(b"* ESEARCH UID COUNT 0\r\n", &mut queue).unwrap();
+        assert!(result.uid);
+        assert_eq!(result.count, Some(0));
+    }
+
+    #[test]
+    fn parse_esearch_partial() {
+        // Examples from RFC 9394, section 3.1.
+        let lines = b"* ESEARCH (TAG \"A01\") UID PARTIAL (-1:-100 200:250,252:300)\r\n";
+        let mut queue = VecDeque::new();
+        let partial = parse_esearch(lines, &mut queue).unwrap().partial.unwrap();
+        assert_eq!(partial.range, PartialRange::Last(1, 100));
+        assert_eq!(partial.messages.to_string(), "200:250,252:300");
+        assert_eq!(partial.messages.len(), 100);
+
+        let lines = b"* ESEARCH (TAG \"A02\") UID PARTIAL (23500:24000 NIL) COUNT 23100\r\n";
+        let result = parse_esearch(lines, &mut queue).unwrap();
+        let partial = result.partial.unwrap();
+        assert_eq!(partial.range, PartialRange::First(23500, 24000));
+        assert!(partial.messages.is_empty());
+        assert_eq!(result.count, Some(23100));
+
+        let lines = b"* ESEARCH (TAG \"A03\") UID PARTIAL (0:10 NIL)\r\n";
+        assert!(matches!(
+            parse_esearch(lines, &mut queue),
+            Err(Error::Parse(ParseError::Invalid(_)))
+        ));
+    }
+
+    #[test]
+    fn partial_range() {
+        assert_eq!(
+            "1:50".parse::<PartialRange>().unwrap(),
+            PartialRange::First(1, 50)
+        );
+        assert_eq!(
+            "-50:-1".parse::<PartialRange>().unwrap(),
+            PartialRange::Last(50, 1)
+        );
+        assert!("-50:1".parse::<PartialRange>().is_err());
+        assert!("0:50".parse::<PartialRange>().is_err());
+        assert!("-0:-1".parse::<PartialRange>().is_err());
+        assert!("1:50 ".parse::<PartialRange>().is_err());
+        assert_eq!(PartialRange::Last(50, 1).to_string(), "-50:-1");
+    }
+
+    #[test]
+    fn uid_esort_partial() {
+        let response = b"a1 OK [CAPABILITY IMAP4rev1 ESORT PARTIAL] Logged in\r\n\
+            * ESEARCH (TAG \"a2\") UID COUNT 800 PARTIAL (1:3 17:15,42)\r\n\
+            a2 OK SORT completed\r\n"
+            .to_vec();
+        let mock_stream = MockStream::new(response);
+        let client = Client::new(mock_stream);
+        let mut session = client.login("username", "password").unwrap();
+        let result = session
+            .uid_esort(
+                &[
+                    SearchReturn::Partial("1:4".parse().unwrap()),
+                    SearchReturn::Count,
+                ],
+                &[SortCriterion::Reverse(&SortCriterion::Date)],
+                SortCharset::Utf8,
+                "UNDELETED",
+            )
+            .unwrap();
+        assert!(session.stream.get_ref().written_buf.ends_with(
+            b"a2 UID SORT RETURN (PARTIAL 1:4 COUNT) (REVERSE DATE) UTF-8 UNDELETED\r\n"
+        ));
+        assert_eq!(result.count, Some(800));
+        let messages = result.partial.unwrap().messages;
+        assert_eq!(messages.ranges(), &[15..=17, 42..=42]);
+        assert_eq!(messages.to_string(), "17:15,42");
+        assert_eq!(messages.iter().collect::<Vec<_>>(), vec![17, 16, 15, 42]);
+    }
+
+    #[test]
+    fn uid_esearch() {
+        let response = b"a1 OK [CAPABILITY IMAP4rev1 ESEARCH] Logged in\r\n\
+            * ESEARCH (TAG \"a2\") UID MIN 4 COUNT 2\r\n\
+            a2 OK SEARCH completed\r\n"
+            .to_vec();
+        let mock_stream = MockStream::new(response);
+        let client = Client::new(mock_stream);
+        let mut session = client.login("username", "password").unwrap();
+        let result = session
+            .uid_esearch(&[SearchReturn::Min, SearchReturn::Count], "UNSEEN")
+            .unwrap();
+        assert!(session
+            .stream
+            .get_ref()
+            .written_buf
+            .ends_with(b"a2 UID SEARCH RETURN (MIN COUNT) UNSEEN\r\n"));
+        assert_eq!(result.min, Some(4));
+        assert_eq!(result.count, Some(2));
+    }
+
+    #[test]
+    fn esearch_save() {
+        let response = b"a1 OK [CAPABILITY IMAP4rev1 ESEARCH SEARCHRES] Logged in\r\n\
+            a2 OK SEARCH completed\r\n"
+            .to_vec();
+        let mock_stream = MockStream::new(response);
+        let client = Client::new(mock_stream);
+        let mut session = client.login("username", "password").unwrap();
+        let result = session.esearch(&[SearchReturn::Save], "DELETED").unwrap();
+        assert!(session
+            .stream
+            .get_ref()
+            .written_buf
+            .ends_with(b"a2 SEARCH RETURN (SAVE) DELETED\r\n"));
+        assert_eq!(result, ESearchResult::default());
+    }
+}
@@ -0,0 +1,216 @@
+//! Adds support for the IMAP ID extension specified in [RFC
+//! 2971](https://tools.ietf.org/html/rfc2971).
+//!
+//! The `ID` command lets the client and the server exchange information about their
+//! implementations (name, version, vendor, ...), which is mostly useful for diagnostics. Some
+//! servers refuse to work with clients that do not identify themselves, so [`Client::id`] can be
+//! used before logging in.
+
+use crate::client::{validate_str, Client, Session};
+use crate::error::Result;
+use crate::parse::{parse_many_into, MapOrNot};
+use crate::types::UnsolicitedResponse;
+use imap_proto::parser::core::{nil, nstring_utf8, parenthesized_list, string_utf8};
+use nom::{
+    branch::alt,
+    bytes::streaming::{tag, tag_no_case},
+    character::streaming::char,
+    combinator::map,
+    sequence::{preceded, separated_pair, terminated},
+    IResult,
+};
+use std::collections::{HashMap, VecDeque};
+use std::io::{Read, Write};
+
+/// The field/value pairs of an `ID` response. A value of `None` was sent as `NIL`.
+pub type IdFields = HashMap<String, Option<String>>;
+
+// id_response ::= "ID" SPACE id_params_list
+// id_params_list ::= "(" #(string SPACE nstring) ")" / nil
+//
+// imap-proto parses this as well, but silently drops fields with a `NIL` value.
+fn id_response(i: &[u8]) -> IResult<&[u8], Vec<(&str, Option<&str>)>> {
+    preceded(
+        tag_no_case("* ID "),
+        alt((
+            map(nil, |_| Vec::new()),
+            parenthesized_list(separated_pair(string_utf8, char(' '), nstring_utf8)),
+        )),
+    )(i)
+}
+
+fn parse_id(lines: &[u8], unsolicited: &mut VecDeque<UnsolicitedResponse>) -> Result<IdFields> {
+    let mut fields = IdFields::new();
+    parse_many_into(
+        lines,
+        &mut fields,
+        unsolicited,
+        map(terminated(id_response, tag("\r\n")), |params| {
+            MapOrNot::MapVec(
+                params
+                    .into_iter()
+                    .map(|(k, v)| (k.to_string(), v.map(str::to_string)))
+                    .collect(),
+            )
+        }),
+        |response| Ok(MapOrNot::Not(response)),
+    )?;
+    Ok(fields)
+}
+
+/// Formats the client's fields as the argument of the `ID` command.
+fn id_params<K, V>(fields: impl IntoIterator<Item = (K, Option<V>)>) -> Result<String>
+where
+    K: AsRef<str>,
+    V: AsRef<str>,
+{
+    let mut params = Vec::new();
+    for (field, value) in fields {
+        params.push(format!(
+            "{} {}",
+            validate_str("ID", "field", field.as_ref())?,
+            match value {
+                Some(v) => validate_str("ID", "value", v.as_ref())?,
+                None => "NIL".to_string(),
+            }
+        ));
+    }
+    if params.is_empty() {
+        Ok("NIL".to_string())
+    } else {
+        Ok(format!("({})", params.join(" ")))
+    }
+}
+
+impl<T: Read + Write> Client<T> {
+    /// The [`ID` command](https://tools.ietf.org/html/rfc2971#section-3.1) sends information
+    /// about the client to the server, and returns the information the server sent back about
+    /// itself.
+    ///
+    /// `fields` are field/value pairs such as `("name", Some("my-client"))`; a value of `None` is
+    /// sent as `NIL`. If `fields` is empty, `NIL` is sent instead of a field list. Likewise, the
+    /// returned map is empty if the server answers with `NIL`, and contains `None` for each field
+    /// whose value was `NIL`. The field names defined by the RFC are `name`, `version`, `os`,
+    /// `os-version`, `vendor`, `support-url`, `address`, `date`, `command`, `arguments` and
+    /// `environment`.
+    ///
+    /// This can be used before authenticating, as some servers require the client to identify
+    /// itself first.
+    ///
+    /// ```no_run
+    /// # {} #[cfg(feature = "native-tls")]
+    /// # fn main() {
+    /// let mut client = imap::ClientBuilder::new("imap.example.com", 993)
+    ///     .connect().unwrap();
+    /// let server = client
+    ///     .id([("name", Some("my-client")), ("version", Some("1.0"))])
+    ///     .unwrap();
+    /// if let Some(Some(name)) = server.get("name") {
+    ///     println!("talking to {}", name);
+    /// }
+    /// # }
+    /// ```
+    ///
+    /// This requires that the server supports the `ID` capability.
+    pub fn id<K, V>(&mut self, fields: impl IntoIterator<Item = (K, Option<V>)>) -> Result<IdFields>
+    where
+        K: AsRef<str>,
+        V: AsRef<str>,
+    {
+        // Create a temporary vec deque as we do not care about out of band responses before login
+        let mut unsolicited_responses = VecDeque::new();
+        let lines = self.run_command_and_read_response(&format!("ID {}", id_params(fields)?))?;
+        parse_id(&lines, &mut unsolicited_responses)
+    }
+}
+
+impl<T: Read + Write> Session<T> {
+    /// Equivalent to [`Client::id`], for use after authenticating.
+    pub fn id<K, V>(&mut self, fields: impl IntoIterator<Item = (K, Option<V>)>) -> Result<IdFields>
+    where
+        K: AsRef<str>,
+        V: AsRef<str>,
+    {
+        let lines = self.run_command_and_read_response(format!("ID {}", id_params(fields)?))?;
+        parse_id(&lines, &mut self.unsolicited_responses)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::client::testutils::assert_validation_error_session;
+    use crate::mock_stream::MockStream;
+
+    #[test]
+    fn client_id() {
+        let response = b"* ID (\"name\" \"Cyrus\" \"version\" \"1.5\" \"os\" NIL)\r\n\
+            a1 OK ID completed\r\n"
+            .to_vec();
+        let mock_stream = MockStream::new(response);
+        let mut client = Client::new(mock_stream);
+        let server = client
+            .id([
+                ("name", Some("sodr")),
+                ("version", Some("19.34")),
+                ("os", None),
+            ])
+            .unwrap();
+        assert_eq!(
+            client.stream.get_ref().written_buf,
+            b"a1 ID (\"name\" \"sodr\" \"version\" \"19.34\" \"os\" NIL)\r\n".to_vec()
+        );
+        assert_eq!(server.len(), 3);
+        assert_eq!(server["name"].as_deref(), Some("Cyrus"));
+        assert_eq!(server["version"].as_deref(), Some("1.5"));
+        assert_eq!(server["os"], None);
+    }
+
+    #[test]
+    fn client_id_nil() {
+        let response = b"* ID NIL\r\n\
+            a1 OK ID completed\r\n"
+            .to_vec();
+        let mock_stream = MockStream::new(response);
+        let mut client = Client::new(mock_stream);
+        let server = client.id(Vec::<(&str, Option<&str>)>::new()).unwrap();
+        assert_eq!(
+            client.stream.get_ref().written_buf,
+            b"a1 ID NIL\r\n".to_vec()
+        );
+        assert!(server.is_empty());
+    }
+
+    #[test]
+    fn session_id() {
+        let response = b"a1 OK Logged in\r\n\
+            * 4 EXISTS\r\n\
+            * ID (\"name\" \"Dovecot\")\r\n\
+            a2 OK ID completed\r\n"
+            .to_vec();
+        let mock_stream = MockStream::new(response);
+        let client = Client::new(mock_stream);
+        let mut session = client.login("testuser", "pass").unwrap();
+        let server = session.id([("name", Some("sodr"))]).unwrap();
+        assert!(session
+            .stream
+            .get_ref()
+            .written_buf
+            .ends_with(b"a2 ID (\"name\" \"sodr\")\r\n"));
+        assert_eq!(server["name"].as_deref(), Some("Dovecot"));
+        assert_eq!(
+            session.take_all_unsolicited().collect::<Vec<_>>(),
+            vec![UnsolicitedResponse::Exists(4)]
+        );
+    }
+
+    #[test]
+    fn session_id_validation() {
+        assert_validation_error_session(
+            |mut session| session.id([("name", Some("sodr\r\n"))]),
+            "ID",
+            "value",
+            '\r',
+        );
+    }
+}
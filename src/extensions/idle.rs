@@ -153,7 +153,10 @@ impl<'a, T: Read + Write + 'a> Handle<'a, T> {
                         // Something went wrong parsing.
                         (_rest, Some(Err(r))) => break Err(r),
                         // Complete response. We expect rest to be empty.
-                        (rest, Some(Ok(response))) => {
+                        (rest, Some(Ok(mut response))) => {
+                            if !self.session.utf8_accepted() {
+                                response.decode_utf7();
+                            }
                             if !callback(response) {
                                 break Ok(WaitOutcome::MailboxChanged);
                             }
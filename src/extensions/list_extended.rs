@@ -0,0 +1,454 @@
+//! Adds support for the IMAP LIST-EXTENDED extension specified in [RFC
+//! 5258](https://tools.ietf.org/html/rfc5258).
+//!
+//! LIST-EXTENDED turns `LIST` into a more capable command: it can match several patterns at once,
+//! select mailboxes by other criteria than their name ([`ListSelection`]), and return more data
+//! about each of them ([`ListReturn`]), such as whether they are subscribed or have children, or
+//! their status (see [RFC 5819](https://tools.ietf.org/html/rfc5819)).
+//! [`Session::list_extended`] returns a [`ListCmd`] builder for such a command.
+
+use crate::client::{validate_str, Session};
+use crate::error::Result;
+use crate::extensions::esearch::tagged_ext_val;
+use crate::extensions::list_status::ExtendedNames;
+use crate::parse::astring_utf8;
+use crate::types::Name;
+use crate::utils::iter_join;
+use imap_proto::parser::core::{is_atom_char, nil, parenthesized_list, quoted_utf8};
+use imap_proto::NameAttribute;
+use nom::{
+    branch::alt,
+    bytes::complete::{tag, tag_no_case, take_while},
+    character::complete::char,
+    combinator::{map, map_res, opt, recognize},
+    sequence::{delimited, pair, preceded, terminated},
+    IResult,
+};
+use std::borrow::Cow;
+use std::fmt;
+use std::io::{Read, Write};
+use std::str::from_utf8;
+
+/// Selection options for [`ListCmd::select`], which select the mailboxes [`ListCmd`] returns
+/// by other criteria than their name. See [RFC 5258, section
+/// 3.1](https://tools.ietf.org/html/rfc5258#section-3.1).
+#[non_exhaustive]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ListSelection {
+    /// Only return subscribed mailboxes, including those that do not exist. This implies
+    /// [`ListReturn::Subscribed`].
+    Subscribed,
+
+    /// Also return mailboxes on other servers, see [RFC 2193](https://tools.ietf.org/html/rfc2193).
+    Remote,
+
+    /// Also return mailboxes that do not match the other selection options themselves, but have
+    /// children that do, with the reason in [`Name::child_info`]. This must be combined with
+    /// another selection option.
+    RecursiveMatch,
+
+    /// Only return mailboxes that have a special use, see [RFC
+    /// 6154](https://tools.ietf.org/html/rfc6154#section-5.1). This implies
+    /// [`ListReturn::SpecialUse`].
+    SpecialUse,
+}
+
+impl fmt::Display for ListSelection {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        use ListSelection::*;
+
+        match self {
+            Subscribed => write!(f, "SUBSCRIBED"),
+            Remote => write!(f, "REMOTE"),
+            RecursiveMatch => write!(f, "RECURSIVEMATCH"),
+            SpecialUse => write!(f, "SPECIAL-USE"),
+        }
+    }
+}
+
+/// Return options for [`ListCmd::return_option`], which ask for more data about each mailbox
+/// returned by [`ListCmd`]. See [RFC 5258, section
+/// 3.2](https://tools.ietf.org/html/rfc5258#section-3.2).
+#[non_exhaustive]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ListReturn<'a> {
+    /// Whether the mailbox has children, see [`Name::has_children`].
+    Children,
+
+    /// Whether the mailbox is subscribed, see [`Name::is_subscribed`].
+    Subscribed,
+
+    /// The status of the mailbox. The argument has the same semantics as `data_items` in
+    /// [`Session::status`], for example `"(MESSAGES UNSEEN)"`. This requires the `LIST-STATUS`
+    /// capability, see [RFC 5819](https://tools.ietf.org/html/rfc5819).
+    Status(&'a str),
+
+    /// The special use of the mailbox, see [`Name::special_use`]. This requires the `SPECIAL-USE`
+    /// capability.
+    SpecialUse,
+}
+
+impl fmt::Display for ListReturn<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        use ListReturn::*;
+
+        match self {
+            Children => write!(f, "CHILDREN"),
+            Subscribed => write!(f, "SUBSCRIBED"),
+            Status(data_items) => write!(f, "STATUS {}", data_items),
+            SpecialUse => write!(f, "SPECIAL-USE"),
+        }
+    }
+}
+
+/// A builder for an extended `LIST` command, see [`Session::list_extended`].
+#[must_use]
+pub struct ListCmd<'a, T: Read + Write> {
+    session: &'a mut Session<T>,
+    reference: &'a str,
+    patterns: Vec<&'a str>,
+    selection: Vec<ListSelection>,
+    returns: Vec<ListReturn<'a>>,
+}
+
+impl<'a, T: Read + Write> ListCmd<'a, T> {
+    /// Set the reference name, which has the same semantics as `reference_name` in
+    /// [`Session::list`]. It is empty by default.
+    pub fn reference(&mut self, reference_name: &'a str) -> &mut Self {
+        self.reference = reference_name;
+        self
+    }
+
+    /// Add a mailbox name pattern, which has the same semantics as `mailbox_pattern` in
+    /// [`Session::list`]. Mailboxes that match any of the patterns are returned. If no pattern is
+    /// added, `*` is used.
+    pub fn pattern(&mut self, mailbox_pattern: &'a str) -> &mut Self {
+        self.patterns.push(mailbox_pattern);
+        self
+    }
+
+    /// Add several mailbox name patterns at once.
+    pub fn patterns(&mut self, mailbox_patterns: impl IntoIterator<Item = &'a str>) -> &mut Self {
+        self.patterns.extend(mailbox_patterns);
+        self
+    }
+
+    /// Add a selection option.
+    pub fn select(&mut self, selection: ListSelection) -> &mut Self {
+        self.selection.push(selection);
+        self
+    }
+
+    /// Add a return option.
+    pub fn return_option(&mut self, return_option: ListReturn<'a>) -> &mut Self {
+        self.returns.push(return_option);
+        self
+    }
+
+    /// Finishes up the command and executes it.
+    ///
+    /// Each returned [`Name`] is paired with its status if [`ListReturn::Status`] was asked for
+    /// and the server could look it up.
+    pub fn finish(&mut self) -> Result<ExtendedNames> {
+        let synopsis = "LIST";
+        let mut command = String::from("LIST ");
+        if !self.selection.is_empty() {
+            command.push_str(&format!("({}) ", iter_join(&self.selection, " ")));
+        }
+        command.push_str(&validate_str(
+            synopsis,
+            "reference",
+            &self.session.encode_mailbox_name(self.reference),
+        )?);
+        let patterns = if self.patterns.is_empty() {
+            vec!["\"*\"".to_string()]
+        } else {
+            self.patterns
+                .iter()
+                .map(|pattern| {
+                    validate_str(
+                        synopsis,
+                        "pattern",
+                        &self.session.encode_mailbox_name(pattern),
+                    )
+                })
+                .collect::<Result<_>>()?
+        };
+        match &patterns[..] {
+            [pattern] => command.push_str(&format!(" {}", pattern)),
+            patterns => command.push_str(&format!(" ({})", patterns.join(" "))),
+        }
+        if !self.returns.is_empty() {
+            command.push_str(&format!(" RETURN ({})", iter_join(&self.returns, " ")));
+        }
+
+        let lines = self.session.run_command_and_read_response(command)?;
+        let mut names = ExtendedNames::parse(lines, &mut self.session.unsolicited_responses)?;
+        self.session.decode_extended_names(&mut names);
+        Ok(names)
+    }
+}
+
+impl Name<'_> {
+    /// Whether this mailbox has children, as returned for [`ListReturn::Children`], or `None` if
+    /// the server did not say.
+    pub fn has_children(&self) -> Option<bool> {
+        self.attributes()
+            .iter()
+            .find_map(|attribute| match attribute {
+                NameAttribute::NoInferiors => Some(false),
+                NameAttribute::Extension(a) if a.eq_ignore_ascii_case("\\HasChildren") => {
+                    Some(true)
+                }
+                NameAttribute::Extension(a) if a.eq_ignore_ascii_case("\\HasNoChildren") => {
+                    Some(false)
+                }
+                _ => None,
+            })
+    }
+
+    /// Whether this mailbox is subscribed, as returned for [`ListReturn::Subscribed`] and
+    /// [`ListSelection::Subscribed`].
+    pub fn is_subscribed(&self) -> bool {
+        self.has_extension_attribute("\\Subscribed")
+    }
+
+    /// Whether this mailbox does not exist, which is the case for instance for subscribed
+    /// mailboxes that have been deleted.
+    pub fn is_non_existent(&self) -> bool {
+        self.has_extension_attribute("\\NonExistent")
+    }
+
+    /// The reasons why this mailbox was returned by a [`ListCmd`] with
+    /// [`ListSelection::RecursiveMatch`] although it does not match the selection options itself.
+    /// For example, `SUBSCRIBED` means that some of its children are subscribed.
+    pub fn child_info(&self) -> impl Iterator<Item = &str> {
+        self.child_info.iter().map(|info| &**info)
+    }
+
+    fn has_extension_attribute(&self, name: &str) -> bool {
+        self.attributes().iter().any(|attribute| {
+            matches!(attribute, NameAttribute::Extension(a) if a.eq_ignore_ascii_case(name))
+        })
+    }
+}
+
+// mbx-list-flag = "\All" / "\Archive" / ... / flag-extension
+fn name_attribute(i: &[u8]) -> IResult<&[u8], NameAttribute<'_>> {
+    map(
+        map_res(
+            recognize(pair(char('\\'), take_while(is_atom_char))),
+            from_utf8,
+        ),
+        |a| match a.to_ascii_lowercase().as_str() {
+            "\\noinferiors" => NameAttribute::NoInferiors,
+            "\\noselect" => NameAttribute::NoSelect,
+            "\\marked" => NameAttribute::Marked,
+            "\\unmarked" => NameAttribute::Unmarked,
+            "\\all" => NameAttribute::All,
+            "\\archive" => NameAttribute::Archive,
+            "\\drafts" => NameAttribute::Drafts,
+            "\\flagged" => NameAttribute::Flagged,
+            "\\junk" => NameAttribute::Junk,
+            "\\sent" => NameAttribute::Sent,
+            "\\trash" => NameAttribute::Trash,
+            _ => NameAttribute::Extension(Cow::Borrowed(a)),
+        },
+    )(i)
+}
+
+enum ExtendedItem<'a> {
+    ChildInfo(Vec<&'a str>),
+    OldName(&'a str),
+    Other,
+}
+
+// mbox-list-extended-item = mbox-list-extended-item-tag SP tagged-ext-val
+//
+// Only CHILDINFO and OLDNAME (RFC 5465, section 5.4) are kept, other extended data is skipped.
+fn extended_item(i: &[u8]) -> IResult<&[u8], ExtendedItem<'_>> {
+    let (i, item) = terminated(astring_utf8, char(' '))(i)?;
+    if item.eq_ignore_ascii_case("CHILDINFO") {
+        map(parenthesized_list(astring_utf8), ExtendedItem::ChildInfo)(i)
+    } else if item.eq_ignore_ascii_case("OLDNAME") {
+        map(
+            delimited(char('('), astring_utf8, char(')')),
+            ExtendedItem::OldName,
+        )(i)
+    } else {
+        map(tagged_ext_val, |_| ExtendedItem::Other)(i)
+    }
+}
+
+// mailbox-list = "(" [mbx-list-flags] ")" SP (DQUOTE QUOTED-CHAR DQUOTE / nil) SP mailbox
+//                [SP mbox-list-extended]
+// mbox-list-extended = "(" [mbox-list-extended-item *(SP mbox-list-extended-item)] ")"
+//
+// imap-proto does not parse the extended data, so LIST responses are parsed here.
+pub(crate) fn list_response(i: &[u8]) -> IResult<&[u8], Name<'_>> {
+    let (i, _) = tag_no_case("* LIST ")(i)?;
+    let (i, attributes) = parenthesized_list(name_attribute)(i)?;
+    let (i, _) = char(' ')(i)?;
+    let (i, delimiter) = alt((map(quoted_utf8, Some), map(nil, |_| None)))(i)?;
+    let (i, _) = char(' ')(i)?;
+    let (i, name) = astring_utf8(i)?;
+    let (i, extended) = opt(preceded(char(' '), parenthesized_list(extended_item)))(i)?;
+    let (i, _) = tag("\r\n")(i)?;
+
+    let name = if name.eq_ignore_ascii_case("INBOX") {
+        "INBOX"
+    } else {
+        name
+    };
+    let mut child_info = Vec::new();
+    let mut old_name = None;
+    for item in extended.into_iter().flatten() {
+        match item {
+            ExtendedItem::ChildInfo(info) => child_info.extend(info.into_iter().map(Cow::Borrowed)),
+            ExtendedItem::OldName(name) => old_name = Some(Cow::Borrowed(name)),
+            ExtendedItem::Other => {}
+        }
+    }
+    Ok((
+        i,
+        Name {
+            attributes,
+            delimiter: delimiter.map(Cow::Borrowed),
+            name: Cow::Borrowed(name),
+            child_info,
+            old_name,
+        },
+    ))
+}
+
+impl<T: Read + Write> Session<T> {
+    /// The [extended `LIST` command](https://tools.ietf.org/html/rfc5258#section-3) returns a
+    /// subset of names from the complete set of all names available to the client, like
+    /// [`Session::list`], but with selection options to choose the names by other criteria, and
+    /// return options to return more data about them.
+    ///
+    /// The returned [`ListCmd`] builder takes the reference name, the patterns and the options,
+    /// and sends the command when [`ListCmd::finish`] is called.
+    ///
+    /// ```no_run
+    /// # use imap::extensions::list_extended::{ListReturn, ListSelection};
+    /// # fn example(session: &mut imap::Session<std::net::TcpStream>) -> imap::Result<()> {
+    /// let names = session
+    ///     .list_extended()
+    ///     .select(ListSelection::Subscribed)
+    ///     .select(ListSelection::RecursiveMatch)
+    ///     .patterns(["INBOX*", "Lists/*"])
+    ///     .return_option(ListReturn::Children)
+    ///     .return_option(ListReturn::Status("(MESSAGES UNSEEN)"))
+    ///     .finish()?;
+    /// for (name, status) in names.iter() {
+    ///     if name.is_subscribed() {
+    ///         println!("{}: {:?}", name.name(), status.as_ref().map(|s| s.unseen));
+    ///     }
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// This requires that the server advertises the `LIST-EXTENDED` capability, or the
+    /// capabilities of the return options used, see [`Session::capabilities`].
+    pub fn list_extended(&mut self) -> ListCmd<'_, T> {
+        ListCmd {
+            session: self,
+            reference: "",
+            patterns: Vec::new(),
+            selection: Vec::new(),
+            returns: Vec::new(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock_stream::MockStream;
+    use crate::Client;
+
+    #[test]
+    fn parse_list_extended() {
+        // Example from RFC 5258, section 5.
+        let lines = b"* LIST () \"/\" \"Foo\" (\"CHILDINFO\" (\"SUBSCRIBED\"))\r\n\
+            * LIST (\\Subscribed \\NonExistent) \"/\" \"Foo/Bar\"\r\n\
+            * LIST (\\HasNoChildren) \"/\" inbox (\"X-OTHER\" (1 \"x\") \"OLDNAME\" (\"Old\"))\r\n";
+        let mut queue = std::collections::VecDeque::new();
+        let names = ExtendedNames::parse(lines.to_vec(), &mut queue).unwrap();
+        assert_eq!(names.len(), 3);
+        let (foo, _) = names.get(0).unwrap();
+        assert_eq!(foo.name(), "Foo");
+        assert_eq!(foo.child_info().collect::<Vec<_>>(), vec!["SUBSCRIBED"]);
+        assert!(!foo.is_subscribed());
+        assert_eq!(foo.has_children(), None);
+        let (bar, _) = names.get(1).unwrap();
+        assert!(bar.is_subscribed());
+        assert!(bar.is_non_existent());
+        assert_eq!(bar.child_info().count(), 0);
+        let (inbox, _) = names.get(2).unwrap();
+        assert_eq!(inbox.name(), "INBOX");
+        assert_eq!(inbox.has_children(), Some(false));
+        assert_eq!(inbox.child_info().count(), 0);
+    }
+
+    #[test]
+    fn list_extended() {
+        let response = b"a1 OK [CAPABILITY IMAP4rev1 LIST-EXTENDED LIST-STATUS] Logged in\r\n\
+            * LIST (\\Subscribed \\HasChildren) \"/\" \"Lists\"\r\n\
+            * STATUS \"Lists\" (MESSAGES 17 UNSEEN 16)\r\n\
+            * LIST (\\HasNoChildren) \"/\" \"Lists/R&AOQ-der\" \
+            (\"CHILDINFO\" (\"SUBSCRIBED\") \"OLDNAME\" (\"Lists/Entw&APw-rfe\"))\r\n\
+            a2 OK LIST completed\r\n"
+            .to_vec();
+        let mock_stream = MockStream::new(response);
+        let client = Client::new(mock_stream);
+        let mut session = client.login("username", "password").unwrap();
+        let names = session
+            .list_extended()
+            .select(ListSelection::Subscribed)
+            .select(ListSelection::RecursiveMatch)
+            .reference("")
+            .patterns(["INBOX", "Lists/*"])
+            .return_option(ListReturn::Children)
+            .return_option(ListReturn::Status("(MESSAGES UNSEEN)"))
+            .finish()
+            .unwrap();
+        assert!(session.stream.get_ref().written_buf.ends_with(
+            b"a2 LIST (SUBSCRIBED RECURSIVEMATCH) \"\" (\"INBOX\" \"Lists/*\") \
+            RETURN (CHILDREN STATUS (MESSAGES UNSEEN))\r\n"
+        ));
+        assert_eq!(names.len(), 2);
+        let (lists, status) = names.get(0).unwrap();
+        assert!(lists.is_subscribed());
+        assert_eq!(lists.has_children(), Some(true));
+        assert_eq!(status.as_ref().unwrap().unseen, Some(16));
+        let (rader, status) = names.get(1).unwrap();
+        assert_eq!(rader.name(), "Lists/Räder");
+        assert_eq!(rader.old_name(), Some("Lists/Entwürfe"));
+        assert_eq!(rader.child_info().collect::<Vec<_>>(), vec!["SUBSCRIBED"]);
+        assert!(status.is_none());
+    }
+
+    #[test]
+    fn list_extended_defaults() {
+        let response = b"a1 OK Logged in\r\n\
+            a2 OK LIST completed\r\n"
+            .to_vec();
+        let mock_stream = MockStream::new(response);
+        let client = Client::new(mock_stream);
+        let mut session = client.login("username", "password").unwrap();
+        let names = session
+            .list_extended()
+            .return_option(ListReturn::SpecialUse)
+            .finish()
+            .unwrap();
+        assert!(names.is_empty());
+        assert!(session
+            .stream
+            .get_ref()
+            .written_buf
+            .ends_with(b"a2 LIST \"\" \"*\" RETURN (SPECIAL-USE)\r\n"));
+    }
+}
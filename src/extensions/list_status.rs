@@ -2,10 +2,11 @@
 //! 5819](https://tools.ietf.org/html/rfc5819).
 
 use crate::client::{validate_str, Session};
-use crate::error::{Error, ParseError, Result};
-use crate::parse::try_handle_unilateral;
+use crate::error::{Error, Result};
+use crate::extensions::list_extended::list_response;
+use crate::parse::{parse_many_into, status_response, MapOrNot};
 use crate::types::{Mailbox, Name, UnsolicitedResponse};
-use imap_proto::types::{MailboxDatum, Response, StatusAttribute};
+use nom::{branch::alt, combinator::map};
 use ouroboros::self_referencing;
 use std::collections::VecDeque;
 use std::io::{Read, Write};
@@ -13,8 +14,8 @@ use std::slice::Iter;
 
 /// A wrapper for one or more [`Name`] responses paired with optional [`Mailbox`] responses.
 ///
-/// This structure represents responses to a LIST-STATUS command, as implemented in
-/// [`Session::list_status`]. See [RFC 5819, section 2](https://tools.ietf.org/html/rfc5819.html#section-2).
+/// This structure represents responses to an extended LIST command, as implemented in
+/// [`Session::list_extended`] and [`Session::list_status`]. See [RFC 5819, section 2](https://tools.ietf.org/html/rfc5819.html#section-2).
 #[self_referencing]
 pub struct ExtendedNames {
     data: Vec<u8>,
@@ -23,6 +24,12 @@ pub struct ExtendedNames {
     pub(crate) extended_names: Vec<(Name<'this>, Option<Mailbox>)>,
 }
 
+// A LIST response, or the STATUS response of LIST-STATUS.
+enum ListOrStatus<'a> {
+    List(Name<'a>),
+    Status(Mailbox),
+}
+
 impl ExtendedNames {
     /// Parse one or more LIST-STATUS responses from a response buffer
     pub(crate) fn parse(
@@ -32,72 +39,34 @@ impl ExtendedNames {
         ExtendedNamesTryBuilder {
             data: owned,
             extended_names_builder: |input| {
-                let mut lines: &[u8] = input;
-                let mut names = Vec::new();
-                let mut current_name: Option<Name<'_>> = None;
-                let mut current_mailbox: Option<Mailbox> = None;
-
-                loop {
-                    if lines.is_empty() {
-                        if let Some(cur_name) = current_name {
-                            names.push((cur_name, current_mailbox));
-                        }
-                        break;
-                    }
+                let mut responses = Vec::new();
+                parse_many_into(
+                    input,
+                    &mut responses,
+                    unsolicited,
+                    alt((
+                        map(list_response, |name| {
+                            MapOrNot::Map(ListOrStatus::List(name))
+                        }),
+                        map(status_response, |(_, mailbox)| {
+                            MapOrNot::Map(ListOrStatus::Status(mailbox))
+                        }),
+                    )),
+                    |response| Ok(MapOrNot::Not(response)),
+                )?;
 
-                    match imap_proto::parser::parse_response(lines) {
-                        Ok((
-                            rest,
-                            Response::MailboxData(MailboxDatum::List {
-                                name_attributes,
-                                delimiter,
-                                name,
-                            }),
-                        )) => {
-                            lines = rest;
-                            if let Some(cur_name) = current_name {
-                                names.push((cur_name, current_mailbox));
-                                current_mailbox = None;
-                            }
-                            current_name = Some(Name {
-                                attributes: name_attributes,
-                                delimiter,
-                                name,
-                            });
-                        }
-                        Ok((
-                            rest,
-                            Response::MailboxData(MailboxDatum::Status { mailbox: _, status }),
-                        )) => {
-                            lines = rest;
-                            let mut mb = Mailbox::default();
-                            for attr in status {
-                                match attr {
-                                    StatusAttribute::HighestModSeq(v) => {
-                                        mb.highest_mod_seq = Some(v)
-                                    }
-                                    StatusAttribute::Messages(v) => mb.exists = v,
-                                    StatusAttribute::Recent(v) => mb.recent = v,
-                                    StatusAttribute::UidNext(v) => mb.uid_next = Some(v),
-                                    StatusAttribute::UidValidity(v) => mb.uid_validity = Some(v),
-                                    StatusAttribute::Unseen(v) => mb.unseen = Some(v),
-                                    _ => {} // needed because StatusAttribute is #[non_exhaustive]
-                                }
-                            }
-                            current_mailbox = Some(mb);
-                        }
-                        Ok((rest, resp)) => {
-                            lines = rest;
-                            if let Some(unhandled) = try_handle_unilateral(resp, unsolicited) {
-                                return Err(unhandled.into());
+                // The STATUS response for a mailbox follows its LIST response.
+                let mut names: Vec<(Name<'_>, Option<Mailbox>)> = Vec::new();
+                for response in responses {
+                    match response {
+                        ListOrStatus::List(name) => names.push((name, None)),
+                        ListOrStatus::Status(mailbox) => {
+                            if let Some((_, status)) = names.last_mut() {
+                                *status = Some(mailbox);
                             }
                         }
-                        Err(_) => {
-                            return Err(Error::Parse(ParseError::Invalid(lines.to_vec())));
-                        }
                     }
                 }
-
                 Ok(names)
             },
         }
@@ -138,20 +107,29 @@ impl<T: Read + Write> Session<T> {
     /// [`Session::list`].
     ///
     /// The `data_items` argument has the same semantics as it does in [`Session::status`].
+    ///
+    /// Unlike with [`Session::list_extended`], the pattern is sent as it is given, as with
+    /// [`Session::list`], so that it may contain wildcards without being quoted.
     pub fn list_status(
         &mut self,
         reference_name: Option<&str>,
         mailbox_pattern: Option<&str>,
         data_items: &str,
     ) -> Result<ExtendedNames> {
-        let reference = validate_str("LIST-STATUS", "reference", reference_name.unwrap_or(""))?;
+        let reference = validate_str(
+            "LIST-STATUS",
+            "reference",
+            &self.encode_mailbox_name(reference_name.unwrap_or("")),
+        )?;
         let lines = self.run_command_and_read_response(format!(
             "LIST {} {} RETURN (STATUS {})",
             &reference,
-            mailbox_pattern.unwrap_or("\"\""),
+            self.encode_mailbox_name(mailbox_pattern.unwrap_or("\"\"")),
             data_items
         ))?;
-        ExtendedNames::parse(lines, &mut self.unsolicited_responses)
+        let mut names = ExtendedNames::parse(lines, &mut self.unsolicited_responses)?;
+        self.decode_extended_names(&mut names);
+        Ok(names)
     }
 }
 
@@ -160,6 +138,8 @@ mod tests {
     use imap_proto::NameAttribute;
 
     use super::*;
+    use crate::mock_stream::MockStream;
+    use crate::Client;
 
     #[test]
     fn parse_list_status_test() {
@@ -194,4 +174,35 @@ mod tests {
         assert!(status.is_some());
         assert_eq!(status.as_ref().unwrap().highest_mod_seq, Some(757));
     }
+
+    #[test]
+    fn list_status() {
+        let response = b"a1 OK Logged in\r\n\
+            * LIST () \".\" \"Entw&APw-rfe\"\r\n\
+            * STATUS \"Entw&APw-rfe\" (MESSAGES 3)\r\n\
+            a2 OK LIST completed\r\n\
+            a3 OK LIST completed\r\n"
+            .to_vec();
+        let mock_stream = MockStream::new(response);
+        let client = Client::new(mock_stream);
+        let mut session = client.login("username", "password").unwrap();
+        let names = session
+            .list_status(Some("Archiv"), Some("Entw*"), "(MESSAGES)")
+            .unwrap();
+        assert!(session
+            .stream
+            .get_ref()
+            .written_buf
+            .ends_with(b"a2 LIST \"Archiv\" Entw* RETURN (STATUS (MESSAGES))\r\n"));
+        let (name, status) = names.get(0).unwrap();
+        assert_eq!(name.name(), "Entwürfe");
+        assert_eq!(status.as_ref().unwrap().exists, 3);
+
+        session.list_status(None, None, "(UNSEEN)").unwrap();
+        assert!(session
+            .stream
+            .get_ref()
+            .written_buf
+            .ends_with(b"a3 LIST \"\" \"\" RETURN (STATUS (UNSEEN))\r\n"));
+    }
 }
@@ -190,7 +190,7 @@ impl<T: Read + Write> Session<T> {
             format!(
                 ") {} ({})",
                 mailbox
-                    .map(|mbox| validate_str(synopsis, "mailbox", mbox))
+                    .map(|mbox| validate_str(synopsis, "mailbox", &self.encode_mailbox_name(mbox)))
                     .unwrap_or_else(|| Ok("\"\"".to_string()))?,
                 s
             )
@@ -248,7 +248,11 @@ impl<T: Read + Write> Session<T> {
         let s = v.as_slice().join(" ");
         let command = format!(
             "SETMETADATA {} ({})",
-            validate_str("SETMETADATA", "mailbox", mbox.as_ref())?,
+            validate_str(
+                "SETMETADATA",
+                "mailbox",
+                &self.encode_mailbox_name(mbox.as_ref())
+            )?,
             s
         );
         self.run_command_and_check_ok(command)
@@ -1,5 +1,22 @@
 //! Implementations of various IMAP extensions.
+pub mod binary;
+#[cfg(feature = "compress")]
+pub mod compress;
+pub mod condstore;
+pub mod enable;
+pub mod esearch;
+pub mod id;
 pub mod idle;
+pub mod list_extended;
 pub mod list_status;
 pub mod metadata;
+pub mod multiappend;
+pub mod namespace;
+pub mod notify;
+pub mod objectid;
+pub mod qresync;
+pub mod searchres;
 pub mod sort;
+pub mod special_use;
+pub mod thread;
+pub mod unselect;
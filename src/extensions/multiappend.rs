@@ -0,0 +1,255 @@
+//! Adds support for the IMAP MULTIAPPEND extension specified in [RFC
+//! 3502](https://tools.ietf.org/html/rfc3502).
+//!
+//! A plain [`APPEND`](https://tools.ietf.org/html/rfc3501#section-6.3.11) uploads a single
+//! message, so appending many messages costs at least one round trip each. With MULTIAPPEND, any
+//! number of messages are appended to a mailbox in one command, and atomically: either all of them
+//! are appended, or none are. [`Session::multi_append`] returns a [`MultiAppendCmd`] builder for
+//! such a command, which falls back to one `APPEND` per message on servers without the extension.
+
+use crate::client::{append_args, Session};
+use crate::command::Command;
+use crate::error::Result;
+use crate::parse::parse_append;
+use crate::types::{Appended, Flag};
+use chrono::{DateTime, FixedOffset};
+use std::io::{Read, Write};
+
+/// A message to append with [`MultiAppendCmd`], together with its flags and internal date.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct AppendMessage<'a> {
+    content: &'a [u8],
+    flags: Vec<Flag<'a>>,
+    date: Option<DateTime<FixedOffset>>,
+}
+
+impl<'a> AppendMessage<'a> {
+    /// A message with the given content, which SHOULD be in the format of an
+    /// [RFC-2822](https://tools.ietf.org/html/rfc2822) message. Unless flags are added, the
+    /// message is appended without flags, and with the current time as its internal date.
+    pub fn new(content: &'a [u8]) -> Self {
+        AppendMessage {
+            content,
+            flags: Vec::new(),
+            date: None,
+        }
+    }
+
+    /// Set a flag on the appended message. See [`AppendCmd::flag`](crate::AppendCmd::flag).
+    pub fn flag(mut self, flag: Flag<'a>) -> Self {
+        self.flags.push(flag);
+        self
+    }
+
+    /// Set multiple flags at once.
+    pub fn flags(mut self, flags: impl IntoIterator<Item = Flag<'a>>) -> Self {
+        self.flags.extend(flags);
+        self
+    }
+
+    /// Set the internal date of the appended message. See
+    /// [`AppendCmd::internal_date`](crate::AppendCmd::internal_date).
+    pub fn internal_date(mut self, date: DateTime<FixedOffset>) -> Self {
+        self.date = Some(date);
+        self
+    }
+}
+
+impl<'a> From<&'a [u8]> for AppendMessage<'a> {
+    fn from(content: &'a [u8]) -> Self {
+        AppendMessage::new(content)
+    }
+}
+
+/// A builder for appending several messages to a mailbox at once, see [`Session::multi_append`].
+#[must_use]
+pub struct MultiAppendCmd<'a, T: Read + Write> {
+    session: &'a mut Session<T>,
+    mailbox: &'a str,
+    messages: Vec<AppendMessage<'a>>,
+}
+
+impl<'a, T: Read + Write> MultiAppendCmd<'a, T> {
+    /// Add a message to append.
+    pub fn message(&mut self, message: impl Into<AppendMessage<'a>>) -> &mut Self {
+        self.messages.push(message.into());
+        self
+    }
+
+    /// Add several messages to append.
+    pub fn messages<M: Into<AppendMessage<'a>>>(
+        &mut self,
+        messages: impl IntoIterator<Item = M>,
+    ) -> &mut Self {
+        self.messages.extend(messages.into_iter().map(Into::into));
+        self
+    }
+
+    /// Finishes up the command and executes it.
+    ///
+    /// If the server advertises `MULTIAPPEND`, all messages are sent in a single `APPEND` command.
+    /// Otherwise, one `APPEND` command is sent per message, in order; should one of them fail,
+    /// the messages before it will have been appended nonetheless.
+    ///
+    /// The returned [`Appended`] holds the UIDs of all appended messages if the server supports
+    /// [`UIDPLUS`](https://tools.ietf.org/html/rfc4315).
+    pub fn finish(&mut self) -> Result<Appended> {
+        if self.messages.is_empty() {
+            return Ok(Appended::default());
+        }
+        if self.session.has_capability("MULTIAPPEND")? {
+            self.append_at_once()
+        } else {
+            self.append_one_by_one()
+        }
+    }
+
+    fn append_at_once(&mut self) -> Result<Appended> {
+        let mailbox = self.session.encode_mailbox_name(self.mailbox);
+        let mut command = Command::new("APPEND ");
+        command.push_string("APPEND", "mailbox", &mailbox)?;
+        for message in &self.messages {
+            command
+                .push_str(&format!(" {} ", append_args(&message.flags, message.date)))
+                .push_literal(message.content);
+        }
+        self.session
+            .execute(&command)
+            .and_then(|(lines, _)| parse_append(&lines, &mut self.session.unsolicited_responses))
+    }
+
+    fn append_one_by_one(&mut self) -> Result<Appended> {
+        let mut all = Appended::default();
+        for (i, message) in self.messages.iter().enumerate() {
+            let mut append = self.session.append(self.mailbox, message.content);
+            append.flags(message.flags.iter().cloned());
+            if let Some(date) = message.date {
+                append.internal_date(date);
+            }
+            let appended = append.finish()?;
+            if i == 0 {
+                all = appended;
+            } else if all.uid_validity != appended.uid_validity {
+                // The UIDs cannot be put together, for instance because the mailbox was
+                // recreated in between.
+                all.uid_validity = None;
+                all.uids = None;
+            } else if let (Some(uids), Some(more)) = (&mut all.uids, appended.uids) {
+                uids.extend(more);
+            } else {
+                all.uids = None;
+            }
+        }
+        Ok(all)
+    }
+}
+
+impl<T: Read + Write> Session<T> {
+    /// Append several messages to the specified destination `mailbox` with a single [`APPEND`
+    /// command](https://tools.ietf.org/html/rfc3502#section-6.3.11), as defined by the
+    /// MULTIAPPEND extension.
+    ///
+    /// Each message is added to the returned [`MultiAppendCmd`] as an [`AppendMessage`], which
+    /// may carry its own flags and internal date. If the server does not advertise the
+    /// `MULTIAPPEND` capability, the messages are appended one by one instead.
+    ///
+    /// ```no_run
+    /// # use imap::extensions::multiappend::AppendMessage;
+    /// # use imap::types::Flag;
+    /// # fn example(session: &mut imap::Session<std::net::TcpStream>) -> imap::Result<()> {
+    /// let appended = session
+    ///     .multi_append("Archive")
+    ///     .message(AppendMessage::new(b"Subject: one\r\n\r\nfirst").flag(Flag::Seen))
+    ///     .message(&b"Subject: two\r\n\r\nsecond"[..])
+    ///     .finish()?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn multi_append<'a>(&'a mut self, mailbox: &'a str) -> MultiAppendCmd<'a, T> {
+        MultiAppendCmd {
+            session: self,
+            mailbox,
+            messages: Vec::new(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock_stream::MockStream;
+    use crate::Client;
+    use imap_proto::UidSetMember;
+
+    #[test]
+    fn multi_append() {
+        let response = b"a1 OK [CAPABILITY IMAP4rev1 MULTIAPPEND UIDPLUS] Logged in\r\n\
+            + Ready for literal data\r\n\
+            + Ready for literal data\r\n\
+            a2 OK [APPENDUID 38505 3955:3956] APPEND completed\r\n"
+            .to_vec();
+        let mock_stream = MockStream::new(response);
+        let client = Client::new(mock_stream);
+        let mut session = client.login("username", "password").unwrap();
+        let date = DateTime::parse_from_rfc3339("2022-03-04T05:06:07+01:00").unwrap();
+        let appended = session
+            .multi_append("Saved")
+            .message(
+                AppendMessage::new(b"first")
+                    .flag(Flag::Seen)
+                    .internal_date(date),
+            )
+            .message(&b"second"[..])
+            .finish()
+            .unwrap();
+        assert!(session.stream.get_ref().written_buf.ends_with(
+            b"a2 APPEND \"Saved\" (\\Seen) \"04-Mar-2022 05:06:07 +0100\" {5}\r\n\
+            first () {6}\r\n\
+            second\r\n"
+        ));
+        assert_eq!(appended.uid_validity, Some(38505));
+        assert_eq!(
+            appended.uids,
+            Some(vec![UidSetMember::UidRange(3955..=3956)])
+        );
+    }
+
+    #[test]
+    fn multi_append_fallback() {
+        let response = b"a1 OK [CAPABILITY IMAP4rev1 LITERAL+ UIDPLUS] Logged in\r\n\
+            a2 OK [APPENDUID 38505 3955] APPEND completed\r\n\
+            a3 OK [APPENDUID 38505 3956] APPEND completed\r\n"
+            .to_vec();
+        let mock_stream = MockStream::new(response);
+        let client = Client::new(mock_stream);
+        let mut session = client.login("username", "password").unwrap();
+        let appended = session
+            .multi_append("Saved")
+            .messages([&b"first"[..], &b"second"[..]])
+            .finish()
+            .unwrap();
+        assert!(session.stream.get_ref().written_buf.ends_with(
+            b"a2 APPEND \"Saved\" () {5+}\r\nfirst\r\n\
+            a3 APPEND \"Saved\" () {6+}\r\nsecond\r\n"
+        ));
+        assert_eq!(appended.uid_validity, Some(38505));
+        assert_eq!(
+            appended.uids,
+            Some(vec![UidSetMember::Uid(3955), UidSetMember::Uid(3956)])
+        );
+    }
+
+    #[test]
+    fn multi_append_nothing() {
+        let mock_stream = MockStream::new(b"a1 OK Logged in\r\n".to_vec());
+        let client = Client::new(mock_stream);
+        let mut session = client.login("username", "password").unwrap();
+        let appended = session.multi_append("Saved").finish().unwrap();
+        assert_eq!(appended, Appended::default());
+        assert!(session
+            .stream
+            .get_ref()
+            .written_buf
+            .ends_with(b"LOGIN \"username\" \"password\"\r\n"));
+    }
+}
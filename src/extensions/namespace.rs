@@ -0,0 +1,405 @@
+//! Adds support for the IMAP NAMESPACE extension specified in [RFC
+//! 2342](https://tools.ietf.org/html/rfc2342).
+//!
+//! Servers commonly partition their mailboxes into namespaces: the user's own mailboxes, the
+//! mailboxes of other users, and mailboxes shared between users. [`Session::namespace`] tells the
+//! client which prefixes and hierarchy delimiters are used for each of these, so it doesn't have
+//! to guess them. [`Namespaces::find`] (or [`Name::namespace`]) then tells which namespace a
+//! mailbox belongs to.
+
+use crate::client::Session;
+use crate::error::{Error, ParseError, Result};
+use crate::parse::{parse_many_into, MapOrNot};
+use crate::types::{Name, UnsolicitedResponse};
+use crate::utf7;
+use imap_proto::parser::core::{nil, parenthesized_nonempty_list, quoted_utf8, string_utf8};
+use nom::{
+    branch::alt,
+    bytes::streaming::tag_no_case,
+    character::streaming::char,
+    combinator::map,
+    multi::many0,
+    sequence::{preceded, tuple},
+    IResult,
+};
+use std::borrow::Cow;
+use std::collections::VecDeque;
+use std::io::{Read, Write};
+
+/// The kind of a [`Namespace`], see [RFC 2342, section
+/// 4](https://tools.ietf.org/html/rfc2342#section-4).
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+pub enum NamespaceKind {
+    /// Mailboxes that belong to the logged in user.
+    Personal,
+    /// Mailboxes that belong to other users, and that this user was given access to.
+    OtherUsers,
+    /// Mailboxes that are intended to be shared amongst users and do not belong to any of them.
+    Shared,
+}
+
+/// A single namespace, as returned by [`Session::namespace`].
+#[derive(Debug, Clone, Eq, PartialEq)]
+#[non_exhaustive]
+pub struct Namespace {
+    /// The prefix of all mailbox names in this namespace, such as `"INBOX."`, `"#shared/"` or
+    /// `""`.
+    ///
+    /// Like the mailbox names returned by [`Session::list`], the prefix is decoded from modified
+    /// UTF-7, so that it can be compared with them.
+    pub prefix: String,
+    /// The hierarchy delimiter used in this namespace, or `None` if there is no hierarchy.
+    pub delimiter: Option<String>,
+    /// Extension data sent by the server for this namespace.
+    pub extensions: Vec<NamespaceExtension>,
+}
+
+impl Namespace {
+    /// Returns true if the given mailbox name is part of this namespace.
+    ///
+    /// The namespace's own root (its prefix without the trailing delimiter) is considered part of
+    /// it as well.
+    pub fn contains(&self, mailbox_name: &str) -> bool {
+        if mailbox_name.starts_with(&self.prefix) {
+            return true;
+        }
+        match &self.delimiter {
+            Some(delimiter) => self.prefix.strip_suffix(delimiter.as_str()) == Some(mailbox_name),
+            None => false,
+        }
+    }
+}
+
+/// A namespace response extension, see [RFC 2342, section
+/// 5](https://tools.ietf.org/html/rfc2342#section-5).
+#[derive(Debug, Clone, Eq, PartialEq)]
+#[non_exhaustive]
+pub struct NamespaceExtension {
+    /// The name of the extension.
+    pub name: String,
+    /// The values given for the extension.
+    pub values: Vec<String>,
+}
+
+/// The personal, other users' and shared namespaces available to the client, as returned by
+/// [`Session::namespace`].
+///
+/// Each kind of namespace may consist of zero or more namespaces. Commonly, there is a single
+/// personal namespace.
+#[derive(Debug, Clone, Default, Eq, PartialEq)]
+#[non_exhaustive]
+pub struct Namespaces {
+    /// The user's personal namespaces.
+    pub personal: Vec<Namespace>,
+    /// The namespaces in which the mailboxes of other users can be found.
+    pub other_users: Vec<Namespace>,
+    /// The namespaces of the shared mailboxes.
+    pub shared: Vec<Namespace>,
+}
+
+impl Namespaces {
+    /// Parse the `NAMESPACE` response from a response buffer.
+    pub(crate) fn parse(
+        lines: &[u8],
+        unsolicited: &mut VecDeque<UnsolicitedResponse>,
+    ) -> Result<Self> {
+        let mut namespaces = Vec::new();
+        parse_many_into(
+            lines,
+            &mut namespaces,
+            unsolicited,
+            map(namespace_response, MapOrNot::Map),
+            |response| Ok(MapOrNot::Not(response)),
+        )?;
+        namespaces
+            .pop()
+            .ok_or_else(|| Error::Parse(ParseError::Invalid(Vec::new())))
+    }
+
+    /// Iterate over all the namespaces, together with their kind.
+    pub fn iter(&self) -> impl Iterator<Item = (NamespaceKind, &Namespace)> {
+        let personal = self.personal.iter().map(|ns| (NamespaceKind::Personal, ns));
+        let other_users = self
+            .other_users
+            .iter()
+            .map(|ns| (NamespaceKind::OtherUsers, ns));
+        let shared = self.shared.iter().map(|ns| (NamespaceKind::Shared, ns));
+        personal.chain(other_users).chain(shared)
+    }
+
+    /// Find the namespace the given mailbox name belongs to.
+    ///
+    /// If the name matches several namespaces, the one with the longest prefix is returned, so
+    /// that for instance `"#shared/foo"` is found in a `"#shared/"` namespace rather than in a
+    /// personal namespace with the empty prefix. `INBOX` always belongs to a personal namespace.
+    pub fn find(&self, mailbox_name: &str) -> Option<(NamespaceKind, &Namespace)> {
+        if mailbox_name.eq_ignore_ascii_case("INBOX") {
+            if let Some(ns) = self.personal.first() {
+                return Some((NamespaceKind::Personal, ns));
+            }
+        }
+        self.iter()
+            .filter(|(_, ns)| ns.contains(mailbox_name))
+            .max_by_key(|(_, ns)| ns.prefix.len())
+    }
+}
+
+impl Name<'_> {
+    /// Find the namespace this mailbox belongs to. See [`Namespaces::find`].
+    pub fn namespace<'n>(
+        &self,
+        namespaces: &'n Namespaces,
+    ) -> Option<(NamespaceKind, &'n Namespace)> {
+        namespaces.find(self.name())
+    }
+}
+
+// Namespace_Response_Extension = SP string SP "(" string *(SP string) ")"
+fn namespace_extension(i: &[u8]) -> IResult<&[u8], NamespaceExtension> {
+    map(
+        tuple((
+            char(' '),
+            string_utf8,
+            char(' '),
+            parenthesized_nonempty_list(string_utf8),
+        )),
+        |(_, name, _, values)| NamespaceExtension {
+            name: name.to_string(),
+            values: values.into_iter().map(str::to_string).collect(),
+        },
+    )(i)
+}
+
+// "(" string SP (<"> QUOTED_CHAR <"> / nil) *(Namespace_Response_Extension) ")"
+fn namespace_description(i: &[u8]) -> IResult<&[u8], Namespace> {
+    map(
+        tuple((
+            char('('),
+            string_utf8,
+            char(' '),
+            alt((map(quoted_utf8, Some), map(nil, |_| None))),
+            many0(namespace_extension),
+            char(')'),
+        )),
+        |(_, prefix, _, delimiter, extensions, _)| Namespace {
+            prefix: prefix.to_string(),
+            delimiter: delimiter.map(str::to_string),
+            extensions,
+        },
+    )(i)
+}
+
+// Namespace = nil / "(" 1*( "(" string SP (<"> QUOTED_CHAR <"> / nil)
+//     *(Namespace_Response_Extension) ")" ) ")"
+fn namespace(i: &[u8]) -> IResult<&[u8], Vec<Namespace>> {
+    alt((
+        map(nil, |_| Vec::new()),
+        map(
+            tuple((
+                char('('),
+                many0(preceded(
+                    nom::combinator::opt(char(' ')),
+                    namespace_description,
+                )),
+                char(')'),
+            )),
+            |(_, namespaces, _)| namespaces,
+        ),
+    ))(i)
+}
+
+// Namespace_Response = "*" SP "NAMESPACE" SP Namespace SP Namespace SP Namespace
+fn namespace_response(i: &[u8]) -> IResult<&[u8], Namespaces> {
+    map(
+        tuple((
+            tag_no_case("* NAMESPACE "),
+            namespace,
+            char(' '),
+            namespace,
+            char(' '),
+            namespace,
+            tag_no_case("\r\n"),
+        )),
+        |(_, personal, _, other_users, _, shared, _)| Namespaces {
+            personal,
+            other_users,
+            shared,
+        },
+    )(i)
+}
+
+impl<T: Read + Write> Session<T> {
+    /// The [`NAMESPACE` command](https://tools.ietf.org/html/rfc2342#section-5) returns the
+    /// prefixes and hierarchy delimiters of the personal, other users' and shared namespaces
+    /// available to the client.
+    ///
+    /// ```no_run
+    /// # {} #[cfg(feature = "native-tls")]
+    /// # fn main() {
+    /// # let client = imap::ClientBuilder::new("imap.example.com", 993)
+    ///     .connect().unwrap();
+    /// # let mut session = client.login("name", "pw").unwrap();
+    /// let namespaces = session.namespace().unwrap();
+    /// for name in session.list(None, Some("*")).unwrap().iter() {
+    ///     if let Some((kind, ns)) = name.namespace(&namespaces) {
+    ///         println!("{} is in the {:?} namespace {:?}", name.name(), kind, ns.prefix);
+    ///     }
+    /// }
+    /// # }
+    /// ```
+    ///
+    /// This requires that the server supports the `NAMESPACE` capability.
+    pub fn namespace(&mut self) -> Result<Namespaces> {
+        let lines = self.run_command_and_read_response("NAMESPACE")?;
+        let mut namespaces = Namespaces::parse(&lines, &mut self.unsolicited_responses)?;
+        if !self.utf8_accepted() {
+            let Namespaces {
+                personal,
+                other_users,
+                shared,
+            } = &mut namespaces;
+            // The delimiter is a single quoted character, so it is always ASCII here and has
+            // nothing to decode.
+            for ns in personal.iter_mut().chain(other_users).chain(shared) {
+                let mut prefix = Cow::Borrowed(ns.prefix.as_str());
+                utf7::decode_lossless(&mut prefix);
+                ns.prefix = prefix.into_owned();
+            }
+        }
+        Ok(namespaces)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock_stream::MockStream;
+    use crate::Client;
+
+    #[test]
+    fn parse_namespace() {
+        let lines = b"* NAMESPACE ((\"\" \"/\")) ((\"~\" \"/\")) ((\"#shared/\" \"/\")\
+            (\"#public/\" \"/\")(\"#ftp/\" \"/\")(\"#news.\" \".\"))\r\n";
+        let mut queue = VecDeque::new();
+        let ns = Namespaces::parse(lines, &mut queue).unwrap();
+        assert_eq!(queue.pop_front(), None);
+        assert_eq!(ns.personal.len(), 1);
+        assert_eq!(ns.personal[0].prefix, "");
+        assert_eq!(ns.personal[0].delimiter.as_deref(), Some("/"));
+        assert_eq!(ns.other_users.len(), 1);
+        assert_eq!(ns.other_users[0].prefix, "~");
+        assert_eq!(ns.shared.len(), 4);
+        assert_eq!(ns.shared[3].prefix, "#news.");
+        assert_eq!(ns.shared[3].delimiter.as_deref(), Some("."));
+    }
+
+    #[test]
+    fn parse_namespace_nil_and_extensions() {
+        let lines = b"* NAMESPACE ((\"\" \"/\" \"X-PARAM\" (\"FLAG1\" \"FLAG2\"))) NIL \
+            ((\"Shared\" NIL))\r\n";
+        let mut queue = VecDeque::new();
+        let ns = Namespaces::parse(lines, &mut queue).unwrap();
+        assert_eq!(queue.pop_front(), None);
+        assert_eq!(
+            ns.personal[0].extensions,
+            vec![NamespaceExtension {
+                name: "X-PARAM".to_string(),
+                values: vec!["FLAG1".to_string(), "FLAG2".to_string()],
+            }]
+        );
+        assert!(ns.other_users.is_empty());
+        assert_eq!(ns.shared[0].prefix, "Shared");
+        assert_eq!(ns.shared[0].delimiter, None);
+    }
+
+    #[test]
+    fn parse_namespace_missing() {
+        let mut queue = VecDeque::new();
+        assert!(Namespaces::parse(b"", &mut queue).is_err());
+    }
+
+    #[test]
+    fn find_namespace() {
+        let lines = b"* NAMESPACE ((\"INBOX.\" \".\")) ((\"Other Users/\" \"/\")) \
+            ((\"Shared/\" \"/\")(\"\" \".\"))\r\n";
+        let mut queue = VecDeque::new();
+        let ns = Namespaces::parse(lines, &mut queue).unwrap();
+
+        let (kind, found) = ns.find("INBOX").unwrap();
+        assert_eq!(kind, NamespaceKind::Personal);
+        assert_eq!(found.prefix, "INBOX.");
+        assert_eq!(ns.find("INBOX.Sent").unwrap().0, NamespaceKind::Personal);
+        assert_eq!(
+            ns.find("Other Users/bob/INBOX").unwrap().0,
+            NamespaceKind::OtherUsers
+        );
+        assert_eq!(ns.find("Other Users").unwrap().0, NamespaceKind::OtherUsers);
+        assert_eq!(ns.find("Shared/support").unwrap().0, NamespaceKind::Shared);
+        let (kind, found) = ns.find("Archive").unwrap();
+        assert_eq!(kind, NamespaceKind::Shared);
+        assert_eq!(found.prefix, "");
+    }
+
+    #[test]
+    fn namespace() {
+        let response = b"a1 OK Logged in\r\n\
+            * NAMESPACE ((\"\" \"/\")) ((\"Other Users/\" \"/\")) ((\"Shared/\" \"/\"))\r\n\
+            a2 OK NAMESPACE command completed\r\n\
+            * LIST (\\HasNoChildren) \"/\" \"Shared/support\"\r\n\
+            * LIST (\\HasNoChildren) \"/\" \"INBOX\"\r\n\
+            a3 OK LIST completed\r\n"
+            .to_vec();
+        let mock_stream = MockStream::new(response);
+        let client = Client::new(mock_stream);
+        let mut session = client.login("testuser", "pass").unwrap();
+        let namespaces = session.namespace().unwrap();
+        assert!(session
+            .stream
+            .get_ref()
+            .written_buf
+            .ends_with(b"a2 NAMESPACE\r\n"));
+        assert_eq!(namespaces.personal.len(), 1);
+        assert_eq!(namespaces.other_users[0].prefix, "Other Users/");
+        assert_eq!(namespaces.shared[0].prefix, "Shared/");
+
+        let names = session.list(None, Some("*")).unwrap();
+        let shared = names.get(0).unwrap();
+        assert_eq!(
+            shared.namespace(&namespaces).unwrap(),
+            (NamespaceKind::Shared, &namespaces.shared[0])
+        );
+        let inbox = names.get(1).unwrap();
+        assert_eq!(
+            inbox.namespace(&namespaces).unwrap().0,
+            NamespaceKind::Personal
+        );
+    }
+
+    #[test]
+    fn namespace_non_ascii_prefix() {
+        let response = b"a1 OK Logged in\r\n\
+            * NAMESPACE ((\"\" \"/\")) NIL ((\"Gemeinsame Entw&APw-rfe/\" \"/\"))\r\n\
+            a2 OK NAMESPACE command completed\r\n\
+            * LIST (\\HasNoChildren) \"/\" \"Gemeinsame Entw&APw-rfe/Angebot\"\r\n\
+            * LIST (\\HasChildren) \"/\" \"Gemeinsame Entw&APw-rfe\"\r\n\
+            a3 OK LIST completed\r\n"
+            .to_vec();
+        let mock_stream = MockStream::new(response);
+        let client = Client::new(mock_stream);
+        let mut session = client.login("testuser", "pass").unwrap();
+        let namespaces = session.namespace().unwrap();
+        assert_eq!(namespaces.shared[0].prefix, "Gemeinsame Entwürfe/");
+
+        let names = session.list(None, Some("*")).unwrap();
+        let draft = names.get(0).unwrap();
+        assert_eq!(draft.name(), "Gemeinsame Entwürfe/Angebot");
+        assert_eq!(
+            draft.namespace(&namespaces).unwrap(),
+            (NamespaceKind::Shared, &namespaces.shared[0])
+        );
+        assert_eq!(
+            names.get(1).unwrap().namespace(&namespaces).unwrap().0,
+            NamespaceKind::Shared
+        );
+    }
+}
@@ -0,0 +1,587 @@
+//! Adds support for the IMAP NOTIFY extension specified in [RFC
+//! 5465](https://tools.ietf.org/html/rfc5465).
+//!
+//! [`Session::idle`] only reports changes to the selected mailbox. With NOTIFY, a client asks the
+//! server to report events in other mailboxes as well, such as all personal mailboxes or a list
+//! of named ones, over the same connection. [`Session::notify`] returns a [`NotifyCmd`] builder to
+//! set up the events to report, grouped by the mailboxes they apply to.
+//!
+//! The server then reports the events as untagged responses: `STATUS` responses for new and
+//! expunged messages in other mailboxes, `LIST` responses for mailboxes that were created,
+//! deleted, renamed or (un)subscribed, and the usual `EXISTS`, `EXPUNGE` and `FETCH` responses for
+//! the selected mailbox. They arrive as [`UnsolicitedResponse`]s, either in the callback of
+//! [`idle::Handle::wait_while`](crate::extensions::idle::Handle::wait_while), or after any other
+//! command such as [`Session::noop`] through [`Session::take_all_unsolicited`]. Either way,
+//! [`Notification::try_from`] turns them into a [`Notification`]:
+//!
+//! ```no_run
+//! # use imap::extensions::notify::{MailboxFilter, Notification, NotifyEvent};
+//! # use std::convert::TryFrom;
+//! # fn example(session: &mut imap::Session<std::net::TcpStream>) -> imap::Result<()> {
+//! session
+//!     .notify()
+//!     .group(
+//!         MailboxFilter::Mailboxes(vec!["INBOX", "Sent", "Shared/Team"]),
+//!         [NotifyEvent::MessageNew, NotifyEvent::MessageExpunge],
+//!     )
+//!     .group(MailboxFilter::Personal, [NotifyEvent::MailboxName])
+//!     .finish()?;
+//!
+//! session.noop()?;
+//! for notification in session
+//!     .take_all_unsolicited()
+//!     .filter_map(|response| Notification::try_from(response).ok())
+//! {
+//!     if let Notification::Status { mailbox, status } = notification {
+//!         println!("{} now has {} messages", mailbox, status.exists);
+//!     }
+//! }
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::client::{validate_str, Session};
+use crate::error::Result;
+use crate::parse::parse_noop;
+use crate::types::{AttributeValue, Mailbox, Name, Seq, UnsolicitedResponse};
+use crate::utils::iter_join;
+use std::convert::TryFrom;
+use std::fmt;
+use std::io::{Read, Write};
+use std::ops::RangeInclusive;
+
+// imap-proto does not know about this response code, so it ends up in the text of the response.
+const NOTIFICATION_OVERFLOW: &str = "[NOTIFICATIONOVERFLOW]";
+
+/// The mailboxes an event group of a [`NotifyCmd`] applies to, see [RFC 5465, section
+/// 6](https://tools.ietf.org/html/rfc5465#section-6).
+#[derive(Clone, Debug, Eq, PartialEq, Hash)]
+#[non_exhaustive]
+pub enum MailboxFilter<'a> {
+    /// The currently selected mailbox, whichever it is.
+    Selected,
+
+    /// Like [`MailboxFilter::Selected`], except that the server delays `EXPUNGE` responses until
+    /// the client can handle them, so message sequence numbers do not change unexpectedly.
+    SelectedDelayed,
+
+    /// All mailboxes that the server delivers new mail to for the user.
+    Inboxes,
+
+    /// All mailboxes in the user's personal namespace.
+    Personal,
+
+    /// All subscribed mailboxes.
+    Subscribed,
+
+    /// The given mailboxes and all mailboxes below them in the hierarchy.
+    Subtree(Vec<&'a str>),
+
+    /// The given mailboxes.
+    Mailboxes(Vec<&'a str>),
+}
+
+/// An event to be notified about, see [RFC 5465, section
+/// 5](https://tools.ietf.org/html/rfc5465#section-5).
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash)]
+#[non_exhaustive]
+pub enum NotifyEvent {
+    /// A message was added to the mailbox.
+    MessageNew,
+
+    /// A message was expunged from the mailbox.
+    MessageExpunge,
+
+    /// The flags of a message changed. This requires [`NotifyEvent::MessageNew`] and
+    /// [`NotifyEvent::MessageExpunge`] in the same event group.
+    FlagChange,
+
+    /// The annotations of a message changed. This requires [`NotifyEvent::MessageNew`] and
+    /// [`NotifyEvent::MessageExpunge`] in the same event group.
+    AnnotationChange,
+
+    /// A mailbox was created, deleted or renamed.
+    MailboxName,
+
+    /// A mailbox was subscribed or unsubscribed.
+    SubscriptionChange,
+
+    /// The metadata of a mailbox changed, see [RFC 5464](https://tools.ietf.org/html/rfc5464).
+    MailboxMetadataChange,
+
+    /// The server metadata changed, see [RFC 5464](https://tools.ietf.org/html/rfc5464).
+    ServerMetadataChange,
+}
+
+impl fmt::Display for NotifyEvent {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        use NotifyEvent::*;
+
+        match self {
+            MessageNew => write!(f, "MessageNew"),
+            MessageExpunge => write!(f, "MessageExpunge"),
+            FlagChange => write!(f, "FlagChange"),
+            AnnotationChange => write!(f, "AnnotationChange"),
+            MailboxName => write!(f, "MailboxName"),
+            SubscriptionChange => write!(f, "SubscriptionChange"),
+            MailboxMetadataChange => write!(f, "MailboxMetadataChange"),
+            ServerMetadataChange => write!(f, "ServerMetadataChange"),
+        }
+    }
+}
+
+/// An event reported by a server after [`Session::notify`], converted from an
+/// [`UnsolicitedResponse`] with [`Notification::try_from`].
+///
+/// Mailbox names are in UTF-8, like those passed to [`NotifyCmd::group`], as long as the
+/// responses come from [`Session::take_all_unsolicited`] or
+/// [`idle::Handle::wait_while`](crate::extensions::idle::Handle::wait_while), which convert them
+/// from [modified UTF-7](crate::utf7) where necessary.
+#[derive(Debug, Eq, PartialEq)]
+#[non_exhaustive]
+pub enum Notification {
+    /// The number of messages in the selected mailbox, after new messages arrived.
+    Exists(u32),
+
+    /// A message was expunged from the selected mailbox.
+    Expunge(Seq),
+
+    /// Messages with the given [`Uid`](crate::types::Uid)s were expunged from the selected
+    /// mailbox, if [QRESYNC](https://tools.ietf.org/html/rfc7162) is enabled.
+    Vanished(Vec<RangeInclusive<u32>>),
+
+    /// The flags of a message in the selected mailbox changed, or a new message arrived.
+    Fetch {
+        /// The sequence number of the message.
+        id: Seq,
+        /// The data items of the message that were sent.
+        attributes: Vec<AttributeValue<'static>>,
+    },
+
+    /// Messages were added to or expunged from another mailbox, which now has the given status.
+    Status {
+        /// The mailbox that changed.
+        mailbox: String,
+        /// Its status, with the fields the server chose to send.
+        status: Mailbox,
+    },
+
+    /// A mailbox was created, renamed or subscribed to, or, if it has the `\NonExistent`
+    /// attribute, deleted or unsubscribed from. See [`Name::old_name`] for renames.
+    Mailbox(Name<'static>),
+
+    /// The metadata of a mailbox, or of the server if `mailbox` is empty, changed.
+    Metadata {
+        /// The mailbox whose metadata changed.
+        mailbox: String,
+        /// The names of the entries that changed.
+        entries: Vec<String>,
+    },
+
+    /// The server could not keep up with the events, and stopped reporting them. The client must
+    /// synchronize its state by other means, and call [`Session::notify`] again.
+    Overflow,
+}
+
+impl TryFrom<UnsolicitedResponse> for Notification {
+    type Error = UnsolicitedResponse;
+
+    fn try_from(response: UnsolicitedResponse) -> std::result::Result<Self, Self::Error> {
+        match response {
+            UnsolicitedResponse::Exists(n) => Ok(Notification::Exists(n)),
+            UnsolicitedResponse::Expunge(n) => Ok(Notification::Expunge(n)),
+            UnsolicitedResponse::Vanished { uids, .. } => Ok(Notification::Vanished(uids)),
+            UnsolicitedResponse::Fetch { id, attributes } => {
+                Ok(Notification::Fetch { id, attributes })
+            }
+            UnsolicitedResponse::MailboxStatus { mailbox, status } => {
+                Ok(Notification::Status { mailbox, status })
+            }
+            UnsolicitedResponse::List(name) => Ok(Notification::Mailbox(name)),
+            UnsolicitedResponse::Metadata {
+                mailbox,
+                metadata_entries,
+            } => Ok(Notification::Metadata {
+                mailbox,
+                entries: metadata_entries,
+            }),
+            UnsolicitedResponse::Ok {
+                code: None,
+                information: Some(information),
+            } if matches!(
+                information.get(..NOTIFICATION_OVERFLOW.len()),
+                Some(code) if code.eq_ignore_ascii_case(NOTIFICATION_OVERFLOW)
+            ) =>
+            {
+                Ok(Notification::Overflow)
+            }
+            response => Err(response),
+        }
+    }
+}
+
+impl<'a> Name<'a> {
+    /// The previous name of a mailbox that was renamed, as reported by a server with the NOTIFY
+    /// extension, see [RFC 5465, section 5.4](https://tools.ietf.org/html/rfc5465#section-5.4).
+    pub fn old_name(&self) -> Option<&str> {
+        self.old_name.as_deref()
+    }
+}
+
+/// A builder for the `NOTIFY` command, see [`Session::notify`].
+#[must_use]
+pub struct NotifyCmd<'a, T: Read + Write> {
+    session: &'a mut Session<T>,
+    status: bool,
+    groups: Vec<(MailboxFilter<'a>, Vec<NotifyEvent>)>,
+}
+
+impl<'a, T: Read + Write> NotifyCmd<'a, T> {
+    /// Ask the server to send the current status of the mailboxes in the event groups right
+    /// away, so the client can start from a known state.
+    pub fn status(&mut self) -> &mut Self {
+        self.status = true;
+        self
+    }
+
+    /// Add an event group: the server is to report the given events in the given mailboxes. With
+    /// no events, the server reports nothing for these mailboxes, which is useful to exclude
+    /// them from a later, wider group.
+    pub fn group(
+        &mut self,
+        mailboxes: MailboxFilter<'a>,
+        events: impl IntoIterator<Item = NotifyEvent>,
+    ) -> &mut Self {
+        self.groups.push((mailboxes, events.into_iter().collect()));
+        self
+    }
+
+    /// Finishes up the command and executes it.
+    ///
+    /// Without any event group, this is the same as [`Session::notify_none`].
+    pub fn finish(&mut self) -> Result<()> {
+        if self.groups.is_empty() {
+            return self.session.notify_none();
+        }
+
+        let mut groups = Vec::new();
+        for (mailboxes, events) in &self.groups {
+            use MailboxFilter::*;
+
+            let mailboxes = match mailboxes {
+                Selected => "selected".to_string(),
+                SelectedDelayed => "selected-delayed".to_string(),
+                Inboxes => "inboxes".to_string(),
+                Personal => "personal".to_string(),
+                Subscribed => "subscribed".to_string(),
+                Subtree(names) => format!("subtree ({})", self.mailbox_names(names)?),
+                Mailboxes(names) => format!("mailboxes ({})", self.mailbox_names(names)?),
+            };
+            let events = if events.is_empty() {
+                "NONE".to_string()
+            } else {
+                format!("({})", iter_join(events.iter(), " "))
+            };
+            groups.push(format!("({} {})", mailboxes, events));
+        }
+
+        let lines = self.session.run_command_and_read_response(format!(
+            "NOTIFY SET{} {}",
+            if self.status { " STATUS" } else { "" },
+            groups.join(" ")
+        ))?;
+        parse_noop(lines, &mut self.session.unsolicited_responses)
+    }
+
+    fn mailbox_names(&self, names: &[&str]) -> Result<String> {
+        let names = names
+            .iter()
+            .map(|name| validate_str("NOTIFY", "mailbox", &self.session.encode_mailbox_name(name)))
+            .collect::<Result<Vec<_>>>()?;
+        Ok(names.join(" "))
+    }
+}
+
+impl<T: Read + Write> Session<T> {
+    /// Ask the server to report events in mailboxes other than the selected one, with the
+    /// [`NOTIFY` command](https://tools.ietf.org/html/rfc5465#section-3).
+    ///
+    /// The events are set up with the returned [`NotifyCmd`], and replace those of any earlier
+    /// `NOTIFY` command. They are reported as [`UnsolicitedResponse`]s, which
+    /// [`Notification::try_from`] converts; see the [module documentation](self) for an example.
+    ///
+    /// As long as event groups other than [`MailboxFilter::Selected`] are active, the client
+    /// should not use [`Session::status`], since the server reports the status of those mailboxes
+    /// on its own.
+    ///
+    /// This requires that the server advertises the `NOTIFY` capability.
+    pub fn notify(&mut self) -> NotifyCmd<'_, T> {
+        NotifyCmd {
+            session: self,
+            status: false,
+            groups: Vec::new(),
+        }
+    }
+
+    /// Ask the server to stop reporting events, with the [`NOTIFY NONE`
+    /// command](https://tools.ietf.org/html/rfc5465#section-3.1).
+    pub fn notify_none(&mut self) -> Result<()> {
+        self.run_command_and_read_response("NOTIFY NONE")
+            .and_then(|lines| parse_noop(lines, &mut self.unsolicited_responses))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock_stream::MockStream;
+    use crate::Client;
+
+    #[test]
+    fn notify_set() {
+        let response = b"a1 OK [CAPABILITY IMAP4rev1 NOTIFY] Logged in\r\n\
+            * STATUS Sent (MESSAGES 4 UIDNEXT 20)\r\n\
+            a2 OK NOTIFY completed\r\n"
+            .to_vec();
+        let mock_stream = MockStream::new(response);
+        let client = Client::new(mock_stream);
+        let mut session = client.login("username", "password").unwrap();
+        session
+            .notify()
+            .status()
+            .group(
+                MailboxFilter::Selected,
+                [
+                    NotifyEvent::MessageNew,
+                    NotifyEvent::MessageExpunge,
+                    NotifyEvent::FlagChange,
+                ],
+            )
+            .group(
+                MailboxFilter::Mailboxes(vec!["Sent", "Shared/Team"]),
+                [NotifyEvent::MessageNew, NotifyEvent::MessageExpunge],
+            )
+            .group(MailboxFilter::Subtree(vec!["Archive"]), [])
+            .group(
+                MailboxFilter::Personal,
+                [NotifyEvent::MailboxName, NotifyEvent::SubscriptionChange],
+            )
+            .finish()
+            .unwrap();
+        assert!(session.stream.get_ref().written_buf.ends_with(
+            b"a2 NOTIFY SET STATUS (selected (MessageNew MessageExpunge FlagChange)) \
+            (mailboxes (\"Sent\" \"Shared/Team\") (MessageNew MessageExpunge)) \
+            (subtree (\"Archive\") NONE) \
+            (personal (MailboxName SubscriptionChange))\r\n"
+        ));
+
+        let notifications: Vec<_> = session
+            .take_all_unsolicited()
+            .filter_map(|response| Notification::try_from(response).ok())
+            .collect();
+        let status = Mailbox {
+            exists: 4,
+            uid_next: Some(20),
+            ..Mailbox::default()
+        };
+        assert_eq!(
+            notifications,
+            vec![Notification::Status {
+                mailbox: "Sent".to_string(),
+                status
+            }]
+        );
+    }
+
+    #[test]
+    fn notify_none() {
+        let response = b"a1 OK Logged in\r\n\
+            a2 OK NOTIFY completed\r\n\
+            a3 OK NOTIFY completed\r\n"
+            .to_vec();
+        let mock_stream = MockStream::new(response);
+        let client = Client::new(mock_stream);
+        let mut session = client.login("username", "password").unwrap();
+        session.notify_none().unwrap();
+        assert!(session
+            .stream
+            .get_ref()
+            .written_buf
+            .ends_with(b"a2 NOTIFY NONE\r\n"));
+        session.notify().finish().unwrap();
+        assert!(session
+            .stream
+            .get_ref()
+            .written_buf
+            .ends_with(b"a3 NOTIFY NONE\r\n"));
+    }
+
+    #[test]
+    fn notifications_on_noop() {
+        let response = b"a1 OK Logged in\r\n\
+            * LIST () \"/\" \"Projects/New\" (\"OLDNAME\" (\"Projects/Old\"))\r\n\
+            * LIST (\\NonExistent) \"/\" \"Trash/Stale\"\r\n\
+            * 4 EXISTS\r\n\
+            * 2 FETCH (FLAGS (\\Seen))\r\n\
+            * METADATA \"INBOX\" /shared/comment\r\n\
+            * OK [NOTIFICATIONOVERFLOW] Too many events\r\n\
+            * OK Still here\r\n\
+            a2 OK NOOP completed\r\n"
+            .to_vec();
+        let mock_stream = MockStream::new(response);
+        let client = Client::new(mock_stream);
+        let mut session = client.login("username", "password").unwrap();
+        session.noop().unwrap();
+        let mut responses = session.take_all_unsolicited();
+
+        let renamed = Notification::try_from(responses.next().unwrap()).unwrap();
+        match renamed {
+            Notification::Mailbox(name) => {
+                assert_eq!(name.name(), "Projects/New");
+                assert_eq!(name.old_name(), Some("Projects/Old"));
+            }
+            n => panic!("unexpected notification {:?}", n),
+        }
+        let deleted = Notification::try_from(responses.next().unwrap()).unwrap();
+        match deleted {
+            Notification::Mailbox(name) => {
+                assert!(name.is_non_existent());
+                assert_eq!(name.old_name(), None);
+            }
+            n => panic!("unexpected notification {:?}", n),
+        }
+        assert_eq!(
+            Notification::try_from(responses.next().unwrap()).unwrap(),
+            Notification::Exists(4)
+        );
+        assert!(matches!(
+            Notification::try_from(responses.next().unwrap()).unwrap(),
+            Notification::Fetch { id: 2, .. }
+        ));
+        assert_eq!(
+            Notification::try_from(responses.next().unwrap()).unwrap(),
+            Notification::Metadata {
+                mailbox: "INBOX".to_string(),
+                entries: vec!["/shared/comment".to_string()]
+            }
+        );
+        assert_eq!(
+            Notification::try_from(responses.next().unwrap()).unwrap(),
+            Notification::Overflow
+        );
+        assert!(Notification::try_from(responses.next().unwrap()).is_err());
+        assert!(responses.next().is_none());
+    }
+
+    #[test]
+    fn status_notifications() {
+        let response = b"a1 OK Logged in\r\n\
+            * STATUS Sent (MESSAGES 4 UIDNEXT 20)\r\n\
+            * STATUS Archive (MESSAGES 7 SIZE 12345 DELETED 1 DELETED-STORAGE 512 \
+            MAILBOXID (F2212ea87-6097-4256-9d51-71338625))\r\n\
+            a2 OK NOOP completed\r\n"
+            .to_vec();
+        let mock_stream = MockStream::new(response);
+        let client = Client::new(mock_stream);
+        let mut session = client.login("username", "password").unwrap();
+        session.noop().unwrap();
+        let notifications: Vec<_> = session
+            .take_all_unsolicited()
+            .filter_map(|response| Notification::try_from(response).ok())
+            .collect();
+        assert_eq!(
+            notifications,
+            vec![
+                Notification::Status {
+                    mailbox: "Sent".to_string(),
+                    status: Mailbox {
+                        exists: 4,
+                        uid_next: Some(20),
+                        ..Mailbox::default()
+                    }
+                },
+                Notification::Status {
+                    mailbox: "Archive".to_string(),
+                    status: Mailbox {
+                        exists: 7,
+                        size: Some(12345),
+                        deleted: Some(1),
+                        deleted_storage: Some(512),
+                        mailbox_id: Some("F2212ea87-6097-4256-9d51-71338625".to_string()),
+                        ..Mailbox::default()
+                    }
+                },
+            ]
+        );
+
+        let (rest, response) =
+            crate::parse::parse_idle(b"* STATUS Archive (MESSAGES 8 SIZE 23456)\r\n");
+        assert!(rest.is_empty());
+        match Notification::try_from(response.unwrap().unwrap()) {
+            Ok(Notification::Status { mailbox, status }) => {
+                assert_eq!(mailbox, "Archive");
+                assert_eq!(status.exists, 8);
+                assert_eq!(status.size, Some(23456));
+            }
+            n => panic!("unexpected notification {:?}", n),
+        }
+    }
+
+    #[test]
+    fn notifications_utf7() {
+        let response = b"a1 OK Logged in\r\n\
+            a2 OK NOTIFY completed\r\n\
+            * STATUS Entw&APw-rfe (MESSAGES 3)\r\n\
+            * LIST () \"/\" \"Alte Entw&APw-rfe\" (\"OLDNAME\" (\"Entw&APw-rfe\"))\r\n\
+            a3 OK NOOP completed\r\n"
+            .to_vec();
+        let mock_stream = MockStream::new(response);
+        let client = Client::new(mock_stream);
+        let mut session = client.login("username", "password").unwrap();
+        session
+            .notify()
+            .group(
+                MailboxFilter::Mailboxes(vec!["Entwürfe"]),
+                [NotifyEvent::MessageNew, NotifyEvent::MailboxName],
+            )
+            .finish()
+            .unwrap();
+        assert!(session.stream.get_ref().written_buf.ends_with(
+            b"a2 NOTIFY SET (mailboxes (\"Entw&APw-rfe\") (MessageNew MailboxName))\r\n"
+        ));
+
+        session.noop().unwrap();
+        let mut notifications = session
+            .take_all_unsolicited()
+            .map(|response| Notification::try_from(response).unwrap());
+        match notifications.next().unwrap() {
+            Notification::Status { mailbox, status } => {
+                assert_eq!(mailbox, "Entwürfe");
+                assert_eq!(status.exists, 3);
+            }
+            n => panic!("unexpected notification {:?}", n),
+        }
+        match notifications.next().unwrap() {
+            Notification::Mailbox(name) => {
+                assert_eq!(name.name(), "Alte Entwürfe");
+                assert_eq!(name.old_name(), Some("Entwürfe"));
+            }
+            n => panic!("unexpected notification {:?}", n),
+        }
+        assert!(notifications.next().is_none());
+    }
+
+    #[test]
+    fn idle_notification() {
+        let (rest, response) = crate::parse::parse_idle(
+            b"* LIST () \"/\" \"Projects/New\" (\"OLDNAME\" (\"Projects/Old\"))\r\n",
+        );
+        assert!(rest.is_empty());
+        match Notification::try_from(response.unwrap().unwrap()) {
+            Ok(Notification::Mailbox(name)) => {
+                assert_eq!(name.old_name(), Some("Projects/Old"))
+            }
+            n => panic!("unexpected notification {:?}", n),
+        }
+    }
+}
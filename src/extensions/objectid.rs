@@ -0,0 +1,187 @@
+//! Adds support for the IMAP OBJECTID extension specified in [RFC
+//! 8474](https://tools.ietf.org/html/rfc8474).
+//!
+//! Mailbox names and UIDs change when mailboxes are renamed or messages are moved, so they are
+//! poor keys for a local cache. With OBJECTID, the server assigns unique identifiers that stay the
+//! same across such changes:
+//!
+//!  - every mailbox has a `MAILBOXID`, returned by [`Session::create`](crate::Session::create) and
+//!    available as [`Mailbox::mailbox_id`](crate::types::Mailbox::mailbox_id) after `SELECT`,
+//!    `EXAMINE`, or a `STATUS` that asks for `MAILBOXID`;
+//!  - every message has an `EMAILID`, which is the same for copies of a message in other
+//!    mailboxes, and a `THREADID` shared by the messages of a conversation. They are fetch items,
+//!    available through [`Fetch::email_id`] and [`Fetch::thread_id`].
+//!
+//! Messages can be searched by their identifiers with the `EMAILID <id>` and `THREADID <id>`
+//! search keys, for example with [`Session::uid_search`](crate::Session::uid_search).
+
+use crate::types::{ExtensionAttribute, Fetch};
+use imap_proto::parser::core::nil;
+use nom::{
+    branch::alt,
+    bytes::complete::{tag, tag_no_case, take_while1},
+    character::complete::char,
+    combinator::{map, map_res},
+    sequence::{delimited, preceded},
+    IResult,
+};
+use std::borrow::Cow;
+
+impl<'a> Fetch<'a> {
+    /// The unique identifier of this message, if `EMAILID` was included in the `query` argument
+    /// to `FETCH`. Copies of a message in other mailboxes have the same identifier.
+    ///
+    /// See [section 5.1 of RFC 8474](https://tools.ietf.org/html/rfc8474#section-5.1) for details.
+    pub fn email_id(&self) -> Option<&str> {
+        self.extensions.iter().find_map(|attr| match attr {
+            ExtensionAttribute::EmailId(id) => Some(&**id),
+            _ => None,
+        })
+    }
+
+    /// The identifier of the thread this message belongs to, if `THREADID` was included in the
+    /// `query` argument to `FETCH` and the server groups messages into threads.
+    ///
+    /// See [section 5.2 of RFC 8474](https://tools.ietf.org/html/rfc8474#section-5.2) for details.
+    pub fn thread_id(&self) -> Option<&str> {
+        self.extensions.iter().find_map(|attr| match attr {
+            ExtensionAttribute::ThreadId(id) => id.as_deref(),
+            _ => None,
+        })
+    }
+}
+
+// objectid = 1*255(ALPHA / DIGIT / "_" / "-")
+fn objectid(i: &[u8]) -> IResult<&[u8], &str> {
+    map_res(
+        take_while1(|c: u8| c.is_ascii_alphanumeric() || c == b'_' || c == b'-'),
+        std::str::from_utf8,
+    )(i)
+}
+
+// fetch-emailid-resp = "EMAILID" SP "(" objectid ")"
+// fetch-threadid-resp = "THREADID" SP ( "(" objectid ")" / nil )
+pub(crate) fn objectid_attribute(i: &[u8]) -> IResult<&[u8], ExtensionAttribute<'_>> {
+    alt((
+        map(
+            preceded(
+                tag_no_case("EMAILID "),
+                delimited(char('('), objectid, char(')')),
+            ),
+            |id| ExtensionAttribute::EmailId(Cow::Borrowed(id)),
+        ),
+        map(
+            preceded(
+                tag_no_case("THREADID "),
+                alt((
+                    map(delimited(char('('), objectid, char(')')), Some),
+                    map(nil, |_| None),
+                )),
+            ),
+            |id| ExtensionAttribute::ThreadId(id.map(Cow::Borrowed)),
+        ),
+    ))(i)
+}
+
+// "MAILBOXID" SP "(" objectid ")", as a status-att-val and a resp-text-code.
+pub(crate) fn mailbox_id(i: &[u8]) -> IResult<&[u8], &str> {
+    preceded(
+        tag_no_case("MAILBOXID "),
+        delimited(char('('), objectid, char(')')),
+    )(i)
+}
+
+/// Extract the `MAILBOXID` response code from the text of a response.
+///
+/// imap-proto does not know about this response code, so it ends up in the text.
+pub(crate) fn mailbox_id_code(information: &str) -> Option<&str> {
+    delimited(tag("["), mailbox_id, tag("]"))(information.as_bytes())
+        .ok()
+        .map(|(_, id)| id)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock_stream::MockStream;
+    use crate::types::Fetches;
+    use crate::Client;
+    use std::collections::VecDeque;
+
+    #[test]
+    fn parse_objectid_fetch() {
+        // Example from RFC 8474, section 5.3.
+        let lines = b"* 3 FETCH (EMAILID (Mdd3c288836c4c7a762b2Aa3) THREADID NIL)\r\n\
+            * 4 FETCH (UID 7 EMAILID (M6d99ac3275bb4e) THREADID (T64b478a75b7ea9))\r\n"
+            .to_vec();
+        let mut queue = VecDeque::new();
+        let fetches = Fetches::parse(lines, &mut queue).unwrap();
+        assert_eq!(fetches.len(), 2);
+
+        let first = fetches.get(0).unwrap();
+        assert_eq!(first.email_id(), Some("Mdd3c288836c4c7a762b2Aa3"));
+        assert_eq!(first.thread_id(), None);
+
+        let second = fetches.get(1).unwrap();
+        assert_eq!(second.uid, Some(7));
+        assert_eq!(second.email_id(), Some("M6d99ac3275bb4e"));
+        assert_eq!(second.thread_id(), Some("T64b478a75b7ea9"));
+    }
+
+    #[test]
+    fn mailbox_id_code_test() {
+        assert_eq!(
+            mailbox_id_code("[MAILBOXID (F2212ea87-6097-4256-9d51-71338625)] Ok"),
+            Some("F2212ea87-6097-4256-9d51-71338625")
+        );
+        assert_eq!(mailbox_id_code("[UNKNOWN-CTE] Ok"), None);
+        assert_eq!(mailbox_id_code("Completed"), None);
+    }
+
+    #[test]
+    fn create_and_select_mailbox_id() {
+        let response = b"a1 OK Logged in\r\n\
+            a2 OK [MAILBOXID (F2212ea87-6097-4256-9d51-71338625)] Completed\r\n\
+            * 0 EXISTS\r\n\
+            * OK [MAILBOXID (F2212ea87-6097-4256-9d51-71338625)] Ok\r\n\
+            a3 OK [READ-WRITE] Completed\r\n"
+            .to_vec();
+        let mock_stream = MockStream::new(response);
+        let client = Client::new(mock_stream);
+        let mut session = client.login("username", "password").unwrap();
+        let id = session.create("foo").unwrap();
+        assert_eq!(id.as_deref(), Some("F2212ea87-6097-4256-9d51-71338625"));
+        let mailbox = session.select("foo").unwrap();
+        assert_eq!(
+            mailbox.mailbox_id.as_deref(),
+            Some("F2212ea87-6097-4256-9d51-71338625")
+        );
+    }
+
+    #[test]
+    fn status_and_search_by_id() {
+        let response = b"a1 OK Logged in\r\n\
+            * STATUS foo (MAILBOXID (F2212ea87-6097-4256-9d51-71338625) MESSAGES 3)\r\n\
+            a2 OK Completed\r\n\
+            * SEARCH 1 5\r\n\
+            a3 OK Completed\r\n"
+            .to_vec();
+        let mock_stream = MockStream::new(response);
+        let client = Client::new(mock_stream);
+        let mut session = client.login("username", "password").unwrap();
+        let mailbox = session.status("foo", "(MAILBOXID MESSAGES)").unwrap();
+        assert_eq!(
+            mailbox.mailbox_id.as_deref(),
+            Some("F2212ea87-6097-4256-9d51-71338625")
+        );
+        assert_eq!(mailbox.exists, 3);
+
+        let uids = session.uid_search("THREADID T64b478a75b7ea9").unwrap();
+        assert!(session
+            .stream
+            .get_ref()
+            .written_buf
+            .ends_with(b"a3 UID SEARCH THREADID T64b478a75b7ea9\r\n"));
+        assert_eq!(uids.len(), 2);
+    }
+}
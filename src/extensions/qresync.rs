@@ -0,0 +1,362 @@
+//! Adds support for the IMAP QRESYNC extension specified in [RFC
+//! 7162](https://tools.ietf.org/html/rfc7162#section-3.2).
+//!
+//! QRESYNC lets a client that has a cached copy of a mailbox resynchronize it in a single round
+//! trip: when the mailbox is opened with [`Session::select_qresync`] or
+//! [`Session::examine_qresync`], the server reports which of the known messages have been
+//! expunged since the last synchronization, and sends `FETCH` responses for the ones whose
+//! flags changed.
+
+use crate::client::{validate_sequence_set, validate_str, Session};
+use crate::error::{Error, Result};
+use crate::parse::parse_mailbox;
+use crate::types::{Fetches, Mailbox, Uid, UnsolicitedResponse};
+use std::collections::VecDeque;
+use std::fmt;
+use std::io::{Read, Write};
+use std::ops::RangeInclusive;
+
+/// The parameters of a [`QRESYNC` `SELECT`/`EXAMINE`
+/// parameter](https://tools.ietf.org/html/rfc7162#section-3.2.5): what the client remembers
+/// about the mailbox from the last time it was synchronized.
+///
+/// Used by [`Session::select_qresync`] and [`Session::examine_qresync`].
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub struct QResync<'a> {
+    uid_validity: u32,
+    mod_seq: u64,
+    known_uids: Option<&'a str>,
+    seq_match_data: Option<(&'a str, &'a str)>,
+}
+
+impl<'a> QResync<'a> {
+    /// Creates the parameters from the last known `UIDVALIDITY` of the mailbox and the last
+    /// known mod-sequence (usually its `HIGHESTMODSEQ`).
+    pub fn new(uid_validity: u32, mod_seq: u64) -> Self {
+        QResync {
+            uid_validity,
+            mod_seq,
+            known_uids: None,
+            seq_match_data: None,
+        }
+    }
+
+    /// Restrict the resynchronization to the given set of [`Uid`]s that the client knows about.
+    ///
+    /// Without this, the server may report every message expunged since `mod_seq`, even those
+    /// the client has never seen.
+    pub fn known_uids(mut self, uids: &'a str) -> Self {
+        self.known_uids = Some(uids);
+        self
+    }
+
+    /// Give the server a sample of message sequence numbers and their corresponding [`Uid`]s,
+    /// which lets it avoid reporting expunges the client already knows about when it does not
+    /// keep track of them itself.
+    pub fn seq_match_data(mut self, seqs: &'a str, uids: &'a str) -> Self {
+        self.seq_match_data = Some((seqs, uids));
+        self
+    }
+
+    fn validate(&self, synopsis: &str) -> Result<()> {
+        if let Some(uids) = self.known_uids {
+            validate_sequence_set(synopsis, "known_uids", uids)?;
+        }
+        if let Some((seqs, uids)) = self.seq_match_data {
+            validate_sequence_set(synopsis, "seq_match_data", seqs)?;
+            validate_sequence_set(synopsis, "seq_match_data", uids)?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for QResync<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "(QRESYNC ({} {}", self.uid_validity, self.mod_seq)?;
+        if let Some(uids) = self.known_uids {
+            write!(f, " {}", uids)?;
+        }
+        if let Some((seqs, uids)) = self.seq_match_data {
+            write!(f, " ({} {})", seqs, uids)?;
+        }
+        write!(f, "))")
+    }
+}
+
+/// The result of [`Session::select_qresync`] and [`Session::examine_qresync`].
+#[non_exhaustive]
+pub struct Resync {
+    /// The state of the newly selected mailbox, as returned by [`Session::select`].
+    pub mailbox: Mailbox,
+    /// The [`Uid`]s of the messages that have been expunged since the given mod-sequence, as
+    /// reported by the `VANISHED (EARLIER)` response.
+    pub vanished: Vec<RangeInclusive<Uid>>,
+    /// The `FETCH` responses for the messages whose metadata changed since the given
+    /// mod-sequence. These include the [`Uid`], flags and mod-sequence of each message.
+    pub changed: Fetches,
+}
+
+impl<T: Read + Write> Session<T> {
+    /// Selects a mailbox like [`Session::select`], while resynchronizing the client's cached
+    /// state of it in the same round trip, as described in [RFC 7162, section
+    /// 3.2.5](https://tools.ietf.org/html/rfc7162#section-3.2.5).
+    ///
+    /// If the `UIDVALIDITY` in `params` still matches the mailbox, the returned [`Resync`] lists
+    /// the messages that have vanished and the ones that changed since the given mod-sequence.
+    /// Otherwise, the server ignores the parameters and the client has to discard its cache.
+    ///
+    /// ```no_run
+    /// # use imap::extensions::qresync::QResync;
+    /// # use imap_proto::Capability;
+    /// # {} #[cfg(feature = "native-tls")]
+    /// # fn main() {
+    /// # let client = imap::ClientBuilder::new("imap.example.com", 993)
+    ///     .connect().unwrap();
+    /// # let mut session = client.login("name", "pw").unwrap();
+    /// session.enable(&[Capability::Atom("QRESYNC".into())]).unwrap();
+    /// let resync = session
+    ///     .select_qresync("INBOX", QResync::new(67890007, 90060115194045000).known_uids("1:200"))
+    ///     .unwrap();
+    /// for uids in &resync.vanished {
+    ///     println!("expunged: {:?}", uids);
+    /// }
+    /// for fetch in resync.changed.iter() {
+    ///     println!("changed: {:?} {:?}", fetch.uid, fetch.flags());
+    /// }
+    /// # }
+    /// ```
+    ///
+    /// This requires that `QRESYNC` has been enabled on this session with [`Session::enable`],
+    /// which is only allowed before a mailbox is selected. Otherwise, [`Error::NotEnabled`] is
+    /// returned without sending anything to the server.
+    ///
+    /// [`Error::NotEnabled`]: crate::error::Error::NotEnabled
+    pub fn select_qresync(
+        &mut self,
+        mailbox_name: impl AsRef<str>,
+        params: QResync<'_>,
+    ) -> Result<Resync> {
+        self.select_with_qresync("SELECT", mailbox_name.as_ref(), params)
+    }
+
+    /// Equivalent to [`Session::select_qresync`], except that the mailbox is opened read-only
+    /// like with [`Session::examine`].
+    pub fn examine_qresync(
+        &mut self,
+        mailbox_name: impl AsRef<str>,
+        params: QResync<'_>,
+    ) -> Result<Resync> {
+        self.select_with_qresync("EXAMINE", mailbox_name.as_ref(), params)
+    }
+
+    fn select_with_qresync(
+        &mut self,
+        synopsis: &str,
+        mailbox_name: &str,
+        params: QResync<'_>,
+    ) -> Result<Resync> {
+        let mailbox = validate_str(synopsis, "mailbox", &self.encode_mailbox_name(mailbox_name))?;
+        params.validate(synopsis)?;
+        if !self.enabled().has_str("QRESYNC") {
+            return Err(Error::NotEnabled("QRESYNC".to_string()));
+        }
+
+        let (mut lines, ok) = match self.run(format!("{} {} {}", synopsis, mailbox, params)) {
+            Ok(response) => response,
+            Err(e) => {
+                self.track_selected(mailbox_name, synopsis == "EXAMINE", Err(&e));
+                return Err(e);
+            }
+        };
+
+        let mut responses = VecDeque::new();
+        let mailbox = parse_mailbox(&lines[..], &mut responses)?;
+        self.track_selected(mailbox_name, synopsis == "EXAMINE", Ok(&mailbox));
+        let mut vanished = Vec::new();
+        for response in responses {
+            match response {
+                UnsolicitedResponse::Vanished {
+                    earlier: true,
+                    uids,
+                } => vanished.extend(uids),
+                // collected separately below
+                UnsolicitedResponse::Fetch { .. } => {}
+                response => self.unsolicited_responses.push_back(response),
+            }
+        }
+
+        // Everything but the FETCH responses was dealt with above.
+        lines.truncate(ok);
+        let changed = Fetches::parse(lines, &mut VecDeque::new())?;
+
+        Ok(Resync {
+            mailbox,
+            vanished,
+            changed,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::client::testutils::assert_validation_error_session;
+    use crate::mock_stream::MockStream;
+    use crate::Client;
+    use imap_proto::Capability;
+
+    #[test]
+    fn qresync_params() {
+        assert_eq!(
+            QResync::new(67890007, 20050715194045000).to_string(),
+            "(QRESYNC (67890007 20050715194045000))"
+        );
+        assert_eq!(
+            QResync::new(67890007, 20050715194045000)
+                .known_uids("41,43:211,214:541")
+                .to_string(),
+            "(QRESYNC (67890007 20050715194045000 41,43:211,214:541))"
+        );
+        assert_eq!(
+            QResync::new(67890007, 90060115194045000)
+                .known_uids("1:29997")
+                .seq_match_data("5000,7500,9000,9990:9999", "15000,22500,27000,29970,29973")
+                .to_string(),
+            "(QRESYNC (67890007 90060115194045000 1:29997 \
+             (5000,7500,9000,9990:9999 15000,22500,27000,29970,29973)))"
+        );
+    }
+
+    #[test]
+    fn select_qresync() {
+        let response = b"a1 OK Logged in\r\n\
+            * ENABLED QRESYNC\r\n\
+            a2 OK ENABLE completed\r\n\
+            * 314 EXISTS\r\n\
+            * 15 RECENT\r\n\
+            * OK [UNSEEN 12] Message 12 is first unseen\r\n\
+            * OK [UIDVALIDITY 3857529045] UIDs valid\r\n\
+            * OK [UIDNEXT 4392] Predicted next UID\r\n\
+            * FLAGS (\\Answered \\Flagged \\Draft \\Deleted \\Seen)\r\n\
+            * OK [PERMANENTFLAGS (\\Deleted \\Seen \\*)] Limited\r\n\
+            * OK [HIGHESTMODSEQ 20010715194045319] Ok\r\n\
+            * VANISHED (EARLIER) 41,43:116,118,120:211,214:540\r\n\
+            * 49 FETCH (UID 117 FLAGS (\\Seen \\Answered) MODSEQ (20010715194032001))\r\n\
+            * 50 FETCH (UID 119 FLAGS (\\Draft $MDNSent) MODSEQ (20010715194032001))\r\n\
+            * 1 EXPUNGE\r\n\
+            a3 OK [READ-WRITE] mailbox selected\r\n"
+            .to_vec();
+        let mock_stream = MockStream::new(response);
+        let client = Client::new(mock_stream);
+        let mut session = client.login("testuser", "pass").unwrap();
+        session
+            .enable(&[Capability::Atom("QRESYNC".into())])
+            .unwrap();
+        let resync = session
+            .select_qresync(
+                "INBOX",
+                QResync::new(3857529045, 20010715194032001).known_uids("41:211,214:541"),
+            )
+            .unwrap();
+        assert!(session.stream.get_ref().written_buf.ends_with(
+            b"a2 ENABLE QRESYNC\r\n\
+              a3 SELECT \"INBOX\" (QRESYNC (3857529045 20010715194032001 41:211,214:541))\r\n"
+        ));
+        assert_eq!(resync.mailbox.exists, 314);
+        assert_eq!(resync.mailbox.uid_validity, Some(3857529045));
+        assert_eq!(resync.mailbox.highest_mod_seq, Some(20010715194045319));
+        assert_eq!(
+            resync.vanished,
+            vec![41..=41, 43..=116, 118..=118, 120..=211, 214..=540]
+        );
+        assert_eq!(resync.changed.len(), 2);
+        let first = resync.changed.get(0).unwrap();
+        assert_eq!(first.message, 49);
+        assert_eq!(first.uid, Some(117));
+        assert_eq!(first.mod_seq(), Some(20010715194032001));
+        assert_eq!(resync.changed.get(1).unwrap().uid, Some(119));
+        assert_eq!(
+            session.take_all_unsolicited().collect::<Vec<_>>(),
+            vec![UnsolicitedResponse::Expunge(1)]
+        );
+    }
+
+    #[test]
+    fn examine_qresync() {
+        let response = b"a1 OK Logged in\r\n\
+            * ENABLED QRESYNC\r\n\
+            a2 OK ENABLE completed\r\n\
+            * 3 EXISTS\r\n\
+            * OK [UIDVALIDITY 1] UIDs valid\r\n\
+            * OK [HIGHESTMODSEQ 12] Ok\r\n\
+            a3 OK [READ-ONLY] mailbox examined\r\n"
+            .to_vec();
+        let mock_stream = MockStream::new(response);
+        let client = Client::new(mock_stream);
+        let mut session = client.login("testuser", "pass").unwrap();
+        session
+            .enable(&[Capability::Atom("QRESYNC".into())])
+            .unwrap();
+        let resync = session
+            .examine_qresync("INBOX", QResync::new(1, 10))
+            .unwrap();
+        assert!(session
+            .stream
+            .get_ref()
+            .written_buf
+            .ends_with(b"a3 EXAMINE \"INBOX\" (QRESYNC (1 10))\r\n"));
+        assert!(resync.mailbox.is_read_only);
+        assert!(resync.vanished.is_empty());
+        assert!(resync.changed.is_empty());
+    }
+
+    #[test]
+    fn select_qresync_not_enabled() {
+        let response = b"a1 OK Logged in\r\n\
+            * 3 EXISTS\r\n\
+            a2 OK [READ-WRITE] mailbox selected\r\n"
+            .to_vec();
+        let mock_stream = MockStream::new(response);
+        let client = Client::new(mock_stream);
+        let mut session = client.login("testuser", "pass").unwrap();
+        session.select("INBOX").unwrap();
+        let written = session.stream.get_ref().written_buf.len();
+        let resync = session.select_qresync("Sent", QResync::new(1, 10));
+        assert!(matches!(resync, Err(Error::NotEnabled(ref e)) if e == "QRESYNC"));
+        // No ENABLE, which the server would reject with a mailbox selected, nor SELECT is sent.
+        assert_eq!(session.stream.get_ref().written_buf.len(), written);
+        assert_eq!(session.selected().unwrap().name, "INBOX");
+    }
+
+    #[test]
+    fn select_qresync_enable_ignored() {
+        let response = b"a1 OK Logged in\r\n\
+            * ENABLED\r\n\
+            a2 OK ENABLE completed\r\n"
+            .to_vec();
+        let mock_stream = MockStream::new(response);
+        let client = Client::new(mock_stream);
+        let mut session = client.login("testuser", "pass").unwrap();
+        let enabled = session
+            .enable(&[Capability::Atom("QRESYNC".into())])
+            .unwrap();
+        assert!(enabled.is_empty());
+        let resync = session.select_qresync("INBOX", QResync::new(1, 10));
+        assert!(matches!(resync, Err(Error::NotEnabled(_))));
+        assert!(session
+            .stream
+            .get_ref()
+            .written_buf
+            .ends_with(b"a2 ENABLE QRESYNC\r\n"));
+    }
+
+    #[test]
+    fn select_qresync_validation_known_uids() {
+        assert_validation_error_session(
+            |mut session| session.select_qresync("INBOX", QResync::new(1, 1).known_uids("1, 2")),
+            "SELECT",
+            "known_uids",
+            ' ',
+        );
+    }
+}
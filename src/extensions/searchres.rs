@@ -0,0 +1,90 @@
+//! Adds support for the IMAP SEARCHRES extension specified in [RFC
+//! 5182](https://tools.ietf.org/html/rfc5182).
+//!
+//! Clients often search for messages only to act on all of them right away, for example to flag
+//! or move them. Rather than sending the matching messages back to the server, a client can ask
+//! the server to save the result of a search with [`SearchReturn::Save`], and then refer to it
+//! as [`SavedSearch`] wherever a sequence set is expected.
+
+#[cfg(doc)]
+use crate::extensions::esearch::SearchReturn;
+use std::fmt;
+
+/// The result of the last search saved with [`SearchReturn::Save`], which may be passed instead
+/// of a sequence set or UID set to commands such as [`Session::uid_fetch`], [`Session::uid_store`],
+/// [`Session::uid_copy`], [`Session::uid_mv`] and [`Session::uid_expunge`]. It is sent as `$`.
+///
+/// The saved result holds UIDs, so using it with the non-UID variants of these commands is
+/// inefficient, but allowed. The server keeps the saved result until the next search that saves
+/// its result, or until another mailbox is selected. This requires the `SEARCHRES` capability.
+///
+/// ```no_run
+/// # use imap::extensions::esearch::SearchReturn;
+/// # use imap::extensions::searchres::SavedSearch;
+/// # fn example(session: &mut imap::Session<std::net::TcpStream>) -> imap::Result<()> {
+/// session.uid_esearch(&[SearchReturn::Save], "FROM \"newsletter@example.com\"")?;
+/// session.uid_mv(SavedSearch, "Newsletters")?;
+/// # Ok(())
+/// # }
+/// ```
+///
+/// [`Session::uid_fetch`]: crate::Session::uid_fetch
+/// [`Session::uid_store`]: crate::Session::uid_store
+/// [`Session::uid_copy`]: crate::Session::uid_copy
+/// [`Session::uid_mv`]: crate::Session::uid_mv
+/// [`Session::uid_expunge`]: crate::Session::uid_expunge
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct SavedSearch;
+
+impl AsRef<str> for SavedSearch {
+    fn as_ref(&self) -> &str {
+        "$"
+    }
+}
+
+impl fmt::Display for SavedSearch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_ref())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::extensions::esearch::SearchReturn;
+    use crate::mock_stream::MockStream;
+    use crate::Client;
+
+    #[test]
+    fn saved_search() {
+        let response = b"a1 OK [CAPABILITY IMAP4rev1 ESEARCH SEARCHRES] Logged in\r\n\
+            a2 OK SEARCH completed\r\n\
+            * 3 FETCH (UID 17 FLAGS (\\Seen))\r\n\
+            a3 OK FETCH completed\r\n\
+            a4 OK STORE completed\r\n\
+            a5 OK COPY completed\r\n\
+            a6 OK MOVE completed\r\n\
+            a7 OK EXPUNGE completed\r\n"
+            .to_vec();
+        let mock_stream = MockStream::new(response);
+        let client = Client::new(mock_stream);
+        let mut session = client.login("username", "password").unwrap();
+        session.uid_esearch(&[SearchReturn::Save], "SEEN").unwrap();
+        let fetches = session.uid_fetch(SavedSearch, "FLAGS").unwrap();
+        assert_eq!(fetches.len(), 1);
+        session
+            .uid_store(SavedSearch, "+FLAGS (\\Deleted)")
+            .unwrap();
+        session.uid_copy(SavedSearch, "Trash").unwrap();
+        session.uid_mv(SavedSearch, "Archive").unwrap();
+        session.uid_expunge(SavedSearch).unwrap();
+        assert!(session.stream.get_ref().written_buf.ends_with(
+            b"a2 UID SEARCH RETURN (SAVE) SEEN\r\n\
+            a3 UID FETCH $ FLAGS\r\n\
+            a4 UID STORE $ +FLAGS (\\Deleted)\r\n\
+            a5 UID COPY $ Trash\r\n\
+            a6 UID MOVE $ \"Archive\"\r\n\
+            a7 UID EXPUNGE $\r\n"
+        ));
+    }
+}
@@ -0,0 +1,253 @@
+//! Adds support for the IMAP SPECIAL-USE and CREATE-SPECIAL-USE extensions specified in [RFC
+//! 6154](https://tools.ietf.org/html/rfc6154).
+//!
+//! Mailboxes such as the trash or the folder for sent messages are named differently on every
+//! server, and often translated to the user's language. Servers that implement SPECIAL-USE mark
+//! these mailboxes with attributes, which [`Name::special_use`] turns into a [`SpecialUse`]. The
+//! mailboxes with a special use can be listed with [`Session::special_use_mailboxes`], and servers
+//! with CREATE-SPECIAL-USE allow a client to create them with [`Session::create_with_use`].
+
+use crate::client::{validate_str, Session};
+use crate::error::Result;
+use crate::parse::parse_create;
+use crate::types::{Name, Names};
+use imap_proto::NameAttribute;
+use std::fmt;
+use std::io::{Read, Write};
+
+/// The special use of a mailbox, see [RFC 6154, section
+/// 2](https://tools.ietf.org/html/rfc6154#section-2).
+#[non_exhaustive]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SpecialUse {
+    /// The mailbox presents all messages in the user's message store, usually as a virtual
+    /// mailbox.
+    All,
+
+    /// The mailbox is used to archive messages.
+    Archive,
+
+    /// The mailbox is used to hold draft messages.
+    Drafts,
+
+    /// The mailbox presents all messages marked as important with the `\Flagged` flag, usually as
+    /// a virtual mailbox.
+    Flagged,
+
+    /// The mailbox holds messages deemed to be junk mail.
+    Junk,
+
+    /// The mailbox holds copies of messages that have been sent.
+    Sent,
+
+    /// The mailbox holds messages that have been deleted or marked for deletion.
+    Trash,
+
+    /// The mailbox presents messages deemed important to the user, see [RFC
+    /// 8457](https://tools.ietf.org/html/rfc8457#section-3).
+    Important,
+}
+
+impl SpecialUse {
+    /// The special use a mailbox attribute stands for, if it stands for one.
+    pub fn from_attribute(attribute: &NameAttribute<'_>) -> Option<Self> {
+        match attribute {
+            NameAttribute::All => Some(SpecialUse::All),
+            NameAttribute::Archive => Some(SpecialUse::Archive),
+            NameAttribute::Drafts => Some(SpecialUse::Drafts),
+            NameAttribute::Flagged => Some(SpecialUse::Flagged),
+            NameAttribute::Junk => Some(SpecialUse::Junk),
+            NameAttribute::Sent => Some(SpecialUse::Sent),
+            NameAttribute::Trash => Some(SpecialUse::Trash),
+            NameAttribute::Extension(a) if a.eq_ignore_ascii_case("\\Important") => {
+                Some(SpecialUse::Important)
+            }
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for SpecialUse {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        use SpecialUse::*;
+
+        match self {
+            All => write!(f, "\\All"),
+            Archive => write!(f, "\\Archive"),
+            Drafts => write!(f, "\\Drafts"),
+            Flagged => write!(f, "\\Flagged"),
+            Junk => write!(f, "\\Junk"),
+            Sent => write!(f, "\\Sent"),
+            Trash => write!(f, "\\Trash"),
+            Important => write!(f, "\\Important"),
+        }
+    }
+}
+
+impl Name<'_> {
+    /// The special uses of this mailbox, as given by its attributes. A mailbox may have more than
+    /// one, although most have none.
+    pub fn special_uses(&self) -> impl Iterator<Item = SpecialUse> + '_ {
+        self.attributes()
+            .iter()
+            .filter_map(SpecialUse::from_attribute)
+    }
+
+    /// The special use of this mailbox, if it has one. Should it have several, the first one is
+    /// returned. See [`Name::special_uses`].
+    pub fn special_use(&self) -> Option<SpecialUse> {
+        self.special_uses().next()
+    }
+}
+
+impl<T: Read + Write> Session<T> {
+    /// List the mailboxes that have a special use.
+    ///
+    /// Servers that advertise both the `SPECIAL-USE` and the `LIST-EXTENDED` capability are asked
+    /// for just those mailboxes, with the [`LIST (SPECIAL-USE)`
+    /// command](https://tools.ietf.org/html/rfc6154#section-5.1). Other servers need not support
+    /// that selection option, so all mailboxes are listed, and those without a special use are
+    /// left out of the result. The capabilities are requested from the server if they are not
+    /// known yet.
+    ///
+    /// Use [`Name::special_use`] to tell what each of the returned mailboxes is used for.
+    ///
+    /// ```no_run
+    /// # use imap::extensions::special_use::SpecialUse;
+    /// # fn example(session: &mut imap::Session<std::net::TcpStream>) -> imap::Result<()> {
+    /// let mailboxes = session.special_use_mailboxes()?;
+    /// let trash = mailboxes
+    ///     .iter()
+    ///     .find(|name| name.special_use() == Some(SpecialUse::Trash));
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// This requires that the server advertises the `SPECIAL-USE` capability, see
+    /// [`Session::capabilities`].
+    pub fn special_use_mailboxes(&mut self) -> Result<Names> {
+        let command = if self.has_capability("LIST-EXTENDED")? {
+            "LIST (SPECIAL-USE) \"\" \"*\""
+        } else {
+            "LIST \"\" \"*\""
+        };
+        let mut names = self
+            .run_command_and_read_response(command)
+            .and_then(|lines| Names::parse(lines, &mut self.unsolicited_responses))?;
+        names.with_names_mut(|names| names.retain(|name| name.special_use().is_some()));
+        self.decode_names(&mut names);
+        Ok(names)
+    }
+
+    /// Create a mailbox with the given special use, with the [`CREATE ... USE`
+    /// command](https://tools.ietf.org/html/rfc6154#section-3). Otherwise, this is the same as
+    /// [`Session::create`].
+    ///
+    /// The server refuses to create the mailbox if it does not allow the special use, or already
+    /// has a mailbox with that special use and does not allow another.
+    ///
+    /// This requires that the server advertises the `CREATE-SPECIAL-USE` capability.
+    pub fn create_with_use(
+        &mut self,
+        mailbox_name: impl AsRef<str>,
+        special_use: SpecialUse,
+    ) -> Result<Option<String>> {
+        self.run(format!(
+            "CREATE {} (USE ({}))",
+            validate_str(
+                "CREATE",
+                "mailbox",
+                &self.encode_mailbox_name(mailbox_name.as_ref())
+            )?,
+            special_use
+        ))
+        .and_then(|(lines, _)| parse_create(&lines, &mut self.unsolicited_responses))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock_stream::MockStream;
+    use crate::Client;
+
+    #[test]
+    fn special_use_mailboxes() {
+        let response = b"a1 OK [CAPABILITY IMAP4rev1 SPECIAL-USE LIST-EXTENDED] Logged in\r\n\
+            * LIST (\\Sent \\HasNoChildren) \"/\" \"Gesendete Objekte\"\r\n\
+            * LIST (\\HasNoChildren \\Trash) \"/\" \"Gel&APY-scht\"\r\n\
+            * LIST (\\Important) \"/\" Wichtig\r\n\
+            * LIST (\\All \\Flagged \\NoInferiors) \"/\" \"Alle\"\r\n\
+            a2 OK LIST completed\r\n"
+            .to_vec();
+        let mock_stream = MockStream::new(response);
+        let client = Client::new(mock_stream);
+        let mut session = client.login("username", "password").unwrap();
+        let names = session.special_use_mailboxes().unwrap();
+        assert!(session
+            .stream
+            .get_ref()
+            .written_buf
+            .ends_with(b"a2 LIST (SPECIAL-USE) \"\" \"*\"\r\n"));
+        let uses: Vec<_> = names.iter().map(|n| (n.name(), n.special_use())).collect();
+        assert_eq!(
+            uses,
+            vec![
+                ("Gesendete Objekte", Some(SpecialUse::Sent)),
+                ("Gelöscht", Some(SpecialUse::Trash)),
+                ("Wichtig", Some(SpecialUse::Important)),
+                ("Alle", Some(SpecialUse::All)),
+            ]
+        );
+        assert_eq!(
+            names.get(3).unwrap().special_uses().collect::<Vec<_>>(),
+            vec![SpecialUse::All, SpecialUse::Flagged]
+        );
+    }
+
+    #[test]
+    fn special_use_mailboxes_without_list_extended() {
+        let response = b"a1 OK [CAPABILITY IMAP4rev1 SPECIAL-USE] Logged in\r\n\
+            * LIST (\\HasNoChildren) \"/\" INBOX\r\n\
+            * LIST (\\Sent \\HasNoChildren) \"/\" \"Gesendete Objekte\"\r\n\
+            * LIST (\\HasChildren) \"/\" Projekte\r\n\
+            * LIST (\\HasNoChildren \\Trash) \"/\" \"Gel&APY-scht\"\r\n\
+            a2 OK LIST completed\r\n"
+            .to_vec();
+        let mock_stream = MockStream::new(response);
+        let client = Client::new(mock_stream);
+        let mut session = client.login("username", "password").unwrap();
+        let names = session.special_use_mailboxes().unwrap();
+        assert!(session
+            .stream
+            .get_ref()
+            .written_buf
+            .ends_with(b"a2 LIST \"\" \"*\"\r\n"));
+        let uses: Vec<_> = names.iter().map(|n| (n.name(), n.special_use())).collect();
+        assert_eq!(
+            uses,
+            vec![
+                ("Gesendete Objekte", Some(SpecialUse::Sent)),
+                ("Gelöscht", Some(SpecialUse::Trash)),
+            ]
+        );
+    }
+
+    #[test]
+    fn create_with_use() {
+        let response = b"a1 OK [CAPABILITY IMAP4rev1 CREATE-SPECIAL-USE] Logged in\r\n\
+            a2 OK CREATE completed\r\n"
+            .to_vec();
+        let mock_stream = MockStream::new(response);
+        let client = Client::new(mock_stream);
+        let mut session = client.login("username", "password").unwrap();
+        session
+            .create_with_use("Papierkorb", SpecialUse::Trash)
+            .unwrap();
+        assert!(session
+            .stream
+            .get_ref()
+            .written_buf
+            .ends_with(b"a2 CREATE \"Papierkorb\" (USE (\\Trash))\r\n"));
+    }
+}
@@ -0,0 +1,337 @@
+//! Adds support for the IMAP THREAD extension specified in [RFC
+//! 5256](https://tools.ietf.org/html/rfc5256#section-4).
+//!
+//! The THREAD command is a variant of SEARCH with threading semantics for the results: the
+//! matching messages are grouped into conversations, each returned as a tree of [`Thread`]s in
+//! which replies are children of the message they reply to. Like SORT, it takes the threading
+//! algorithm and the searching charset before the searching criteria.
+
+use crate::client::Session;
+use crate::command::Command;
+use crate::error::Result;
+use crate::extensions::sort::SortCharset;
+use crate::parse::{parse_many_into, MapOrNot};
+use crate::types::{Seq, Uid, UnsolicitedResponse};
+use imap_proto::parser::core::number;
+use nom::{
+    bytes::complete::tag_no_case,
+    character::complete::{char, space0},
+    combinator::map,
+    multi::{many0, many1},
+    sequence::{preceded, terminated},
+    IResult,
+};
+use std::borrow::Cow;
+use std::collections::VecDeque;
+use std::fmt;
+use std::io::{Read, Write};
+
+/// The algorithm used by [`Session::thread`] to group messages into threads, see [RFC 5256,
+/// section 3](https://tools.ietf.org/html/rfc5256#section-3).
+///
+/// The algorithms a server implements are advertised as `THREAD=<algorithm>` capabilities.
+#[non_exhaustive]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum ThreadAlgorithm<'a> {
+    /// Groups messages by their base subject, and sorts each thread by sent date. Also known as
+    /// "poor man's threading".
+    OrderedSubject,
+
+    /// Groups messages by their `References` and `In-Reply-To` headers, falling back to the base
+    /// subject for messages that have none.
+    References,
+
+    /// Like [`ThreadAlgorithm::References`], but does not group messages by subject, and sorts
+    /// threads by their most recent message. See [RFC
+    /// 5957](https://tools.ietf.org/html/rfc5957#section-3).
+    Refs,
+
+    /// Some other algorithm advertised by the server.
+    Custom(Cow<'a, str>),
+}
+
+impl fmt::Display for ThreadAlgorithm<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        use ThreadAlgorithm::*;
+
+        match self {
+            OrderedSubject => write!(f, "ORDEREDSUBJECT"),
+            References => write!(f, "REFERENCES"),
+            Refs => write!(f, "REFS"),
+            Custom(a) => write!(f, "{}", a),
+        }
+    }
+}
+
+/// How deeply thread-lists may be nested in a `THREAD` response.
+const MAX_THREAD_DEPTH: usize = 100;
+
+/// A message in a thread returned by [`Session::thread`] or [`Session::uid_thread`], together
+/// with the replies to it.
+///
+/// `T` is [`Seq`] or [`Uid`], depending on the command used.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub struct Thread<T> {
+    /// The message, or `None` if the messages in `children` belong to the same thread but the
+    /// message they descend from is not in the mailbox or does not match the search criteria.
+    pub id: Option<T>,
+
+    /// The replies to this message, in the order given by the threading algorithm.
+    pub children: Vec<Thread<T>>,
+}
+
+impl<T: Copy> Thread<T> {
+    /// Iterate over the messages in this thread, depth-first, starting with this one.
+    pub fn ids(&self) -> impl Iterator<Item = T> + '_ {
+        let mut stack = vec![self];
+        std::iter::from_fn(move || {
+            while let Some(thread) = stack.pop() {
+                stack.extend(thread.children.iter().rev());
+                if thread.id.is_some() {
+                    return thread.id;
+                }
+            }
+            None
+        })
+    }
+}
+
+// thread-list = "(" (thread-members / thread-nested) ")"
+// thread-members = nz-number *(SP nz-number) [SP thread-nested]
+// thread-nested = 2*thread-list
+//
+// Some servers send a single nested thread-list as well, so that is accepted too.
+//
+// `depth` is the number of thread-lists this one is nested in. Responses nested deeper than
+// `MAX_THREAD_DEPTH` are rejected rather than risking a stack overflow.
+fn thread_list(i: &[u8], depth: usize) -> IResult<&[u8], Thread<u32>> {
+    let (i, _) = char('(')(i)?;
+    if depth >= MAX_THREAD_DEPTH {
+        return Err(nom::Err::Failure(nom::error::Error::new(
+            i,
+            nom::error::ErrorKind::TooLarge,
+        )));
+    }
+    let (i, members) = many0(terminated(number, space0))(i)?;
+    let (i, children) = many0(|i| thread_list(i, depth + 1))(i)?;
+    let (i, _) = char(')')(i)?;
+
+    // A list of members is a chain in which each message is the only child of the previous one.
+    let mut members = members.into_iter().rev();
+    let thread = match members.next() {
+        Some(last) => members.fold(
+            Thread {
+                id: Some(last),
+                children,
+            },
+            |thread, id| Thread {
+                id: Some(id),
+                children: vec![thread],
+            },
+        ),
+        None if children.is_empty() => {
+            return Err(nom::Err::Error(nom::error::Error::new(
+                i,
+                nom::error::ErrorKind::Many1,
+            )))
+        }
+        None => Thread { id: None, children },
+    };
+    Ok((i, thread))
+}
+
+// thread-data = "THREAD" [SP 1*thread-list]
+fn thread_response(i: &[u8]) -> IResult<&[u8], Vec<Thread<u32>>> {
+    let (i, _) = tag_no_case("* THREAD")(i)?;
+    let (i, threads) = many0(preceded(space0, many1(|i| thread_list(i, 0))))(i)?;
+    let (i, _) = space0(i)?;
+    let (i, _) = tag_no_case("\r\n")(i)?;
+    Ok((i, threads.into_iter().flatten().collect()))
+}
+
+/// Parse the `THREAD` responses in a response buffer.
+pub(crate) fn parse_threads(
+    lines: &[u8],
+    unsolicited: &mut VecDeque<UnsolicitedResponse>,
+) -> Result<Vec<Thread<u32>>> {
+    let mut threads = Vec::new();
+    parse_many_into(
+        lines,
+        &mut threads,
+        unsolicited,
+        map(thread_response, MapOrNot::MapVec),
+        |response| Ok(MapOrNot::Not(response)),
+    )?;
+    Ok(threads)
+}
+
+impl<T: Read + Write> Session<T> {
+    /// This issues the [THREAD command](https://tools.ietf.org/html/rfc5256#section-4), which
+    /// returns the messages matching the search criteria grouped into threads.
+    ///
+    /// This command is like [`Session::search`], except that the results are arranged in a tree
+    /// of [`Thread`]s according to the given `algorithm`. `charset` applies to the strings in
+    /// `query`, as for [`Session::sort`].
+    ///
+    /// This command requires that the server advertises the `THREAD=<algorithm>` capability for
+    /// the algorithm used (see [`Session::capabilities`]).
+    pub fn thread(
+        &mut self,
+        algorithm: ThreadAlgorithm<'_>,
+        charset: SortCharset<'_>,
+        query: impl AsRef<str>,
+    ) -> Result<Vec<Thread<Seq>>> {
+        self.run_thread("THREAD", algorithm, charset, query.as_ref())
+    }
+
+    /// Equivalent to [`Session::thread`], except that it returns [`Uid`]s.
+    ///
+    /// See also [`Session::uid_search`].
+    pub fn uid_thread(
+        &mut self,
+        algorithm: ThreadAlgorithm<'_>,
+        charset: SortCharset<'_>,
+        query: impl AsRef<str>,
+    ) -> Result<Vec<Thread<Uid>>> {
+        self.run_thread("UID THREAD", algorithm, charset, query.as_ref())
+    }
+
+    fn run_thread(
+        &mut self,
+        command: &str,
+        algorithm: ThreadAlgorithm<'_>,
+        charset: SortCharset<'_>,
+        query: &str,
+    ) -> Result<Vec<Thread<u32>>> {
+        let mut command = Command::new(format!("{} {} {} ", command, algorithm, charset));
+        command.push_criteria(query);
+        self.execute_and_read_response(&command)
+            .and_then(|lines| parse_threads(&lines, &mut self.unsolicited_responses))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::{Error, ParseError};
+    use crate::mock_stream::MockStream;
+    use crate::Client;
+
+    fn leaf(id: u32) -> Thread<u32> {
+        Thread {
+            id: Some(id),
+            children: Vec::new(),
+        }
+    }
+
+    fn node(id: Option<u32>, children: Vec<Thread<u32>>) -> Thread<u32> {
+        Thread { id, children }
+    }
+
+    #[test]
+    fn parse_thread_test() {
+        // Example from RFC 5256, section 4.
+        let lines = b"* THREAD (2)(3 6 (4 23)(44 7 96))\r\n";
+        let mut queue = VecDeque::new();
+        let threads = parse_threads(lines, &mut queue).unwrap();
+        assert_eq!(queue.pop_front(), None);
+        assert_eq!(
+            threads,
+            vec![
+                leaf(2),
+                node(
+                    Some(3),
+                    vec![node(
+                        Some(6),
+                        vec![
+                            node(Some(4), vec![leaf(23)]),
+                            node(Some(44), vec![node(Some(7), vec![leaf(96)])]),
+                        ]
+                    )]
+                ),
+            ]
+        );
+        assert_eq!(
+            threads[1].ids().collect::<Vec<_>>(),
+            vec![3, 6, 4, 23, 44, 7, 96]
+        );
+    }
+
+    #[test]
+    fn parse_thread_missing_parent() {
+        // Example from RFC 5256, section 4: 3 and 5 are siblings whose parent is missing.
+        let lines = b"* THREAD ((3)(5))\r\n* 4 EXISTS\r\n";
+        let mut queue = VecDeque::new();
+        let threads = parse_threads(lines, &mut queue).unwrap();
+        assert_eq!(threads, vec![node(None, vec![leaf(3), leaf(5)])]);
+        assert_eq!(threads[0].ids().collect::<Vec<_>>(), vec![3, 5]);
+        assert_eq!(queue.pop_front(), Some(UnsolicitedResponse::Exists(4)));
+    }
+
+    #[test]
+    fn parse_thread_empty() {
+        let mut queue = VecDeque::new();
+        assert!(parse_threads(b"* THREAD\r\n", &mut queue)
+            .unwrap()
+            .is_empty());
+        assert!(parse_threads(b"* THREAD \r\n", &mut queue)
+            .unwrap()
+            .is_empty());
+        assert!(parse_threads(b"* THREAD ()\r\n", &mut queue).is_err());
+    }
+
+    #[test]
+    fn parse_thread_too_deep() {
+        let nested = |depth| {
+            let mut line = b"* THREAD ".to_vec();
+            line.extend(vec![b'('; depth]);
+            line.push(b'1');
+            line.extend(vec![b')'; depth]);
+            line.extend(b"\r\n");
+            line
+        };
+        let mut queue = VecDeque::new();
+        let threads = parse_threads(&nested(MAX_THREAD_DEPTH), &mut queue).unwrap();
+        assert_eq!(threads[0].ids().collect::<Vec<_>>(), vec![1]);
+        assert!(matches!(
+            parse_threads(&nested(MAX_THREAD_DEPTH + 1), &mut queue),
+            Err(Error::Parse(ParseError::Invalid(_)))
+        ));
+        assert!(matches!(
+            parse_threads(&nested(100_000), &mut queue),
+            Err(Error::Parse(ParseError::Invalid(_)))
+        ));
+    }
+
+    #[test]
+    fn uid_thread() {
+        let response = b"a1 OK Logged in\r\n\
+            * THREAD (166)(167)(168)(169)(172)(170)(171)(173)(174 (175)(176)(178)(181)(180))\r\n\
+            a2 OK Thread completed\r\n"
+            .to_vec();
+        let mock_stream = MockStream::new(response);
+        let client = Client::new(mock_stream);
+        let mut session = client.login("username", "password").unwrap();
+        let threads = session
+            .uid_thread(
+                ThreadAlgorithm::References,
+                SortCharset::UsAscii,
+                "SINCE 5-MAR-2000",
+            )
+            .unwrap();
+        assert!(session
+            .stream
+            .get_ref()
+            .written_buf
+            .ends_with(b"a2 UID THREAD REFERENCES US-ASCII SINCE 5-MAR-2000\r\n"));
+        assert_eq!(threads.len(), 9);
+        assert_eq!(
+            threads[8],
+            node(
+                Some(174),
+                vec![leaf(175), leaf(176), leaf(178), leaf(181), leaf(180)]
+            )
+        );
+    }
+}
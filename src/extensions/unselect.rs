@@ -0,0 +1,131 @@
+//! Adds support for the IMAP UNSELECT extension specified in [RFC
+//! 3691](https://tools.ietf.org/html/rfc3691).
+//!
+//! [`Session::close`] leaves the selected state, but also permanently removes all messages that
+//! have [`Flag::Deleted`](crate::types::Flag::Deleted) set. [`Session::unselect`] leaves the
+//! selected state without removing anything.
+
+use crate::client::Session;
+use crate::error::{Error, Result};
+use std::io::{Read, Write};
+
+/// The mailbox that is examined in order to deselect the current mailbox on servers that do not
+/// support `UNSELECT`. It is not expected to exist.
+const NONEXISTENT_MAILBOX: &str = "imap-rs-unselect-nonexistent-mailbox";
+
+impl<T: Read + Write> Session<T> {
+    /// The [`UNSELECT` command](https://tools.ietf.org/html/rfc3691#section-2) returns to the
+    /// authenticated state from the selected state, like [`Session::close`] does. Unlike `CLOSE`,
+    /// it does *not* remove messages that have [`Flag::Deleted`](crate::types::Flag::Deleted)
+    /// set from the mailbox.
+    ///
+    /// If the server does not advertise the `UNSELECT` capability, the mailbox is deselected by
+    /// examining a mailbox that does not exist instead: a failed [`Session::examine`] leaves no
+    /// mailbox selected, and never expunges messages. The server's capabilities are requested
+    /// first if they are not known yet.
+    pub fn unselect(&mut self) -> Result<()> {
+        if self.has_capability("UNSELECT")? {
+            self.run_command_and_check_ok("UNSELECT")?;
+            self.clear_selected();
+            return Ok(());
+        }
+
+        match self.examine(NONEXISTENT_MAILBOX) {
+            Err(Error::No(_)) => Ok(()),
+            // The mailbox unexpectedly exists, but it is read-only, so closing it is harmless.
+            Ok(_) => self.close(),
+            Err(e) => Err(e),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock_stream::MockStream;
+    use crate::types::SelectedMailbox;
+    use crate::Client;
+
+    fn select_inbox_session(response: &[u8]) -> Session<MockStream> {
+        let mut full = b"a1 OK Logged in\r\n\
+            * 3 EXISTS\r\n\
+            a2 OK [READ-WRITE] SELECT completed\r\n"
+            .to_vec();
+        full.extend_from_slice(response);
+        let client = Client::new(MockStream::new(full));
+        let mut session = client.login("testuser", "pass").unwrap();
+        session.select("INBOX").unwrap();
+        session
+    }
+
+    #[test]
+    fn unselect() {
+        let mut session = select_inbox_session(
+            b"* CAPABILITY IMAP4rev1 UNSELECT\r\n\
+            a3 OK CAPABILITY completed\r\n\
+            a4 OK UNSELECT completed\r\n\
+            * 1 EXISTS\r\n\
+            a5 OK [READ-WRITE] SELECT completed\r\n\
+            a6 OK UNSELECT completed\r\n",
+        );
+        assert_eq!(
+            session.selected(),
+            Some(&SelectedMailbox {
+                name: "INBOX".to_string(),
+                is_read_only: false,
+            })
+        );
+        session.unselect().unwrap();
+        assert!(session
+            .stream
+            .get_ref()
+            .written_buf
+            .ends_with(b"a3 CAPABILITY\r\na4 UNSELECT\r\n"));
+        assert_eq!(session.selected(), None);
+
+        // The capabilities are only requested once.
+        session.select("INBOX").unwrap();
+        session.unselect().unwrap();
+        assert!(session
+            .stream
+            .get_ref()
+            .written_buf
+            .ends_with(b"a5 SELECT \"INBOX\"\r\na6 UNSELECT\r\n"));
+        assert_eq!(session.selected(), None);
+    }
+
+    #[test]
+    fn unselect_fallback() {
+        let mut session = select_inbox_session(
+            b"* CAPABILITY IMAP4rev1\r\n\
+            a3 OK CAPABILITY completed\r\n\
+            a4 NO Mailbox doesn't exist\r\n",
+        );
+        session.unselect().unwrap();
+        let expected = format!("a4 EXAMINE \"{}\"\r\n", NONEXISTENT_MAILBOX);
+        assert!(session
+            .stream
+            .get_ref()
+            .written_buf
+            .ends_with(expected.as_bytes()));
+        assert_eq!(session.selected(), None);
+    }
+
+    #[test]
+    fn unselect_fallback_mailbox_exists() {
+        let mut session = select_inbox_session(
+            b"* CAPABILITY IMAP4rev1\r\n\
+            a3 OK CAPABILITY completed\r\n\
+            * 0 EXISTS\r\n\
+            a4 OK [READ-ONLY] EXAMINE completed\r\n\
+            a5 OK CLOSE completed\r\n",
+        );
+        session.unselect().unwrap();
+        assert!(session
+            .stream
+            .get_ref()
+            .written_buf
+            .ends_with(b"a5 CLOSE\r\n"));
+        assert_eq!(session.selected(), None);
+    }
+}
@@ -73,17 +73,25 @@
 //! Even without `native_tls`, you can still use TLS by leveraging the pure Rust `rustls`
 //! crate, which is enabled with the `rustls-tls` feature. See the example/rustls.rs file
 //! for a working example.
+//!
+//! The `compress` feature, which is also enabled by default, provides [`Session::compress`] for the
+//! COMPRESS=DEFLATE extension and pulls in the `flate2` crate. It can be left out the same way.
+//! So can the `sasl` feature, which provides the authenticators in [`sasl`] and
+//! [`Client::authenticate_best`], and pulls in the hashing crates they need.
 #![deny(missing_docs)]
 #![warn(rust_2018_idioms)]
 #![cfg_attr(docsrs, feature(doc_cfg))]
 
+mod command;
 mod parse;
 mod utils;
 
 pub mod types;
 
 mod authenticator;
-pub use crate::authenticator::Authenticator;
+pub use crate::authenticator::{Authenticator, AuthenticatorMut};
+#[cfg(feature = "sasl")]
+pub mod sasl;
 
 mod conn;
 pub use conn::{Connection, ImapConnection};
@@ -100,6 +108,8 @@ pub use error::{Error, Result};
 
 pub mod extensions;
 
+pub mod utf7;
+
 #[cfg(feature = "test_helpers")]
 pub mod testing;
 
@@ -1,3 +1,7 @@
+use imap_proto::parser::core::{
+    is_astring_char, is_atom_char, is_quoted_specials, is_text_char, literal, number, number_64,
+    parenthesized_list, quoted,
+};
 use imap_proto::{MailboxDatum, Response, ResponseCode, StatusAttribute};
 use lazy_static::lazy_static;
 use regex::Regex;
@@ -6,7 +10,21 @@ use std::collections::VecDeque;
 use std::convert::TryFrom;
 use std::iter::Extend;
 
+use nom::{
+    branch::alt,
+    bytes::complete::{escaped, tag, tag_no_case, take_while, take_while1},
+    character::complete::{char, digit1, one_of, space0},
+    combinator::{fail, map, map_opt, map_res, opt, recognize, verify},
+    multi::{separated_list0, separated_list1},
+    sequence::{delimited, preceded, tuple},
+    IResult,
+};
+
 use super::error::{Error, ParseError, Result};
+use super::extensions::binary::{binary_attribute, literal8};
+use super::extensions::esearch::esearch_response;
+use super::extensions::list_extended::list_response;
+use super::extensions::objectid::{mailbox_id, mailbox_id_code, objectid_attribute};
 use super::types::*;
 
 lazy_static! {
@@ -34,28 +52,29 @@ pub(crate) enum MapOrNot<'a, T> {
 
 /// Parse many `T` Responses with `F` and extend `into` with them.
 /// Responses other than `T` go into the `unsolicited` channel.
-pub(crate) fn parse_many_into<'input, T, F>(
+///
+/// Every response is first given to `pre_parse`, for the responses that imap-proto does not
+/// parse. Pass [`nom::combinator::fail`] if there are none.
+pub(crate) fn parse_many_into<'input, T, P, F>(
     input: &'input [u8],
     into: &mut impl Extend<T>,
     unsolicited: &mut VecDeque<UnsolicitedResponse>,
+    mut pre_parse: P,
     mut map: F,
 ) -> Result<()>
 where
+    P: FnMut(&'input [u8]) -> IResult<&'input [u8], MapOrNot<'input, T>>,
     F: FnMut(Response<'input>) -> Result<MapOrNot<'input, T>>,
 {
     let mut other = Vec::new();
 
-    parse_many_into2::<_, (), _, _, _>(
+    parse_many_into2::<_, (), _, _, _, _>(
         input,
         into,
         &mut other,
         unsolicited,
-        |response| match map(response)? {
-            MapOrNot::Map(t) => Ok(MapOrNot2::Map1(t)),
-            MapOrNot::MapVec(t) => Ok(MapOrNot2::MapVec1(t)),
-            MapOrNot::Not(t) => Ok(MapOrNot2::Not(t)),
-            MapOrNot::Ignore => Ok(MapOrNot2::Ignore),
-        },
+        |i| pre_parse(i).map(|(rest, t)| (rest, t.into())),
+        |response| map(response).map(MapOrNot2::from),
     )?;
 
     assert_eq!(other.len(), 0);
@@ -73,18 +92,34 @@ pub(crate) enum MapOrNot2<'a, T, U> {
     Ignore,
 }
 
+impl<'a, T, U> From<MapOrNot<'a, T>> for MapOrNot2<'a, T, U> {
+    fn from(t: MapOrNot<'a, T>) -> Self {
+        match t {
+            MapOrNot::Map(t) => MapOrNot2::Map1(t),
+            MapOrNot::MapVec(t) => MapOrNot2::MapVec1(t),
+            MapOrNot::Not(t) => MapOrNot2::Not(t),
+            MapOrNot::Ignore => MapOrNot2::Ignore,
+        }
+    }
+}
+
 /// Parse many `T` or `U` Responses with `F` and extend `into1` or `into2` with them.
 /// Responses other than `T` or `U` go into the `unsolicited` channel.
-pub(crate) fn parse_many_into2<'input, T, U, F, IU, IT>(
+///
+/// Every response is first given to `pre_parse`, for the responses that imap-proto does not
+/// parse. Pass [`nom::combinator::fail`] if there are none.
+pub(crate) fn parse_many_into2<'input, T, U, P, F, IU, IT>(
     input: &'input [u8],
     into1: &mut IT,
     into2: &mut IU,
     unsolicited: &mut VecDeque<UnsolicitedResponse>,
+    mut pre_parse: P,
     mut map: F,
 ) -> Result<()>
 where
     IT: Extend<T>,
     IU: Extend<U>,
+    P: FnMut(&'input [u8]) -> IResult<&'input [u8], MapOrNot2<'input, T, U>>,
     F: FnMut(Response<'input>) -> Result<MapOrNot2<'input, T, U>>,
 {
     let mut lines = input;
@@ -93,26 +128,37 @@ where
             break Ok(());
         }
 
-        match imap_proto::parser::parse_response(lines) {
-            Ok((rest, resp)) => {
+        let mapped = match pre_parse(lines) {
+            Ok((rest, mapped)) => {
                 lines = rest;
-
-                match map(resp)? {
-                    MapOrNot2::Map1(t) => into1.extend(std::iter::once(t)),
-                    MapOrNot2::Map2(t) => into2.extend(std::iter::once(t)),
-                    MapOrNot2::MapVec1(t) => into1.extend(t),
-                    MapOrNot2::MapVec2(t) => into2.extend(t),
-                    MapOrNot2::Not(resp) => match try_handle_unilateral(resp, unsolicited) {
-                        Some(Response::Fetch(..)) => continue,
-                        Some(resp) => break Err(resp.into()),
-                        None => {}
-                    },
-                    MapOrNot2::Ignore => continue,
-                }
-            }
-            _ => {
-                break Err(Error::Parse(ParseError::Invalid(lines.to_vec())));
+                mapped
             }
+            Err(_) => match imap_proto::parser::parse_response(lines) {
+                Ok((rest, resp)) => {
+                    lines = rest;
+                    map(resp)?
+                }
+                _ => match try_handle_unilateral_extended(lines, unsolicited) {
+                    Some(rest) => {
+                        lines = rest;
+                        continue;
+                    }
+                    None => break Err(Error::Parse(ParseError::Invalid(lines.to_vec()))),
+                },
+            },
+        };
+
+        match mapped {
+            MapOrNot2::Map1(t) => into1.extend(std::iter::once(t)),
+            MapOrNot2::Map2(t) => into2.extend(std::iter::once(t)),
+            MapOrNot2::MapVec1(t) => into1.extend(t),
+            MapOrNot2::MapVec2(t) => into2.extend(t),
+            MapOrNot2::Not(resp) => match try_handle_unilateral(resp, unsolicited) {
+                Some(Response::Fetch(..)) => continue,
+                Some(resp) => break Err(resp.into()),
+                None => {}
+            },
+            MapOrNot2::Ignore => continue,
         }
     }
 }
@@ -128,7 +174,7 @@ where
 {
     let mut temp_output = Vec::<T>::new();
 
-    parse_many_into(input, &mut temp_output, unsolicited, map)?;
+    parse_many_into(input, &mut temp_output, unsolicited, fail, map)?;
 
     match temp_output.len() {
         1 => Ok(Some(temp_output.remove(0))),
@@ -256,6 +302,85 @@ pub fn parse_append(
     }
 }
 
+pub fn parse_copy(
+    mut lines: &[u8],
+    unsolicited: &mut VecDeque<UnsolicitedResponse>,
+) -> Result<Copied> {
+    let mut copied = Copied::default();
+
+    while !lines.is_empty() {
+        match imap_proto::parser::parse_response(lines) {
+            // MOVE returns COPYUID in an untagged response, COPY in the tagged one.
+            Ok((
+                rest,
+                Response::Data {
+                    status: imap_proto::Status::Ok,
+                    code: Some(ResponseCode::CopyUid(validity, source, destination)),
+                    ..
+                },
+            ))
+            | Ok((
+                rest,
+                Response::Done {
+                    status: imap_proto::Status::Ok,
+                    code: Some(ResponseCode::CopyUid(validity, source, destination)),
+                    ..
+                },
+            )) => {
+                lines = rest;
+                copied.uid_validity = Some(validity);
+                copied.source_uids = Some(source);
+                copied.destination_uids = Some(destination);
+            }
+            Ok((rest, Response::Done { .. })) => {
+                lines = rest;
+            }
+            Ok((rest, data)) => {
+                lines = rest;
+                if let Some(resp) = try_handle_unilateral(data, unsolicited) {
+                    return Err(resp.into());
+                }
+            }
+            _ => {
+                return Err(Error::Parse(ParseError::Invalid(lines.to_vec())));
+            }
+        }
+    }
+    Ok(copied)
+}
+
+/// Parse the response to a `CREATE` command, returning the `MAILBOXID` of the new mailbox if the
+/// server sent one.
+pub fn parse_create(
+    mut lines: &[u8],
+    unsolicited: &mut VecDeque<UnsolicitedResponse>,
+) -> Result<Option<String>> {
+    let mut id = None;
+
+    while !lines.is_empty() {
+        match imap_proto::parser::parse_response(lines) {
+            Ok((rest, Response::Done { information, .. })) => {
+                lines = rest;
+                id = information
+                    .as_deref()
+                    .and_then(mailbox_id_code)
+                    .map(str::to_string);
+            }
+            Ok((rest, data)) => {
+                lines = rest;
+                if let Some(resp) = try_handle_unilateral(data, unsolicited) {
+                    return Err(resp.into());
+                }
+            }
+            _ => {
+                return Err(Error::Parse(ParseError::Invalid(lines.to_vec())));
+            }
+        }
+    }
+
+    Ok(id)
+}
+
 pub fn parse_noop(lines: Vec<u8>, unsolicited: &mut VecDeque<UnsolicitedResponse>) -> Result<()> {
     let mut lines: &[u8] = &lines;
 
@@ -271,9 +396,10 @@ pub fn parse_noop(lines: Vec<u8>, unsolicited: &mut VecDeque<UnsolicitedResponse
                     break Err(resp.into());
                 }
             }
-            _ => {
-                break Err(Error::Parse(ParseError::Invalid(lines.to_vec())));
-            }
+            _ => match try_handle_unilateral_extended(lines, unsolicited) {
+                Some(rest) => lines = rest,
+                None => break Err(Error::Parse(ParseError::Invalid(lines.to_vec()))),
+            },
         }
     }
 }
@@ -297,7 +423,14 @@ pub fn parse_mailbox(
                     mailbox.is_read_only = true;
                 }
             }
-            Ok((rest, Response::Data { status, code, .. })) => {
+            Ok((
+                rest,
+                Response::Data {
+                    status,
+                    code,
+                    information,
+                },
+            )) => {
                 lines = rest;
 
                 if let imap_proto::Status::Ok = status {
@@ -322,6 +455,11 @@ pub fn parse_mailbox(
                     Some(ResponseCode::PermanentFlags(flags)) => {
                         mailbox.permanent_flags.extend(Flag::from_strs(flags));
                     }
+                    None => {
+                        if let Some(id) = information.as_deref().and_then(mailbox_id_code) {
+                            mailbox.mailbox_id = Some(id.to_string());
+                        }
+                    }
                     _ => {}
                 }
             }
@@ -330,9 +468,9 @@ pub fn parse_mailbox(
 
                 match m {
                     MailboxDatum::Status { mailbox, status } => {
-                        unsolicited.push_back(UnsolicitedResponse::Status {
+                        unsolicited.push_back(UnsolicitedResponse::MailboxStatus {
                             mailbox: mailbox.into(),
-                            attributes: status,
+                            status: status_from_attributes(status),
                         })
                     }
                     MailboxDatum::Exists(e) => {
@@ -351,12 +489,18 @@ pub fn parse_mailbox(
                 lines = rest;
                 unsolicited.push_back(UnsolicitedResponse::Expunge(n))
             }
-            Ok((_, resp)) => {
-                break Err(resp.into());
-            }
-            _ => {
-                break Err(Error::Parse(ParseError::Invalid(lines.to_vec())));
+            Ok((rest, resp)) => {
+                lines = rest;
+                if let Some(resp) = try_handle_unilateral(resp, unsolicited) {
+                    break Err(resp.into());
+                }
             }
+            // IMAP4rev2 servers include a LIST response for the mailbox, which may carry extended
+            // data.
+            _ => match try_handle_unilateral_extended(lines, unsolicited) {
+                Some(rest) => lines = rest,
+                None => break Err(Error::Parse(ParseError::Invalid(lines.to_vec()))),
+            },
         }
 
         if lines.is_empty() {
@@ -365,51 +509,257 @@ pub fn parse_mailbox(
     }
 }
 
-pub fn parse_status(
-    mut lines: &[u8],
-    mailbox_name: &str,
-    unsolicited: &mut VecDeque<UnsolicitedResponse>,
-) -> Result<Mailbox> {
+// quoted = DQUOTE *QUOTED-CHAR DQUOTE
+// QUOTED-CHAR =/ UTF8-2 / UTF8-3 / UTF8-4
+//
+// IMAP4rev2 (RFC 9051, section 9) and UTF8=ACCEPT (RFC 6855, section 3) allow UTF-8 in quoted
+// strings, which imap-proto only accepts in literals.
+fn quoted_utf8(i: &[u8]) -> IResult<&[u8], &str> {
+    map_res(
+        delimited(
+            char('"'),
+            escaped(
+                take_while1(|b: u8| (is_text_char(b) || !b.is_ascii()) && !is_quoted_specials(b)),
+                '\\',
+                one_of("\\\""),
+            ),
+            char('"'),
+        ),
+        std::str::from_utf8,
+    )(i)
+}
+
+// astring = 1*ASTRING-CHAR / string
+pub(crate) fn astring_utf8(i: &[u8]) -> IResult<&[u8], &str> {
+    alt((
+        map_res(take_while1(is_astring_char), std::str::from_utf8),
+        quoted_utf8,
+        map_res(literal, std::str::from_utf8),
+    ))(i)
+}
+
+// The status data items of a mailbox, including those that imap-proto does not know about.
+enum StatusItem<'a> {
+    Messages(u32),
+    Recent(u32),
+    UidNext(u32),
+    UidValidity(u32),
+    Unseen(u32),
+    HighestModSeq(u64),
+    Size(u64),
+    Deleted(u32),
+    DeletedStorage(u64),
+    MailboxId(&'a str),
+    Other,
+}
+
+// status-att-val = "MESSAGES" SP number / ... / "SIZE" SP number64 / "DELETED" SP number /
+//                  "DELETED-STORAGE" SP number64 / "MAILBOXID" SP "(" objectid ")"
+//
+// See RFC 8438, section 4, RFC 9208, section 4.2 and RFC 8474, section 4. Unknown items are
+// skipped.
+fn status_item(i: &[u8]) -> IResult<&[u8], StatusItem<'_>> {
+    alt((
+        map(
+            preceded(tag_no_case("MESSAGES "), number),
+            StatusItem::Messages,
+        ),
+        map(preceded(tag_no_case("RECENT "), number), StatusItem::Recent),
+        map(
+            preceded(tag_no_case("UIDNEXT "), number),
+            StatusItem::UidNext,
+        ),
+        map(
+            preceded(tag_no_case("UIDVALIDITY "), number),
+            StatusItem::UidValidity,
+        ),
+        map(preceded(tag_no_case("UNSEEN "), number), StatusItem::Unseen),
+        map(
+            preceded(tag_no_case("HIGHESTMODSEQ "), number_64),
+            StatusItem::HighestModSeq,
+        ),
+        map(preceded(tag_no_case("SIZE "), number_64), StatusItem::Size),
+        map(
+            preceded(tag_no_case("DELETED "), number),
+            StatusItem::Deleted,
+        ),
+        map(
+            preceded(tag_no_case("DELETED-STORAGE "), number_64),
+            StatusItem::DeletedStorage,
+        ),
+        map(mailbox_id, StatusItem::MailboxId),
+        map(
+            tuple((
+                take_while1(is_atom_char),
+                char(' '),
+                alt((
+                    recognize(number_64),
+                    delimited(char('('), take_while(|c| c != b')'), char(')')),
+                )),
+            )),
+            |_| StatusItem::Other,
+        ),
+    ))(i)
+}
+
+// mailbox-data =/ "STATUS" SP mailbox SP "(" [status-att-list] ")"
+pub(crate) fn status_response(i: &[u8]) -> IResult<&[u8], (&str, Mailbox)> {
+    let (i, (_, name, _, items, _, _)) = tuple((
+        tag_no_case("* STATUS "),
+        astring_utf8,
+        char(' '),
+        parenthesized_list(status_item),
+        space0,
+        tag("\r\n"),
+    ))(i)?;
+    let name = if name.eq_ignore_ascii_case("INBOX") {
+        "INBOX"
+    } else {
+        name
+    };
+
+    Ok((i, (name, mailbox_status(items))))
+}
+
+// The status of a mailbox with the given status data items.
+fn mailbox_status<'a>(items: impl IntoIterator<Item = StatusItem<'a>>) -> Mailbox {
     let mut mailbox = Mailbox::default();
-    let mut got_anything = false;
-    while !lines.is_empty() {
-        match imap_proto::parser::parse_response(lines) {
-            Ok((
-                rest,
-                Response::MailboxData(MailboxDatum::Status {
-                    mailbox: their_mailbox_name,
-                    status,
-                }),
-            )) if their_mailbox_name == mailbox_name => {
-                lines = rest;
-                got_anything = true;
-                for attr in status {
-                    match attr {
-                        StatusAttribute::HighestModSeq(v) => mailbox.highest_mod_seq = Some(v),
-                        StatusAttribute::Messages(v) => mailbox.exists = v,
-                        StatusAttribute::Recent(v) => mailbox.recent = v,
-                        StatusAttribute::UidNext(v) => mailbox.uid_next = Some(v),
-                        StatusAttribute::UidValidity(v) => mailbox.uid_validity = Some(v),
-                        StatusAttribute::Unseen(v) => mailbox.unseen = Some(v),
-                        _ => {} // needed because StatusAttribute is #[non_exhaustive]
-                    }
-                }
+    for item in items {
+        match item {
+            StatusItem::Messages(v) => mailbox.exists = v,
+            StatusItem::Recent(v) => mailbox.recent = v,
+            StatusItem::UidNext(v) => mailbox.uid_next = Some(v),
+            StatusItem::UidValidity(v) => mailbox.uid_validity = Some(v),
+            StatusItem::Unseen(v) => mailbox.unseen = Some(v),
+            StatusItem::HighestModSeq(v) => mailbox.highest_mod_seq = Some(v),
+            StatusItem::Size(v) => mailbox.size = Some(v),
+            StatusItem::Deleted(v) => mailbox.deleted = Some(v),
+            StatusItem::DeletedStorage(v) => mailbox.deleted_storage = Some(v),
+            StatusItem::MailboxId(v) => mailbox.mailbox_id = Some(v.to_string()),
+            StatusItem::Other => {}
+        }
+    }
+    mailbox
+}
+
+/// The status of a mailbox with the status data items of a `STATUS` response that imap-proto
+/// parsed.
+pub(crate) fn status_from_attributes(attributes: Vec<StatusAttribute>) -> Mailbox {
+    mailbox_status(attributes.into_iter().map(|attr| match attr {
+        StatusAttribute::Messages(v) => StatusItem::Messages(v),
+        StatusAttribute::Recent(v) => StatusItem::Recent(v),
+        StatusAttribute::UidNext(v) => StatusItem::UidNext(v),
+        StatusAttribute::UidValidity(v) => StatusItem::UidValidity(v),
+        StatusAttribute::Unseen(v) => StatusItem::Unseen(v),
+        StatusAttribute::HighestModSeq(v) => StatusItem::HighestModSeq(v),
+        _ => StatusItem::Other, // needed because StatusAttribute is #[non_exhaustive]
+    }))
+}
+
+// Any value of a fetch item, which is only recognized to be handed on to imap-proto.
+fn value(i: &[u8]) -> IResult<&[u8], &[u8]> {
+    recognize(alt((
+        map(
+            delimited(char('('), separated_list0(char(' '), value), char(')')),
+            |_| (),
+        ),
+        map(literal8, |_| ()),
+        map(literal, |_| ()),
+        map(quoted, |_| ()),
+        map(
+            take_while1(|c| !matches!(c, b' ' | b'(' | b')' | b'"' | b'{' | b'\r' | b'\n')),
+            |_| (),
+        ),
+    )))(i)
+}
+
+// Any other fetch item, such as `UID 4` or `BODY[HEADER.FIELDS (DATE)]<0> {12}\r\n...`.
+fn other_attribute(i: &[u8]) -> IResult<&[u8], &[u8]> {
+    recognize(tuple((
+        take_while1(|c| is_atom_char(c) && c != b'['),
+        opt(delimited(char('['), take_while(|c| c != b']'), char(']'))),
+        opt(delimited(char('<'), digit1, char('>'))),
+        char(' '),
+        value,
+    )))(i)
+}
+
+enum FetchAttribute<'a> {
+    Extension(ExtensionAttribute<'a>),
+    Other(&'a [u8]),
+}
+
+// "*" SP nz-number SP "FETCH" SP "(" msg-att-item *(SP msg-att-item) ")" CRLF
+fn fetch_response(i: &[u8]) -> IResult<&[u8], (u32, Vec<FetchAttribute<'_>>)> {
+    let (i, (_, message, _)) = tuple((tag("* "), number, tag_no_case(" FETCH (")))(i)?;
+    let (i, attributes) = separated_list1(
+        char(' '),
+        alt((
+            map(
+                alt((binary_attribute, objectid_attribute)),
+                FetchAttribute::Extension,
+            ),
+            map(other_attribute, FetchAttribute::Other),
+        )),
+    )(i)?;
+    let (i, _) = tag(")\r\n")(i)?;
+    Ok((i, (message, attributes)))
+}
+
+/// A `FETCH` response that contains fetch items of extensions that imap-proto does not parse.
+///
+/// The other items are put back together into a `FETCH` response of their own for imap-proto to
+/// parse, so they are owned by the returned [`Fetch`].
+pub(crate) fn fetch_extensions_response(i: &[u8]) -> IResult<&[u8], Fetch<'_>> {
+    map_opt(fetch_response, |(message, attributes)| {
+        let mut extensions = Vec::new();
+        let mut others = Vec::new();
+        for attribute in attributes {
+            match attribute {
+                FetchAttribute::Extension(attr) => extensions.push(attr),
+                FetchAttribute::Other(other) => others.push(other),
             }
-            Ok((rest, data)) => {
-                lines = rest;
-                if let Some(resp) = try_handle_unilateral(data, unsolicited) {
-                    return Err(resp.into());
+        }
+        if extensions.is_empty() {
+            return None;
+        }
+
+        let fetch = if others.is_empty() {
+            Vec::new()
+        } else {
+            let mut response = format!("* {} FETCH (", message).into_bytes();
+            response.extend(others.join(&b' '));
+            response.extend(b")\r\n");
+            match imap_proto::parser::parse_response(&response) {
+                Ok(([], Response::Fetch(_, attrs))) => {
+                    attrs.into_iter().map(|attr| attr.into_owned()).collect()
                 }
+                _ => return None,
             }
-            _ => {
-                return Err(Error::Parse(ParseError::Invalid(lines.to_vec())));
-            }
-        }
-    }
-    if !got_anything {
-        return Err(Error::MissingStatusResponse);
-    }
-    Ok(mailbox)
+        };
+        Some(Fetch::new(message, fetch, extensions))
+    })(i)
+}
+
+pub fn parse_status(
+    lines: &[u8],
+    mailbox_name: &str,
+    unsolicited: &mut VecDeque<UnsolicitedResponse>,
+) -> Result<Mailbox> {
+    let mut mailbox = Vec::new();
+    parse_many_into(
+        lines,
+        &mut mailbox,
+        unsolicited,
+        map(
+            verify(status_response, |(their_mailbox_name, _)| {
+                *their_mailbox_name == mailbox_name
+            }),
+            |(_, status)| MapOrNot::Map(status),
+        ),
+        |response| Ok(MapOrNot::Not(response)),
+    )?;
+    mailbox.pop().ok_or(Error::MissingStatusResponse)
 }
 
 fn parse_ids_with<T: Extend<u32>>(
@@ -417,32 +767,21 @@ fn parse_ids_with<T: Extend<u32>>(
     unsolicited: &mut VecDeque<UnsolicitedResponse>,
     mut collection: T,
 ) -> Result<T> {
-    let mut lines = lines;
-    loop {
-        if lines.is_empty() {
-            break Ok(collection);
-        }
-
-        match imap_proto::parser::parse_response(lines) {
-            Ok((rest, Response::MailboxData(MailboxDatum::Search(c)))) => {
-                lines = rest;
-                collection.extend(c);
-            }
-            Ok((rest, Response::MailboxData(MailboxDatum::Sort(c)))) => {
-                lines = rest;
-                collection.extend(c);
-            }
-            Ok((rest, data)) => {
-                lines = rest;
-                if let Some(resp) = try_handle_unilateral(data, unsolicited) {
-                    break Err(resp.into());
-                }
-            }
-            _ => {
-                break Err(Error::Parse(ParseError::Invalid(lines.to_vec())));
-            }
-        }
-    }
+    parse_many_into(
+        lines,
+        &mut collection,
+        unsolicited,
+        // IMAP4rev2 servers answer with an ESEARCH response.
+        map(esearch_response, |result| {
+            MapOrNot::MapVec(result.all.map_or_else(Vec::new, |all| all.iter().collect()))
+        }),
+        |response| match response {
+            Response::MailboxData(MailboxDatum::Search(c)) => Ok(MapOrNot::MapVec(c)),
+            Response::MailboxData(MailboxDatum::Sort(c)) => Ok(MapOrNot::MapVec(c)),
+            resp => Ok(MapOrNot::Not(resp)),
+        },
+    )?;
+    Ok(collection)
 }
 
 pub fn parse_id_set(
@@ -461,17 +800,50 @@ pub fn parse_id_seq(
 
 /// Parse a single unsolicited response from IDLE responses.
 pub fn parse_idle(lines: &[u8]) -> (&[u8], Option<Result<UnsolicitedResponse>>) {
+    if let Ok((rest, name)) = list_response(lines) {
+        return (rest, Some(Ok(UnsolicitedResponse::List(name.into_owned()))));
+    }
     match imap_proto::parser::parse_response(lines) {
         Ok((rest, response)) => match UnsolicitedResponse::try_from(response) {
             Ok(unsolicited) => (rest, Some(Ok(unsolicited))),
             Err(res) => (rest, Some(Err(res.into()))),
         },
         Err(nom::Err::Incomplete(_)) => (lines, None),
-        Err(_) => (
-            lines,
-            Some(Err(Error::Parse(ParseError::Invalid(lines.to_vec())))),
-        ),
+        Err(_) => match status_response(lines) {
+            Ok((rest, (mailbox, status))) => (
+                rest,
+                Some(Ok(UnsolicitedResponse::MailboxStatus {
+                    mailbox: mailbox.to_string(),
+                    status,
+                })),
+            ),
+            Err(_) => (
+                lines,
+                Some(Err(Error::Parse(ParseError::Invalid(lines.to_vec())))),
+            ),
+        },
+    }
+}
+
+// imap-proto does not parse LIST responses with extended data, such as the OLDNAME that servers
+// with the NOTIFY extension send when a mailbox is renamed, nor STATUS responses with status data
+// items it does not know about, such as SIZE or MAILBOXID, so those are handled separately.
+//
+// Returns the remaining input if the response was handled.
+fn try_handle_unilateral_extended<'a>(
+    lines: &'a [u8],
+    unsolicited: &mut VecDeque<UnsolicitedResponse>,
+) -> Option<&'a [u8]> {
+    if let Ok((rest, name)) = list_response(lines) {
+        unsolicited.push_back(UnsolicitedResponse::List(name.into_owned()));
+        return Some(rest);
     }
+    let (rest, (mailbox, status)) = status_response(lines).ok()?;
+    unsolicited.push_back(UnsolicitedResponse::MailboxStatus {
+        mailbox: mailbox.to_string(),
+        status,
+    });
+    Some(rest)
 }
 
 // Check if this is simply a unilateral server response (see Section 7 of RFC 3501).
@@ -515,6 +887,19 @@ mod tests {
         }
     }
 
+    #[test]
+    fn parse_capability_imap4rev2_test() {
+        let lines = b"* CAPABILITY IMAP4rev2 auth=PLAIN LITERAL-\r\n";
+        let mut queue = VecDeque::new();
+        let capabilities = Capabilities::parse(lines.to_vec(), &mut queue).unwrap();
+        assert_eq!(queue.pop_front(), None);
+        assert_eq!(capabilities.len(), 3);
+        assert!(capabilities.has_str("IMAP4rev2"));
+        assert!(!capabilities.has_str("IMAP4rev1"));
+        assert!(capabilities.has_str("AUTH=PLAIN"));
+        assert!(capabilities.has_str("LITERAL-"));
+    }
+
     #[test]
     fn parse_capability_case_insensitive_test() {
         // Test that "IMAP4REV1" (instead of "IMAP4rev1") is accepted
@@ -670,14 +1055,15 @@ mod tests {
 
         assert_eq!(
             queue.pop_front(),
-            Some(UnsolicitedResponse::Status {
+            Some(UnsolicitedResponse::MailboxStatus {
                 mailbox: "dev.github".to_string(),
-                attributes: vec![
-                    StatusAttribute::Messages(10),
-                    StatusAttribute::UidNext(11),
-                    StatusAttribute::UidValidity(1408806928),
-                    StatusAttribute::Unseen(0)
-                ]
+                status: Mailbox {
+                    exists: 10,
+                    uid_next: Some(11),
+                    uid_validity: Some(1408806928),
+                    unseen: Some(0),
+                    ..Default::default()
+                }
             })
         );
         assert_eq!(queue.pop_front(), Some(UnsolicitedResponse::Exists(4)));
@@ -697,14 +1083,15 @@ mod tests {
         assert_eq!(queue.pop_front().unwrap(), UnsolicitedResponse::Recent(1));
         assert_eq!(
             queue.pop_front().unwrap(),
-            UnsolicitedResponse::Status {
+            UnsolicitedResponse::MailboxStatus {
                 mailbox: "INBOX".to_string(),
-                attributes: vec![
-                    StatusAttribute::Messages(10),
-                    StatusAttribute::UidNext(11),
-                    StatusAttribute::UidValidity(1408806928),
-                    StatusAttribute::Unseen(0)
-                ]
+                status: Mailbox {
+                    exists: 10,
+                    uid_next: Some(11),
+                    uid_validity: Some(1408806928),
+                    unseen: Some(0),
+                    ..Default::default()
+                }
             }
         );
     }
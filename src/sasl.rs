@@ -0,0 +1,903 @@
+//! Ready-made authenticators for common [SASL](https://tools.ietf.org/html/rfc4422)
+//! mechanisms, for use with [`Client::authenticate`](crate::Client::authenticate).
+//!
+//! Servers list the mechanisms they support as `AUTH=<mechanism>` capabilities.
+//! [`Client::authenticate_best`](crate::Client::authenticate_best) picks the strongest of those
+//! that can be used with the given [`Credentials`]:
+//!
+//! ```no_run
+//! # {} #[cfg(feature = "native-tls")]
+//! # fn main() {
+//! use imap::sasl::Credentials;
+//!
+//! let client = imap::ClientBuilder::new("imap.example.com", 993).connect().unwrap();
+//! let credentials = Credentials::Password {
+//!     username: "me@example.com",
+//!     password: "password",
+//! };
+//! match client.authenticate_best(credentials) {
+//!     Ok(session) => {
+//!         // you are successfully authenticated!
+//!     }
+//!     Err((e, orig_client)) => {
+//!         eprintln!("error authenticating: {}", e);
+//!     }
+//! }
+//! # }
+//! ```
+//!
+//! `PLAIN`, `XOAUTH2`, `OAUTHBEARER` and SCRAM are client-first mechanisms, so their first
+//! response is sent along with the `AUTHENTICATE` command if the server supports `SASL-IR`.
+//!
+//! Passwords are used as they are, without the [SASLprep](https://tools.ietf.org/html/rfc4013)
+//! normalization that some servers apply to non-ASCII passwords.
+
+use crate::authenticator::{Authenticator, AuthenticatorMut};
+use crate::error::{AuthenticationError, Error};
+use crate::types::Capabilities;
+use base64::{engine::general_purpose, Engine as _};
+use hmac::digest::KeyInit;
+use hmac::{Hmac, Mac};
+use md5::Md5;
+use sha1::{Digest, Sha1};
+use sha2::Sha256;
+use std::fmt::Write;
+
+/// The credentials [`Client::authenticate_best`](crate::Client::authenticate_best) authenticates
+/// with.
+#[derive(Clone, Copy)]
+#[non_exhaustive]
+pub enum Credentials<'a> {
+    /// A user name and password, for the `SCRAM-SHA-256`, `SCRAM-SHA-1`, `CRAM-MD5`, `PLAIN` and
+    /// `LOGIN` mechanisms, in that order of preference.
+    Password {
+        /// The user name.
+        username: &'a str,
+        /// The password.
+        password: &'a str,
+    },
+
+    /// A user name and OAuth 2.0 access token, for the `OAUTHBEARER` and `XOAUTH2` mechanisms,
+    /// in that order of preference.
+    OAuth2 {
+        /// The user name.
+        username: &'a str,
+        /// The access token.
+        access_token: &'a str,
+    },
+}
+
+/// The authenticator [`Credentials::best_authenticator`] picked.
+pub(crate) enum BestAuthenticator {
+    Scram(Scram),
+    CramMd5(CramMd5),
+    Plain(Plain),
+    Login(Login),
+    OAuthBearer(OAuthBearer),
+    XOAuth2(XOAuth2),
+}
+
+impl Credentials<'_> {
+    /// Pick the strongest mechanism that the server advertises and that can be used with these
+    /// credentials.
+    pub(crate) fn best_authenticator(
+        &self,
+        capabilities: &Capabilities,
+    ) -> Option<BestAuthenticator> {
+        let has = |mechanism: &str| capabilities.has_str(format!("AUTH={}", mechanism));
+        match *self {
+            Credentials::Password { username, password } => {
+                if has(SCRAM_SHA_256) {
+                    Some(BestAuthenticator::Scram(Scram::sha256(username, password)))
+                } else if has(SCRAM_SHA_1) {
+                    Some(BestAuthenticator::Scram(Scram::sha1(username, password)))
+                } else if has(CRAM_MD5) {
+                    Some(BestAuthenticator::CramMd5(CramMd5::new(username, password)))
+                } else if has(PLAIN) {
+                    Some(BestAuthenticator::Plain(Plain::new(username, password)))
+                } else if has(LOGIN) {
+                    Some(BestAuthenticator::Login(Login::new(username, password)))
+                } else {
+                    None
+                }
+            }
+            Credentials::OAuth2 {
+                username,
+                access_token,
+            } => {
+                if has(OAUTHBEARER) {
+                    Some(BestAuthenticator::OAuthBearer(OAuthBearer::new(
+                        username,
+                        access_token,
+                    )))
+                } else if has(XOAUTH2) {
+                    Some(BestAuthenticator::XOAuth2(XOAuth2::new(
+                        username,
+                        access_token,
+                    )))
+                } else {
+                    None
+                }
+            }
+        }
+    }
+}
+
+const PLAIN: &str = "PLAIN";
+const LOGIN: &str = "LOGIN";
+const XOAUTH2: &str = "XOAUTH2";
+const OAUTHBEARER: &str = "OAUTHBEARER";
+const CRAM_MD5: &str = "CRAM-MD5";
+const SCRAM_SHA_1: &str = "SCRAM-SHA-1";
+const SCRAM_SHA_256: &str = "SCRAM-SHA-256";
+
+// The highest SCRAM iteration count that is accepted. Servers commonly use 4096 and RFC 7677
+// recommends at least that, but a hostile server could otherwise keep the client busy hashing.
+const SCRAM_MAX_ITERATIONS: u32 = 100_000;
+
+/// Escapes a user name for the GS2 header of `OAUTHBEARER` and the `n=` attribute of SCRAM, see
+/// [RFC 5801, section 4](https://tools.ietf.org/html/rfc5801#section-4).
+fn saslname(name: &str) -> String {
+    name.replace('=', "=3D").replace(',', "=2C")
+}
+
+/// The [`PLAIN` mechanism](https://tools.ietf.org/html/rfc4616), which sends the password in the
+/// clear. Only use it on encrypted connections.
+#[derive(Clone)]
+pub struct Plain {
+    username: String,
+    password: String,
+}
+
+impl Plain {
+    /// Authenticate as `username` with `password`.
+    pub fn new(username: impl Into<String>, password: impl Into<String>) -> Self {
+        Plain {
+            username: username.into(),
+            password: password.into(),
+        }
+    }
+
+    /// The name of this mechanism, to pass to
+    /// [`Client::authenticate`](crate::Client::authenticate).
+    pub fn mechanism(&self) -> &'static str {
+        PLAIN
+    }
+}
+
+impl Authenticator for Plain {
+    type Response = Vec<u8>;
+
+    fn process(&self, _: &[u8]) -> Self::Response {
+        format!("\0{}\0{}", self.username, self.password).into_bytes()
+    }
+
+    fn client_first(&self) -> bool {
+        true
+    }
+}
+
+/// The obsolete [`LOGIN`
+/// mechanism](https://tools.ietf.org/html/draft-murchison-sasl-login-00), which sends the user
+/// name and then the password in the clear. Only use it on encrypted connections, with servers
+/// that do not support [`Plain`].
+pub struct Login {
+    username: String,
+    password: String,
+    sent_username: bool,
+}
+
+impl Login {
+    /// Authenticate as `username` with `password`.
+    pub fn new(username: impl Into<String>, password: impl Into<String>) -> Self {
+        Login {
+            username: username.into(),
+            password: password.into(),
+            sent_username: false,
+        }
+    }
+
+    /// The name of this mechanism, to pass to
+    /// [`Client::authenticate`](crate::Client::authenticate).
+    pub fn mechanism(&self) -> &'static str {
+        LOGIN
+    }
+}
+
+impl AuthenticatorMut for Login {
+    type Response = Vec<u8>;
+
+    fn process(&mut self, _: &[u8]) -> Result<Self::Response, AuthenticationError> {
+        if self.sent_username {
+            Ok(self.password.clone().into_bytes())
+        } else {
+            self.sent_username = true;
+            Ok(self.username.clone().into_bytes())
+        }
+    }
+}
+
+/// The [`XOAUTH2` mechanism](https://developers.google.com/gmail/imap/xoauth2-protocol) used by
+/// Gmail and Outlook.com, which authenticates with an OAuth 2.0 access token.
+///
+/// If the server rejects the token, authenticating fails with
+/// [`AuthenticationError::ServerError`] holding the JSON error sent by the server.
+pub struct XOAuth2 {
+    username: String,
+    access_token: String,
+    sent_token: bool,
+    error: Option<String>,
+}
+
+impl XOAuth2 {
+    /// Authenticate as `username` with `access_token`.
+    pub fn new(username: impl Into<String>, access_token: impl Into<String>) -> Self {
+        XOAuth2 {
+            username: username.into(),
+            access_token: access_token.into(),
+            sent_token: false,
+            error: None,
+        }
+    }
+
+    /// The name of this mechanism, to pass to
+    /// [`Client::authenticate`](crate::Client::authenticate).
+    pub fn mechanism(&self) -> &'static str {
+        XOAUTH2
+    }
+}
+
+impl AuthenticatorMut for XOAuth2 {
+    type Response = Vec<u8>;
+
+    fn process(&mut self, challenge: &[u8]) -> Result<Self::Response, AuthenticationError> {
+        if self.sent_token {
+            // The server rejected the token, and sent the details as a challenge that has to be
+            // answered with an empty response.
+            self.error = Some(String::from_utf8_lossy(challenge).into_owned());
+            Ok(Vec::new())
+        } else {
+            self.sent_token = true;
+            Ok(format!(
+                "user={}\x01auth=Bearer {}\x01\x01",
+                self.username, self.access_token
+            )
+            .into_bytes())
+        }
+    }
+
+    fn client_first(&self) -> bool {
+        true
+    }
+
+    fn finish(&mut self, outcome: Result<(), &Error>) -> Result<(), AuthenticationError> {
+        match (outcome, self.error.take()) {
+            (Err(_), Some(error)) => Err(AuthenticationError::ServerError(error)),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// The [`OAUTHBEARER` mechanism](https://tools.ietf.org/html/rfc7628), which authenticates with
+/// an OAuth 2.0 access token.
+///
+/// If the server rejects the token, authenticating fails with
+/// [`AuthenticationError::ServerError`] holding the JSON error sent by the server.
+pub struct OAuthBearer {
+    username: String,
+    access_token: String,
+    sent_token: bool,
+    error: Option<String>,
+}
+
+impl OAuthBearer {
+    /// Authenticate as `username` with `access_token`.
+    pub fn new(username: impl Into<String>, access_token: impl Into<String>) -> Self {
+        OAuthBearer {
+            username: username.into(),
+            access_token: access_token.into(),
+            sent_token: false,
+            error: None,
+        }
+    }
+
+    /// The name of this mechanism, to pass to
+    /// [`Client::authenticate`](crate::Client::authenticate).
+    pub fn mechanism(&self) -> &'static str {
+        OAUTHBEARER
+    }
+}
+
+impl AuthenticatorMut for OAuthBearer {
+    type Response = Vec<u8>;
+
+    fn process(&mut self, challenge: &[u8]) -> Result<Self::Response, AuthenticationError> {
+        if self.sent_token {
+            // The server rejected the token, and sent the details as a challenge that has to be
+            // answered with a single ^A, see RFC 7628, section 3.2.3.
+            self.error = Some(String::from_utf8_lossy(challenge).into_owned());
+            Ok(b"\x01".to_vec())
+        } else {
+            self.sent_token = true;
+            Ok(format!(
+                "n,a={},\x01auth=Bearer {}\x01\x01",
+                saslname(&self.username),
+                self.access_token
+            )
+            .into_bytes())
+        }
+    }
+
+    fn client_first(&self) -> bool {
+        true
+    }
+
+    fn finish(&mut self, outcome: Result<(), &Error>) -> Result<(), AuthenticationError> {
+        match (outcome, self.error.take()) {
+            (Err(_), Some(error)) => Err(AuthenticationError::ServerError(error)),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// The [`CRAM-MD5` mechanism](https://tools.ietf.org/html/rfc2195), which proves knowledge of
+/// the password without sending it.
+#[derive(Clone)]
+pub struct CramMd5 {
+    username: String,
+    password: String,
+}
+
+impl CramMd5 {
+    /// Authenticate as `username` with `password`.
+    pub fn new(username: impl Into<String>, password: impl Into<String>) -> Self {
+        CramMd5 {
+            username: username.into(),
+            password: password.into(),
+        }
+    }
+
+    /// The name of this mechanism, to pass to
+    /// [`Client::authenticate`](crate::Client::authenticate).
+    pub fn mechanism(&self) -> &'static str {
+        CRAM_MD5
+    }
+}
+
+impl Authenticator for CramMd5 {
+    type Response = Vec<u8>;
+
+    fn process(&self, challenge: &[u8]) -> Self::Response {
+        let digest = hmac::<Hmac<Md5>>(self.password.as_bytes(), challenge);
+        let mut response = format!("{} ", self.username);
+        for b in digest {
+            let _ = write!(response, "{:02x}", b);
+        }
+        response.into_bytes()
+    }
+}
+
+fn hmac<M: Mac + KeyInit>(key: &[u8], data: &[u8]) -> Vec<u8> {
+    let mut mac = <M as Mac>::new_from_slice(key).expect("HMAC takes keys of any length");
+    mac.update(data);
+    mac.finalize().into_bytes().to_vec()
+}
+
+#[derive(Clone, Copy)]
+enum ScramHash {
+    Sha1,
+    Sha256,
+}
+
+impl ScramHash {
+    fn hmac(self, key: &[u8], data: &[u8]) -> Vec<u8> {
+        match self {
+            ScramHash::Sha1 => hmac::<Hmac<Sha1>>(key, data),
+            ScramHash::Sha256 => hmac::<Hmac<Sha256>>(key, data),
+        }
+    }
+
+    fn hash(self, data: &[u8]) -> Vec<u8> {
+        match self {
+            ScramHash::Sha1 => Sha1::digest(data).to_vec(),
+            ScramHash::Sha256 => Sha256::digest(data).to_vec(),
+        }
+    }
+
+    // Hi(str, salt, i), which is PBKDF2 with HMAC as the pseudorandom function and one block of
+    // output.
+    fn hi(self, password: &[u8], salt: &[u8], iterations: u32) -> Vec<u8> {
+        let mut u = self.hmac(password, &[salt, &[0, 0, 0, 1]].concat());
+        let mut result = u.clone();
+        for _ in 1..iterations {
+            u = self.hmac(password, &u);
+            for (r, b) in result.iter_mut().zip(&u) {
+                *r ^= b;
+            }
+        }
+        result
+    }
+}
+
+enum ScramState {
+    Initial,
+    ClientFirst { client_first_bare: String },
+    ClientFinal { server_signature: Vec<u8> },
+    Verified,
+    Failed,
+}
+
+/// The [`SCRAM-SHA-1`](https://tools.ietf.org/html/rfc5802) and
+/// [`SCRAM-SHA-256`](https://tools.ietf.org/html/rfc7677) mechanisms, which prove knowledge of
+/// the password without sending it, and have the server prove that it knows the password too.
+///
+/// If the server's proof is missing or wrong, authenticating fails with
+/// [`AuthenticationError::ServerNotVerified`]. An iteration count above 100 000 is rejected with
+/// [`AuthenticationError::InvalidChallenge`], rather than spending that long hashing.
+pub struct Scram {
+    hash: ScramHash,
+    username: String,
+    password: String,
+    nonce: String,
+    state: ScramState,
+}
+
+impl Scram {
+    /// Authenticate as `username` with `password` using `SCRAM-SHA-1`.
+    pub fn sha1(username: impl Into<String>, password: impl Into<String>) -> Self {
+        Scram::new(ScramHash::Sha1, username.into(), password.into(), nonce())
+    }
+
+    /// Authenticate as `username` with `password` using `SCRAM-SHA-256`.
+    pub fn sha256(username: impl Into<String>, password: impl Into<String>) -> Self {
+        Scram::new(ScramHash::Sha256, username.into(), password.into(), nonce())
+    }
+
+    fn new(hash: ScramHash, username: String, password: String, nonce: String) -> Self {
+        Scram {
+            hash,
+            username,
+            password,
+            nonce,
+            state: ScramState::Initial,
+        }
+    }
+
+    /// The name of this mechanism, to pass to
+    /// [`Client::authenticate`](crate::Client::authenticate).
+    pub fn mechanism(&self) -> &'static str {
+        match self.hash {
+            ScramHash::Sha1 => SCRAM_SHA_1,
+            ScramHash::Sha256 => SCRAM_SHA_256,
+        }
+    }
+
+    // server-first-message = [reserved-mext ","] nonce "," salt "," iteration-count ["," extensions]
+    //
+    // Returns the client-final-message and the expected server signature, or `None` if the
+    // server-first-message is invalid.
+    fn client_final(
+        &self,
+        client_first_bare: &str,
+        server_first: &[u8],
+    ) -> Option<(String, Vec<u8>)> {
+        let server_first = std::str::from_utf8(server_first).ok()?;
+        let mut nonce = None;
+        let mut salt = None;
+        let mut iterations = None;
+        for attr in server_first.split(',') {
+            match attr.split_once('=')? {
+                // A mandatory extension this client does not know about.
+                ("m", _) => return None,
+                ("r", r) => nonce = Some(r),
+                ("s", s) => salt = Some(general_purpose::STANDARD.decode(s).ok()?),
+                ("i", i) => iterations = Some(i.parse::<u32>().ok()?),
+                _ => {}
+            }
+        }
+        let (nonce, salt, iterations) = (nonce?, salt?, iterations?);
+        if !nonce.starts_with(&self.nonce)
+            || nonce.len() == self.nonce.len()
+            || iterations == 0
+            || iterations > SCRAM_MAX_ITERATIONS
+        {
+            return None;
+        }
+
+        // "biws" is the base64 encoding of the GS2 header "n,,".
+        let client_final_without_proof = format!("c=biws,r={}", nonce);
+        let auth_message = format!(
+            "{},{},{}",
+            client_first_bare, server_first, client_final_without_proof
+        );
+
+        let salted_password = self.hash.hi(self.password.as_bytes(), &salt, iterations);
+        let client_key = self.hash.hmac(&salted_password, b"Client Key");
+        let stored_key = self.hash.hash(&client_key);
+        let client_signature = self.hash.hmac(&stored_key, auth_message.as_bytes());
+        let client_proof: Vec<u8> = client_key
+            .iter()
+            .zip(&client_signature)
+            .map(|(k, s)| k ^ s)
+            .collect();
+        let server_key = self.hash.hmac(&salted_password, b"Server Key");
+        let server_signature = self.hash.hmac(&server_key, auth_message.as_bytes());
+
+        Some((
+            format!(
+                "{},p={}",
+                client_final_without_proof,
+                general_purpose::STANDARD.encode(client_proof)
+            ),
+            server_signature,
+        ))
+    }
+}
+
+impl AuthenticatorMut for Scram {
+    type Response = Vec<u8>;
+
+    fn process(&mut self, challenge: &[u8]) -> Result<Self::Response, AuthenticationError> {
+        match std::mem::replace(&mut self.state, ScramState::Failed) {
+            ScramState::Initial => {
+                let client_first_bare = format!("n={},r={}", saslname(&self.username), self.nonce);
+                let response = format!("n,,{}", client_first_bare).into_bytes();
+                self.state = ScramState::ClientFirst { client_first_bare };
+                Ok(response)
+            }
+            ScramState::ClientFirst { client_first_bare } => {
+                let (response, server_signature) = self
+                    .client_final(&client_first_bare, challenge)
+                    .ok_or_else(|| AuthenticationError::InvalidChallenge(challenge.to_vec()))?;
+                self.state = ScramState::ClientFinal { server_signature };
+                Ok(response.into_bytes())
+            }
+            ScramState::ClientFinal { server_signature } => {
+                // server-final-message = (server-error / verifier) ["," extensions]
+                let attr = challenge.split(|&b| b == b',').next().unwrap_or(&[]);
+                if let Some(error) = attr.strip_prefix(b"e=") {
+                    return Err(AuthenticationError::ServerError(
+                        String::from_utf8_lossy(error).into_owned(),
+                    ));
+                }
+                let verifier = attr
+                    .strip_prefix(b"v=")
+                    .and_then(|v| general_purpose::STANDARD.decode(v).ok());
+                if verifier.as_deref() != Some(&server_signature[..]) {
+                    return Err(AuthenticationError::ServerNotVerified);
+                }
+                self.state = ScramState::Verified;
+                Ok(Vec::new())
+            }
+            ScramState::Verified | ScramState::Failed => {
+                Err(AuthenticationError::InvalidChallenge(challenge.to_vec()))
+            }
+        }
+    }
+
+    fn client_first(&self) -> bool {
+        true
+    }
+
+    fn finish(&mut self, outcome: Result<(), &Error>) -> Result<(), AuthenticationError> {
+        match (outcome, &self.state) {
+            (Ok(()), ScramState::Verified) | (Err(_), _) => Ok(()),
+            // The server accepted the credentials without proving that it knows them.
+            (Ok(()), _) => Err(AuthenticationError::ServerNotVerified),
+        }
+    }
+}
+
+/// A random client nonce, encoded as printable characters other than ",".
+fn nonce() -> String {
+    let mut bytes = [0; 18];
+    getrandom::getrandom(&mut bytes).expect("no source of randomness available");
+    general_purpose::STANDARD.encode(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock_stream::MockStream;
+    use crate::Client;
+
+    fn process(
+        authenticator: &mut impl AuthenticatorMut<Response = Vec<u8>>,
+        challenge: &[u8],
+    ) -> String {
+        String::from_utf8(authenticator.process(challenge).unwrap()).unwrap()
+    }
+
+    fn scram_sha1() -> Scram {
+        // Example from RFC 5802, section 5.
+        Scram::new(
+            ScramHash::Sha1,
+            "user".to_string(),
+            "pencil".to_string(),
+            "fyko+d2lbbFgONRv9qkxdawL".to_string(),
+        )
+    }
+
+    fn decode_written(written: &[u8]) -> Vec<String> {
+        String::from_utf8(written.to_vec())
+            .unwrap()
+            .lines()
+            .map(|line| {
+                general_purpose::STANDARD
+                    .decode(line)
+                    .map(|l| String::from_utf8(l).unwrap())
+                    .unwrap_or_else(|_| line.to_string())
+            })
+            .collect()
+    }
+
+    #[test]
+    fn simple_mechanisms() {
+        let plain = Plain::new("tim", "tanstaaftanstaaf");
+        assert_eq!(process(&mut &plain, b""), "\0tim\0tanstaaftanstaaf");
+
+        let mut login = Login::new("tim", "tanstaaftanstaaf");
+        assert_eq!(process(&mut login, b"Username:"), "tim");
+        assert_eq!(process(&mut login, b"Password:"), "tanstaaftanstaaf");
+
+        let mut xoauth2 = XOAuth2::new(
+            "someuser@example.com",
+            "ya29.vF9dft4qmTc2Nvb3RlckBhdHRhdmlzdGEuY29tCg",
+        );
+        assert_eq!(
+            process(&mut xoauth2, b""),
+            "user=someuser@example.com\x01auth=Bearer ya29.vF9dft4qmTc2Nvb3RlckBhdHRhdmlzdGEuY29tCg\x01\x01"
+        );
+        assert_eq!(process(&mut xoauth2, br#"{"status":"401"}"#), "");
+        assert!(matches!(
+            xoauth2.finish(Err(&Error::ConnectionLost)),
+            Err(AuthenticationError::ServerError(e)) if e == r#"{"status":"401"}"#
+        ));
+
+        let mut oauthbearer = OAuthBearer::new(
+            "user,=name@example.com",
+            "vF9dft4qmTc2Nvb3RlckBhdHRhdmlzdGEuY29tCg==",
+        );
+        assert_eq!(
+            process(&mut oauthbearer, b""),
+            "n,a=user=2C=3Dname@example.com,\x01auth=Bearer vF9dft4qmTc2Nvb3RlckBhdHRhdmlzdGEuY29tCg==\x01\x01"
+        );
+        assert!(oauthbearer.finish(Ok(())).is_ok());
+    }
+
+    #[test]
+    fn cram_md5() {
+        // Example from RFC 2195, section 2.
+        let cram = CramMd5::new("tim", "tanstaaftanstaaf");
+        assert_eq!(
+            process(&mut &cram, b"<1896.697170952@postoffice.reston.mci.net>"),
+            "tim b913a602c7eda7a495b4e6e7334d3890"
+        );
+    }
+
+    #[test]
+    fn scram_sha1_exchange() {
+        let mut scram = scram_sha1();
+        assert_eq!(
+            process(&mut scram, b""),
+            "n,,n=user,r=fyko+d2lbbFgONRv9qkxdawL"
+        );
+        assert_eq!(
+            process(
+                &mut scram,
+                b"r=fyko+d2lbbFgONRv9qkxdawL3rfcNHYJY1ZVvWVs7j,s=QSXCR+Q6sek8bf92,i=4096"
+            ),
+            "c=biws,r=fyko+d2lbbFgONRv9qkxdawL3rfcNHYJY1ZVvWVs7j,p=v0X8v3Bz2T0CJGbJQyF0X+HI4Ts="
+        );
+        assert_eq!(process(&mut scram, b"v=rmF9pqV8S7suAoZWja4dJRkFsKQ="), "");
+        assert!(scram.finish(Ok(())).is_ok());
+    }
+
+    #[test]
+    fn scram_rejects_bad_server() {
+        // The server must extend the client nonce.
+        let mut scram = scram_sha1();
+        process(&mut scram, b"");
+        assert!(matches!(
+            scram.process(b"r=fyko+d2lbbFgONRv9qkxdawL,s=QSXCR+Q6sek8bf92,i=4096"),
+            Err(AuthenticationError::InvalidChallenge(_))
+        ));
+
+        let mut scram = scram_sha1();
+        process(&mut scram, b"");
+        process(
+            &mut scram,
+            b"r=fyko+d2lbbFgONRv9qkxdawL3rfcNHYJY1ZVvWVs7j,s=QSXCR+Q6sek8bf92,i=4096",
+        );
+        assert!(matches!(
+            scram.process(b"v=AAAAAAAAAAAAAAAAAAAAAAAAAAA="),
+            Err(AuthenticationError::ServerNotVerified)
+        ));
+
+        let mut scram = scram_sha1();
+        process(&mut scram, b"");
+        process(
+            &mut scram,
+            b"r=fyko+d2lbbFgONRv9qkxdawL3rfcNHYJY1ZVvWVs7j,s=QSXCR+Q6sek8bf92,i=4096",
+        );
+        assert!(matches!(
+            scram.process(b"e=invalid-proof"),
+            Err(AuthenticationError::ServerError(e)) if e == "invalid-proof"
+        ));
+
+        // The iteration count must be reasonable.
+        for i in ["0", "100001", "4294967295"] {
+            let mut scram = scram_sha1();
+            process(&mut scram, b"");
+            let server_first = format!(
+                "r=fyko+d2lbbFgONRv9qkxdawL3rfcNHYJY1ZVvWVs7j,s=QSXCR+Q6sek8bf92,i={}",
+                i
+            );
+            assert!(matches!(
+                scram.process(server_first.as_bytes()),
+                Err(AuthenticationError::InvalidChallenge(_))
+            ));
+        }
+
+        // The server must not accept the credentials before proving that it knows them.
+        let mut scram = scram_sha1();
+        process(&mut scram, b"");
+        assert!(matches!(
+            scram.finish(Ok(())),
+            Err(AuthenticationError::ServerNotVerified)
+        ));
+    }
+
+    #[test]
+    fn scram_sha256_authenticate() {
+        // Example from RFC 7677, section 3.
+        let response = b"+ \r\n\
+            + cj1yT3ByTkdmd0ViZVJXZ2JORWtxTyVodllEcFdVYTJSYVRDQWZ1eEZJbGopaE5sRiRrMCxzPVcyMlphSjBTTlk3c29Fc1VFamI2Z1E9PSxpPTQwOTY=\r\n\
+            + dj02cnJpVFJCaTIzV3BSUi93dHVwK21NaFVaVW4vZEI1bkxUSlJzamw5NUc0PQ==\r\n\
+            a1 OK Logged in\r\n"
+            .to_vec();
+        let client = Client::new(MockStream::new(response));
+        let mut scram = Scram::new(
+            ScramHash::Sha256,
+            "user".to_string(),
+            "pencil".to_string(),
+            "rOprNGfwEbeRWgbNEkqO".to_string(),
+        );
+        let session = client.authenticate(scram.mechanism(), &mut scram).unwrap();
+        assert_eq!(
+            decode_written(&session.stream.get_ref().written_buf),
+            [
+                "a1 AUTHENTICATE SCRAM-SHA-256",
+                "n,,n=user,r=rOprNGfwEbeRWgbNEkqO",
+                "c=biws,r=rOprNGfwEbeRWgbNEkqO%hvYDpWUa2RaTCAfuxFIlj)hNlF$k0,\
+                 p=dHzbZapWIk4jUhN+Ute9ytag9zjfMHgsqmmiz7AndVQ=",
+                "",
+            ]
+        );
+    }
+
+    #[test]
+    fn scram_cancels_on_bad_server_signature() {
+        let response = b"+ \r\n\
+            + cj1meWtvK2QybGJiRmdPTlJ2OXFreGRhd0wzcmZjTkhZSlkxWlZ2V1ZzN2oscz1RU1hDUitRNnNlazhiZjkyLGk9NDA5Ng==\r\n\
+            + dj1BQUFBQUFBQUFBQUFBQUFBQUFBQUFBQUFBQUE9\r\n\
+            a1 BAD Exchange cancelled\r\n"
+            .to_vec();
+        let client = Client::new(MockStream::new(response));
+        let mut scram = scram_sha1();
+        let (err, client) = client
+            .authenticate(scram.mechanism(), &mut scram)
+            .unwrap_err();
+        assert!(matches!(
+            err,
+            Error::Authentication(AuthenticationError::ServerNotVerified)
+        ));
+        assert!(client.stream.get_ref().written_buf.ends_with(b"\r\n*\r\n"));
+    }
+
+    #[test]
+    fn scram_logs_out_when_server_not_verified() {
+        let response = b"+ \r\n\
+            + cj1meWtvK2QybGJiRmdPTlJ2OXFreGRhd0wzcmZjTkhZSlkxWlZ2V1ZzN2oscz1RU1hDUitRNnNlazhiZjkyLGk9NDA5Ng==\r\n\
+            a1 OK Logged in\r\n\
+            * BYE Logging out\r\n\
+            a2 OK Logout completed\r\n"
+            .to_vec();
+        let client = Client::new(MockStream::new(response));
+        let mut scram = scram_sha1();
+        let (err, client) = client
+            .authenticate(scram.mechanism(), &mut scram)
+            .unwrap_err();
+        assert!(matches!(
+            err,
+            Error::Authentication(AuthenticationError::ServerNotVerified)
+        ));
+        assert!(client
+            .stream
+            .get_ref()
+            .written_buf
+            .ends_with(b"\r\na2 LOGOUT\r\n"));
+        // The server has closed the connection after logging out, so the client cannot be used to
+        // try again.
+        assert!(client.login("user", "pencil").is_err());
+    }
+
+    #[test]
+    fn oauthbearer_server_error() {
+        let response = b"+ \r\n\
+            + eyJzdGF0dXMiOiJpbnZhbGlkX3Rva2VuIn0=\r\n\
+            a1 NO Authentication failed\r\n"
+            .to_vec();
+        let client = Client::new(MockStream::new(response));
+        let mut oauthbearer = OAuthBearer::new("user", "token");
+        let (err, client) = client
+            .authenticate(oauthbearer.mechanism(), &mut oauthbearer)
+            .unwrap_err();
+        assert!(matches!(
+            err,
+            Error::Authentication(AuthenticationError::ServerError(e))
+                if e == r#"{"status":"invalid_token"}"#
+        ));
+        assert_eq!(
+            decode_written(&client.stream.get_ref().written_buf),
+            [
+                "a1 AUTHENTICATE OAUTHBEARER",
+                "n,a=user,\x01auth=Bearer token\x01\x01",
+                "\x01",
+            ]
+        );
+    }
+
+    #[test]
+    fn scram_sasl_ir() {
+        let response = b"+ cj1meWtvK2QybGJiRmdPTlJ2OXFreGRhd0wzcmZjTkhZSlkxWlZ2V1ZzN2oscz1RU1hDUitRNnNlazhiZjkyLGk9NDA5Ng==\r\n\
+            + dj1ybUY5cHFWOFM3c3VBb1pXamE0ZEpSa0ZzS1E9\r\n\
+            a1 OK Logged in\r\n"
+            .to_vec();
+        let mut client = Client::new(MockStream::new(response));
+        client
+            .cache_capabilities(b"* OK [CAPABILITY IMAP4rev1 SASL-IR AUTH=SCRAM-SHA-1] ready\r\n");
+        let mut scram = scram_sha1();
+        let session = client.authenticate(scram.mechanism(), &mut scram).unwrap();
+        assert_eq!(
+            decode_written(&session.stream.get_ref().written_buf),
+            [
+                "a1 AUTHENTICATE SCRAM-SHA-1 biwsbj11c2VyLHI9ZnlrbytkMmxiYkZnT05Sdjlxa3hkYXdM",
+                "c=biws,r=fyko+d2lbbFgONRv9qkxdawL3rfcNHYJY1ZVvWVs7j,p=v0X8v3Bz2T0CJGbJQyF0X+HI4Ts=",
+                "",
+            ]
+        );
+    }
+
+    #[test]
+    fn authenticate_best() {
+        let response = b"* CAPABILITY IMAP4rev1 AUTH=PLAIN AUTH=CRAM-MD5 AUTH=XOAUTH2\r\n\
+            a1 OK Completed\r\n\
+            + PDE4OTYuNjk3MTcwOTUyQHBvc3RvZmZpY2UucmVzdG9uLm1jaS5uZXQ+\r\n\
+            a2 OK Logged in\r\n"
+            .to_vec();
+        let client = Client::new(MockStream::new(response));
+        let session = client
+            .authenticate_best(Credentials::Password {
+                username: "tim",
+                password: "tanstaaftanstaaf",
+            })
+            .unwrap();
+        assert!(session.stream.get_ref().written_buf.ends_with(
+            b"a2 AUTHENTICATE CRAM-MD5\r\ndGltIGI5MTNhNjAyYzdlZGE3YTQ5NWI0ZTZlNzMzNGQzODkw\r\n"
+        ));
+
+        let mut client = Client::new(MockStream::new(Vec::new()));
+        client.cache_capabilities(b"* OK [CAPABILITY IMAP4rev1 AUTH=PLAIN] ready\r\n");
+        let (err, _) = client
+            .authenticate_best(Credentials::OAuth2 {
+                username: "me",
+                access_token: "token",
+            })
+            .unwrap_err();
+        assert!(matches!(
+            err,
+            Error::Authentication(AuthenticationError::NoMechanism)
+        ));
+    }
+}
@@ -1,11 +1,22 @@
 use crate::error::Error;
 use crate::parse::{parse_many_into, MapOrNot};
 use crate::types::UnsolicitedResponse;
-use imap_proto::{Capability, Response};
+use imap_proto::parser::core::atom;
+use imap_proto::Capability;
+use nom::{
+    bytes::complete::{tag, tag_no_case, take_till1},
+    character::complete::{char, space0},
+    combinator::map,
+    multi::many0,
+    sequence::{delimited, pair, preceded, terminated, tuple},
+    IResult,
+};
 use ouroboros::self_referencing;
+use std::borrow::Cow;
 use std::collections::hash_set::Iter;
 use std::collections::HashSet;
 use std::collections::VecDeque;
+use std::fmt;
 
 const IMAP4REV1_CAPABILITY: &str = "IMAP4rev1";
 const AUTH_CAPABILITY_PREFIX: &str = "AUTH=";
@@ -13,7 +24,8 @@ const AUTH_CAPABILITY_PREFIX: &str = "AUTH=";
 /// From [section 7.2.1 of RFC 3501](https://tools.ietf.org/html/rfc3501#section-7.2.1).
 ///
 /// A list of capabilities that the server supports.
-/// The capability list will include the atom "IMAP4rev1".
+/// The capability list will include the atom "IMAP4rev1", or "IMAP4rev2" for servers that
+/// implement [RFC 9051](https://tools.ietf.org/html/rfc9051), or both.
 ///
 /// In addition, all servers implement the `STARTTLS`, `LOGINDISABLED`, and `AUTH=PLAIN` (described
 /// in [IMAP-TLS](https://tools.ietf.org/html/rfc2595)) capabilities. See the [Security
@@ -53,16 +65,34 @@ impl Capabilities {
             data: owned,
             capabilities_builder: |input| {
                 let mut caps = HashSet::new();
-                parse_many_into(input, &mut caps, unsolicited, |response| match response {
-                    Response::Capabilities(c) => Ok(MapOrNot::MapVec(c)),
-                    resp => Ok(MapOrNot::Not(resp)),
-                })?;
+                parse_many_into(
+                    input,
+                    &mut caps,
+                    unsolicited,
+                    map(capability_response, MapOrNot::MapVec),
+                    |response| Ok(MapOrNot::Not(response)),
+                )?;
                 Ok(caps)
             },
         }
         .try_build()
     }
 
+    /// Parse the `[CAPABILITY ...]` response code that servers may include in the greeting or in
+    /// the response to a successful login, if there is one.
+    pub(crate) fn parse_response_code(owned: Vec<u8>) -> Option<Self> {
+        CapabilitiesTryBuilder {
+            data: owned,
+            capabilities_builder: |input| {
+                capability_code(input)
+                    .map(|(_, c)| c.into_iter().collect())
+                    .map_err(|_| ())
+            },
+        }
+        .try_build()
+        .ok()
+    }
+
     /// Check if the server has the given capability.
     pub fn has(&self, cap: &Capability<'_>) -> bool {
         self.borrow_capabilities().contains(cap)
@@ -98,3 +128,67 @@ impl Capabilities {
         self.borrow_capabilities().is_empty()
     }
 }
+
+// capability = ("AUTH=" auth-type) / atom
+fn capability(i: &[u8]) -> IResult<&[u8], Capability<'_>> {
+    map(atom, |a| {
+        if a.eq_ignore_ascii_case(IMAP4REV1_CAPABILITY) {
+            return Capability::Imap4rev1;
+        }
+        match a.get(..AUTH_CAPABILITY_PREFIX.len()) {
+            Some(pre) if pre.eq_ignore_ascii_case(AUTH_CAPABILITY_PREFIX) => {
+                Capability::Auth(Cow::Borrowed(&a[AUTH_CAPABILITY_PREFIX.len()..]))
+            }
+            _ => Capability::Atom(Cow::Borrowed(a)),
+        }
+    })(i)
+}
+
+// capability-data = "CAPABILITY" *(SP capability) SP "IMAP4rev1" *(SP capability)
+//
+// imap-proto rejects lists without IMAP4rev1, which servers that only speak IMAP4rev2 send (see
+// RFC 9051, section 7.2.2), so capabilities are parsed here.
+fn capability_data(i: &[u8]) -> IResult<&[u8], Vec<Capability<'_>>> {
+    preceded(
+        tag_no_case("CAPABILITY"),
+        many0(preceded(char(' '), capability)),
+    )(i)
+}
+
+// "*" SP capability-data CRLF
+fn capability_response(i: &[u8]) -> IResult<&[u8], Vec<Capability<'_>>> {
+    delimited(tag("* "), capability_data, pair(space0, tag("\r\n")))(i)
+}
+
+// (tag / "*") SP resp-cond-state SP "[" capability-data "]" ...
+fn capability_code(i: &[u8]) -> IResult<&[u8], Vec<Capability<'_>>> {
+    preceded(
+        tuple((take_till1(|b| b == b' '), char(' '), atom, tag(" ["))),
+        terminated(capability_data, char(']')),
+    )(i)
+}
+
+impl Clone for Capabilities {
+    fn clone(&self) -> Self {
+        CapabilitiesBuilder {
+            data: Vec::new(),
+            capabilities_builder: |_| {
+                self.borrow_capabilities()
+                    .iter()
+                    .map(|c| match c {
+                        Capability::Imap4rev1 => Capability::Imap4rev1,
+                        Capability::Auth(a) => Capability::Auth(Cow::Owned(a.to_string())),
+                        Capability::Atom(a) => Capability::Atom(Cow::Owned(a.to_string())),
+                    })
+                    .collect()
+            },
+        }
+        .build()
+    }
+}
+
+impl fmt::Debug for Capabilities {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_set().entries(self.iter()).finish()
+    }
+}
@@ -0,0 +1,42 @@
+use imap_proto::UidSetMember;
+
+use crate::types::Uid;
+
+/// Meta-information about copied or moved messages, as returned by
+/// [`COPY`](https://tools.ietf.org/html/rfc3501#section-6.4.7) and
+/// [`MOVE`](https://tools.ietf.org/html/rfc6851#section-3.1).
+/// Note that these commands only return any data if the server supports
+/// [`UIDPLUS`](https://tools.ietf.org/html/rfc4315), and the destination mailbox supports
+/// persistent UIDs.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+#[non_exhaustive]
+pub struct Copied {
+    /// The unique identifier validity value of the mailbox that the messages were copied to.
+    /// See [`Uid`] for more details.
+    pub uid_validity: Option<u32>,
+
+    /// The unique identifier values of the copied messages in the source mailbox.
+    pub source_uids: Option<Vec<UidSetMember>>,
+
+    /// The unique identifier values of the copies in the destination mailbox, in the same order
+    /// as [`Copied::source_uids`].
+    pub destination_uids: Option<Vec<UidSetMember>>,
+}
+
+impl Copied {
+    /// Iterate over the copied messages as pairs of their [`Uid`] in the source mailbox and the
+    /// [`Uid`] of their copy in the destination mailbox. This is empty if the server did not
+    /// return them.
+    pub fn uid_map(&self) -> impl Iterator<Item = (Uid, Uid)> + '_ {
+        uids(&self.source_uids).zip(uids(&self.destination_uids))
+    }
+}
+
+fn uids(set: &Option<Vec<UidSetMember>>) -> impl Iterator<Item = Uid> + '_ {
+    set.iter().flatten().flat_map(|member| match member {
+        UidSetMember::Uid(uid) => *uid..=*uid,
+        UidSetMember::UidRange(range) => {
+            *range.start().min(range.end())..=*range.start().max(range.end())
+        }
+    })
+}
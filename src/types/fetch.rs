@@ -1,13 +1,16 @@
 use super::{Flag, Seq, Uid};
 use crate::error::Error;
-use crate::parse::{parse_many_into, MapOrNot};
+use crate::parse::{fetch_extensions_response, parse_many_into, MapOrNot};
 use crate::types::UnsolicitedResponse;
 use chrono::{DateTime, FixedOffset};
 use imap_proto::types::{
     AttributeValue, BodyStructure, Envelope, MessageSection, Response, SectionPath,
 };
+use nom::combinator::map;
 use ouroboros::self_referencing;
+use std::borrow::Cow;
 use std::collections::VecDeque;
+use std::fmt;
 use std::slice::Iter;
 
 /// Format of Date and Time as defined RFC3501.
@@ -34,33 +37,18 @@ impl Fetches {
             data: owned,
             fetches_builder: |input| {
                 let mut fetches = Vec::new();
-                parse_many_into(input, &mut fetches, unsolicited, |response| {
-                    match response {
+                parse_many_into(
+                    input,
+                    &mut fetches,
+                    unsolicited,
+                    map(fetch_extensions_response, MapOrNot::Map),
+                    |response| match response {
                         Response::Fetch(num, attrs) => {
-                            let mut fetch = Fetch {
-                                message: num,
-                                flags: vec![],
-                                uid: None,
-                                size: None,
-                                fetch: attrs,
-                            };
-
-                            // set some common fields eagerly
-                            for attr in &fetch.fetch {
-                                match attr {
-                                    AttributeValue::Flags(flags) => {
-                                        fetch.flags.extend(Flag::from_strs(flags));
-                                    }
-                                    AttributeValue::Uid(uid) => fetch.uid = Some(*uid),
-                                    AttributeValue::Rfc822Size(sz) => fetch.size = Some(*sz),
-                                    _ => {}
-                                }
-                            }
-                            Ok(MapOrNot::Map(fetch))
+                            Ok(MapOrNot::Map(Fetch::new(num, attrs, Vec::new())))
                         }
                         resp => Ok(MapOrNot::Not(resp)),
-                    }
-                })?;
+                    },
+                )?;
                 Ok(fetches)
             },
         }
@@ -88,6 +76,51 @@ impl Fetches {
     }
 }
 
+impl fmt::Debug for Fetches {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list().entries(self.iter()).finish()
+    }
+}
+
+/// A fetch item of an extension that imap-proto does not parse.
+#[derive(Debug, Eq, PartialEq)]
+pub(crate) enum ExtensionAttribute<'a> {
+    /// `BINARY[<part>]`, see [`Fetch::binary`].
+    BinarySection {
+        part: Vec<u32>,
+        data: Option<Cow<'a, [u8]>>,
+    },
+
+    /// `BINARY.SIZE[<part>]`, see [`Fetch::binary_size`].
+    BinarySize { part: Vec<u32>, size: u32 },
+
+    /// `EMAILID`, see [`Fetch::email_id`].
+    EmailId(Cow<'a, str>),
+
+    /// `THREADID`, see [`Fetch::thread_id`].
+    ThreadId(Option<Cow<'a, str>>),
+}
+
+impl ExtensionAttribute<'_> {
+    fn into_owned(self) -> ExtensionAttribute<'static> {
+        match self {
+            ExtensionAttribute::BinarySection { part, data } => ExtensionAttribute::BinarySection {
+                part,
+                data: data.map(|data| Cow::Owned(data.into_owned())),
+            },
+            ExtensionAttribute::BinarySize { part, size } => {
+                ExtensionAttribute::BinarySize { part, size }
+            }
+            ExtensionAttribute::EmailId(id) => {
+                ExtensionAttribute::EmailId(Cow::Owned(id.into_owned()))
+            }
+            ExtensionAttribute::ThreadId(id) => {
+                ExtensionAttribute::ThreadId(id.map(|id| Cow::Owned(id.into_owned())))
+            }
+        }
+    }
+}
+
 /// An IMAP [`FETCH` response](https://tools.ietf.org/html/rfc3501#section-7.4.2) that contains
 /// data about a particular message. This response occurs as the result of a `FETCH` or `STORE`
 /// command, as well as by unilateral server decision (e.g., flag updates).
@@ -106,10 +139,39 @@ pub struct Fetch<'a> {
     pub size: Option<u32>,
 
     pub(crate) fetch: Vec<AttributeValue<'a>>,
+    pub(crate) extensions: Vec<ExtensionAttribute<'a>>,
     pub(crate) flags: Vec<Flag<'static>>,
 }
 
 impl<'a> Fetch<'a> {
+    pub(crate) fn new(
+        message: Seq,
+        fetch: Vec<AttributeValue<'a>>,
+        extensions: Vec<ExtensionAttribute<'a>>,
+    ) -> Self {
+        let mut fetch = Fetch {
+            message,
+            flags: vec![],
+            uid: None,
+            size: None,
+            fetch,
+            extensions,
+        };
+
+        // set some common fields eagerly
+        for attr in &fetch.fetch {
+            match attr {
+                AttributeValue::Flags(flags) => {
+                    fetch.flags.extend(Flag::from_strs(flags));
+                }
+                AttributeValue::Uid(uid) => fetch.uid = Some(*uid),
+                AttributeValue::Rfc822Size(sz) => fetch.size = Some(*sz),
+                _ => {}
+            }
+        }
+        fetch
+    }
+
     /// A list of flags that are set for this message.
     pub fn flags(&self) -> &[Flag<'a>] {
         &self.flags[..]
@@ -248,6 +310,11 @@ impl<'a> Fetch<'a> {
             uid: self.uid,
             size: self.size,
             fetch: self.fetch.into_iter().map(|av| av.into_owned()).collect(),
+            extensions: self
+                .extensions
+                .into_iter()
+                .map(|e| e.into_owned())
+                .collect(),
             flags: self.flags.clone(),
         }
     }
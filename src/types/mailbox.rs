@@ -41,11 +41,46 @@ pub struct Mailbox {
     /// [Conditional STORE](https://tools.ietf.org/html/rfc4551#section-3.1.1).
     pub highest_mod_seq: Option<u64>,
 
+    /// The total size of the mailbox in octets, if `SIZE` was requested with
+    /// [`Session::status`](crate::Session::status). See [RFC
+    /// 8438](https://tools.ietf.org/html/rfc8438#section-2).
+    pub size: Option<u64>,
+
+    /// The number of messages with the `\Deleted` flag set, if `DELETED` was requested with
+    /// [`Session::status`](crate::Session::status). See [RFC 9208, section
+    /// 4.2](https://tools.ietf.org/html/rfc9208#section-4.2).
+    pub deleted: Option<u32>,
+
+    /// The amount of storage, in units of 1024 octets, that would be freed by expunging the
+    /// messages with the `\Deleted` flag set, if `DELETED-STORAGE` was requested with
+    /// [`Session::status`](crate::Session::status). See [RFC 9208, section
+    /// 4.2](https://tools.ietf.org/html/rfc9208#section-4.2).
+    pub deleted_storage: Option<u64>,
+
+    /// The unique identifier of the mailbox, which does not change when it is renamed. Only
+    /// present if the server supports the [OBJECTID](https://tools.ietf.org/html/rfc8474)
+    /// extension, and, for [`Session::status`](crate::Session::status), if `MAILBOXID` was
+    /// requested.
+    pub mailbox_id: Option<String>,
+
     /// The mailbox is selected read-only, or its access while selected has changed from read-write
     /// to read-only.
     pub is_read_only: bool,
 }
 
+/// The mailbox that is currently selected on a [`Session`](crate::Session), see
+/// [`Session::selected`](crate::Session::selected).
+#[derive(Clone, Debug, Eq, PartialEq, Hash)]
+#[non_exhaustive]
+pub struct SelectedMailbox {
+    /// The name of the mailbox, as it was passed to `select` or `examine`.
+    pub name: String,
+
+    /// The mailbox was opened with `examine`, or the server reported that it was selected
+    /// read-only.
+    pub is_read_only: bool,
+}
+
 #[allow(clippy::derivable_impls)]
 impl Default for Mailbox {
     fn default() -> Mailbox {
@@ -58,6 +93,10 @@ impl Default for Mailbox {
             uid_next: None,
             uid_validity: None,
             highest_mod_seq: None,
+            size: None,
+            deleted: None,
+            deleted_storage: None,
+            mailbox_id: None,
             is_read_only: false,
         }
     }
@@ -68,7 +107,8 @@ impl fmt::Display for Mailbox {
         write!(
             f,
             "flags: {:?}, exists: {}, recent: {}, unseen: {:?}, permanent_flags: {:?},\
-             uid_next: {:?}, uid_validity: {:?}, highest_mod_seq: {:?}, is_read_only: {:?}",
+             uid_next: {:?}, uid_validity: {:?}, highest_mod_seq: {:?}, size: {:?}, \
+             deleted: {:?}, deleted_storage: {:?}, mailbox_id: {:?}, is_read_only: {:?}",
             self.flags,
             self.exists,
             self.recent,
@@ -77,6 +117,10 @@ impl fmt::Display for Mailbox {
             self.uid_next,
             self.uid_validity,
             self.highest_mod_seq,
+            self.size,
+            self.deleted,
+            self.deleted_storage,
+            self.mailbox_id,
             self.is_read_only,
         )
     }
@@ -104,13 +104,14 @@ pub type Uid = u32;
 pub type Seq = u32;
 
 mod fetch;
+pub(crate) use self::fetch::ExtensionAttribute;
 pub use self::fetch::{Fetch, Fetches};
 
 mod flag;
 pub use self::flag::Flag;
 
 mod mailbox;
-pub use self::mailbox::Mailbox;
+pub use self::mailbox::{Mailbox, SelectedMailbox};
 
 mod name;
 pub use self::name::{Name, Names};
@@ -132,3 +133,9 @@ pub use self::unsolicited_response::{AttributeValue, UnsolicitedResponse};
 
 mod appended;
 pub use self::appended::Appended;
+
+mod sequence_set;
+pub use self::sequence_set::SequenceSet;
+
+mod copied;
+pub use self::copied::Copied;
@@ -1,7 +1,10 @@
 use crate::error::Error;
+use crate::extensions::list_extended::list_response;
 use crate::parse::{parse_many_into, MapOrNot};
 use crate::types::UnsolicitedResponse;
-use imap_proto::{MailboxDatum, NameAttribute, Response};
+use crate::utf7;
+use imap_proto::NameAttribute;
+use nom::combinator::map;
 use ouroboros::self_referencing;
 use std::borrow::Cow;
 use std::collections::VecDeque;
@@ -26,18 +29,13 @@ impl Names {
             data: owned,
             names_builder: |input| {
                 let mut names = Vec::new();
-                parse_many_into(input, &mut names, unsolicited, |response| match response {
-                    Response::MailboxData(MailboxDatum::List {
-                        name_attributes,
-                        delimiter,
-                        name,
-                    }) => Ok(MapOrNot::Map(Name {
-                        attributes: name_attributes,
-                        delimiter,
-                        name,
-                    })),
-                    resp => Ok(MapOrNot::Not(resp)),
-                })?;
+                parse_many_into(
+                    input,
+                    &mut names,
+                    unsolicited,
+                    map(list_response, MapOrNot::Map),
+                    |response| Ok(MapOrNot::Not(response)),
+                )?;
                 Ok(names)
             },
         }
@@ -71,6 +69,8 @@ pub struct Name<'a> {
     pub(crate) attributes: Vec<NameAttribute<'a>>,
     pub(crate) delimiter: Option<Cow<'a, str>>,
     pub(crate) name: Cow<'a, str>,
+    pub(crate) child_info: Vec<Cow<'a, str>>,
+    pub(crate) old_name: Option<Cow<'a, str>>,
 }
 
 impl<'a> Name<'a> {
@@ -105,6 +105,20 @@ impl<'a> Name<'a> {
                 .collect(),
             delimiter: self.delimiter.map(|cow| Cow::Owned(cow.into_owned())),
             name: Cow::Owned(self.name.into_owned()),
+            child_info: self
+                .child_info
+                .into_iter()
+                .map(|cow| Cow::Owned(cow.into_owned()))
+                .collect(),
+            old_name: self.old_name.map(|cow| Cow::Owned(cow.into_owned())),
+        }
+    }
+
+    /// Converts the mailbox names sent by the server from modified UTF-7 into UTF-8.
+    pub(crate) fn decode_utf7(&mut self) {
+        utf7::decode_lossless(&mut self.name);
+        if let Some(old_name) = &mut self.old_name {
+            utf7::decode_lossless(old_name);
         }
     }
 }
@@ -1,12 +1,15 @@
 use crate::error::{Error, ParseError};
 use crate::parse::{parse_many_into2, parse_until_done_optional, MapOrNot, MapOrNot2};
-use crate::types::UnsolicitedResponse;
-use imap_proto::Response;
+use crate::types::{Capabilities, UnsolicitedResponse};
+use imap_proto::{Capability, Response};
+use nom::combinator::fail;
 use ouroboros::self_referencing;
 use std::borrow::Cow;
 use std::collections::VecDeque;
 use std::fmt::{Debug, Display, Formatter};
 
+const QUOTA_RESOURCE_PREFIX: &str = "QUOTA=RES-";
+
 /// From [SETQUOTA Resource limit](https://datatracker.ietf.org/doc/html/rfc2087#section-4.1)
 ///
 /// Used by [`Session::set_quota`](crate::Session::set_quota).
@@ -33,9 +36,11 @@ impl Display for QuotaResourceLimit<'_> {
     }
 }
 
-/// From [Resources](https://datatracker.ietf.org/doc/html/rfc2087#section-3)
+/// From [Resources](https://datatracker.ietf.org/doc/html/rfc2087#section-3), and [RFC
+/// 9208](https://datatracker.ietf.org/doc/html/rfc9208#section-5)
 ///
-/// Used by [`QuotaResourceLimit`], and [`QuotaResource`]
+/// Used by [`QuotaResourceLimit`], and [`QuotaResource`]. The resources a server supports are
+/// listed by [`Capabilities::quota_resources`].
 #[derive(Debug, Eq, PartialEq, Clone)]
 #[non_exhaustive]
 pub enum QuotaResourceName<'a> {
@@ -43,27 +48,23 @@ pub enum QuotaResourceName<'a> {
     Storage,
     /// Number of messages
     Message,
+    /// Number of mailboxes
+    Mailbox,
+    /// Sum of the sizes of all annotations, in units of 1024 octets
+    AnnotationStorage,
     /// Any other string (for future RFCs)
     Atom(Cow<'a, str>),
 }
 
 impl<'a> From<&'a str> for QuotaResourceName<'a> {
     fn from(input: &'a str) -> Self {
-        match input {
-            "STORAGE" => QuotaResourceName::Storage,
-            "MESSAGE" => QuotaResourceName::Message,
-            _ => QuotaResourceName::Atom(Cow::from(input)),
-        }
+        QuotaResourceName::from_cow(Cow::from(input))
     }
 }
 
 impl From<String> for QuotaResourceName<'_> {
     fn from(input: String) -> Self {
-        match input.as_str() {
-            "STORAGE" => QuotaResourceName::Storage,
-            "MESSAGE" => QuotaResourceName::Message,
-            _ => QuotaResourceName::Atom(Cow::from(input)),
-        }
+        QuotaResourceName::from_cow(Cow::from(input))
     }
 }
 
@@ -72,22 +73,67 @@ impl Display for QuotaResourceName<'_> {
         match self {
             Self::Storage => write!(f, "STORAGE"),
             Self::Message => write!(f, "MESSAGE"),
+            Self::Mailbox => write!(f, "MAILBOX"),
+            Self::AnnotationStorage => write!(f, "ANNOTATION-STORAGE"),
             Self::Atom(s) => write!(f, "{}", s),
         }
     }
 }
 
 impl<'a> QuotaResourceName<'a> {
+    fn from_cow(input: Cow<'a, str>) -> Self {
+        if input.eq_ignore_ascii_case("STORAGE") {
+            QuotaResourceName::Storage
+        } else if input.eq_ignore_ascii_case("MESSAGE") {
+            QuotaResourceName::Message
+        } else if input.eq_ignore_ascii_case("MAILBOX") {
+            QuotaResourceName::Mailbox
+        } else if input.eq_ignore_ascii_case("ANNOTATION-STORAGE") {
+            QuotaResourceName::AnnotationStorage
+        } else {
+            QuotaResourceName::Atom(input)
+        }
+    }
+
     /// Get an owned version of the [`QuotaResourceName`].
     pub fn into_owned(self) -> QuotaResourceName<'static> {
         match self {
             QuotaResourceName::Storage => QuotaResourceName::Storage,
             QuotaResourceName::Message => QuotaResourceName::Message,
+            QuotaResourceName::Mailbox => QuotaResourceName::Mailbox,
+            QuotaResourceName::AnnotationStorage => QuotaResourceName::AnnotationStorage,
             QuotaResourceName::Atom(n) => QuotaResourceName::Atom(Cow::Owned(n.into_owned())),
         }
     }
 }
 
+impl Capabilities {
+    /// The quota resources the server supports, as advertised by its `QUOTA=RES-<resource>`
+    /// capabilities. See [RFC 9208, section 3.1](https://datatracker.ietf.org/doc/html/rfc9208#section-3.1).
+    pub fn quota_resources(&self) -> impl Iterator<Item = QuotaResourceName<'_>> {
+        let len = QUOTA_RESOURCE_PREFIX.len();
+        self.iter().filter_map(move |cap| match cap {
+            Capability::Atom(atom) => match (atom.get(..len), atom.get(len..)) {
+                (Some(prefix), Some(resource))
+                    if prefix.eq_ignore_ascii_case(QUOTA_RESOURCE_PREFIX)
+                        && !resource.is_empty() =>
+                {
+                    Some(QuotaResourceName::from(resource))
+                }
+                _ => None,
+            },
+            _ => None,
+        })
+    }
+
+    /// Check if the server allows changing resource limits with
+    /// [`Session::set_quota`](crate::Session::set_quota), as advertised by its `QUOTASET`
+    /// capability. See [RFC 9208, section 3.1](https://datatracker.ietf.org/doc/html/rfc9208#section-3.1).
+    pub fn has_quota_set(&self) -> bool {
+        self.has_str("QUOTASET")
+    }
+}
+
 /// From [QUOTA Response](https://datatracker.ietf.org/doc/html/rfc2087#section-5.1)
 ///
 /// This is a wrapper around a single single [`Quota`].
@@ -149,7 +195,7 @@ impl<'a> Quota<'a> {
                     name: match e.name {
                         imap_proto::QuotaResourceName::Storage => QuotaResourceName::Storage,
                         imap_proto::QuotaResourceName::Message => QuotaResourceName::Message,
-                        imap_proto::QuotaResourceName::Atom(e) => QuotaResourceName::Atom(e),
+                        imap_proto::QuotaResourceName::Atom(e) => QuotaResourceName::from_cow(e),
                     },
                     usage: e.usage,
                     limit: e.limit,
@@ -213,6 +259,7 @@ impl QuotaRootResponse {
                     &mut quota_roots,
                     &mut quotas,
                     unsolicited,
+                    fail,
                     |response| match response {
                         Response::QuotaRoot(q) => Ok(MapOrNot2::Map1(q)),
                         Response::Quota(q) => Ok(MapOrNot2::Map2(Quota::from_imap_proto(q))),
@@ -283,6 +330,44 @@ mod tests {
         assert!(matches!(name, QuotaResourceName::Storage));
     }
 
+    #[test]
+    fn test_quota_resource_name_rfc9208() {
+        assert_eq!(
+            QuotaResourceName::from("mailbox"),
+            QuotaResourceName::Mailbox
+        );
+        assert_eq!(
+            QuotaResourceName::from("ANNOTATION-STORAGE"),
+            QuotaResourceName::AnnotationStorage
+        );
+        assert_eq!(
+            QuotaResourceName::AnnotationStorage.to_string(),
+            "ANNOTATION-STORAGE"
+        );
+    }
+
+    #[test]
+    fn test_capabilities_quota_resources() {
+        let caps = Capabilities::parse(
+            b"* CAPABILITY IMAP4rev1 QUOTA QUOTA=RES-STORAGE QUOTA=RES-MAILBOX QUOTASET\r\n"
+                .to_vec(),
+            &mut VecDeque::new(),
+        )
+        .unwrap();
+        let mut resources: Vec<_> = caps.quota_resources().map(|r| r.to_string()).collect();
+        resources.sort();
+        assert_eq!(resources, vec!["MAILBOX", "STORAGE"]);
+        assert!(caps.has_quota_set());
+
+        let caps = Capabilities::parse(
+            b"* CAPABILITY IMAP4rev1 QUOTA\r\n".to_vec(),
+            &mut VecDeque::new(),
+        )
+        .unwrap();
+        assert_eq!(caps.quota_resources().count(), 0);
+        assert!(!caps.has_quota_set());
+    }
+
     #[test]
     fn test_quota_resource_limit_new() {
         let limit = QuotaResourceLimit::new("STORAGE", 1000);
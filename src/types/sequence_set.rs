@@ -0,0 +1,141 @@
+use std::fmt;
+use std::ops::RangeInclusive;
+
+/// A set of message sequence numbers or [`Uid`](crate::types::Uid)s in the compact form of a
+/// [`sequence-set`](https://tools.ietf.org/html/rfc3501#section-9), that is, as a list of
+/// ranges.
+///
+/// This is what servers return when they are asked for a potentially large set of messages, for
+/// example by [`Session::esearch`](crate::Session::esearch). Use [`SequenceSet::iter`] to go
+/// through the individual numbers without materializing them all at once. The [`Display`]
+/// implementation formats the set the way it is sent to the server, so it can be passed on to
+/// commands such as [`Session::uid_fetch`](crate::Session::uid_fetch).
+///
+/// The order of the ranges is kept as the server sent them. In the results of
+/// [`Session::esort`](crate::Session::esort), the order of the numbers within a range is kept as
+/// well, so that a range such as `5:3` stands for 5, 4 and 3, in that order.
+///
+/// [`Display`]: fmt::Display
+#[derive(Clone, Debug, Default, Eq, PartialEq, Hash)]
+pub struct SequenceSet {
+    ranges: Vec<RangeInclusive<u32>>,
+    /// Whether the range at the same position goes from its upper bound down to its lower bound.
+    descending: Vec<bool>,
+}
+
+impl SequenceSet {
+    /// A set of the given ranges, where a range such as `5..=3` stands for 5, 4 and 3, in that
+    /// order.
+    pub(crate) fn in_order(ranges: Vec<RangeInclusive<u32>>) -> Self {
+        let (ranges, descending) = ranges
+            .into_iter()
+            .map(|range| {
+                let (start, end) = range.into_inner();
+                (start.min(end)..=start.max(end), start > end)
+            })
+            .unzip();
+        SequenceSet { ranges, descending }
+    }
+
+    /// The same set, with the numbers of every range in ascending order.
+    pub(crate) fn into_ascending(mut self) -> Self {
+        self.descending.iter_mut().for_each(|d| *d = false);
+        self
+    }
+
+    /// The ranges in this set, in the order the server sent them, each with its lower bound
+    /// first.
+    pub fn ranges(&self) -> &[RangeInclusive<u32>] {
+        &self.ranges
+    }
+
+    /// Iterate over the numbers in this set, in order.
+    pub fn iter(&self) -> impl Iterator<Item = u32> + '_ {
+        self.ranges
+            .iter()
+            .zip(&self.descending)
+            .flat_map(|(range, &descending)| {
+                let (start, end) = (*range.start(), *range.end());
+                (0..=end - start).map(move |k| if descending { end - k } else { start + k })
+            })
+    }
+
+    /// Returns the number of numbers in this set, counting a number as often as it appears.
+    ///
+    /// The count saturates at `usize::MAX`, which a set such as `1:4294967295` exceeds on 32-bit
+    /// targets.
+    pub fn len(&self) -> usize {
+        self.ranges.iter().fold(0usize, |len, range| {
+            let range_len = usize::try_from(range.end() - range.start())
+                .unwrap_or(usize::MAX)
+                .saturating_add(1);
+            len.saturating_add(range_len)
+        })
+    }
+
+    /// Returns true if this set is empty.
+    pub fn is_empty(&self) -> bool {
+        self.ranges.is_empty()
+    }
+
+    /// Returns true if `number` is in this set.
+    pub fn contains(&self, number: u32) -> bool {
+        self.ranges.iter().any(|range| range.contains(&number))
+    }
+}
+
+impl From<Vec<RangeInclusive<u32>>> for SequenceSet {
+    /// Ranges may be given in either order, as in a sequence-set: `5..=3` is the same as `3..=5`.
+    fn from(ranges: Vec<RangeInclusive<u32>>) -> Self {
+        SequenceSet::in_order(ranges).into_ascending()
+    }
+}
+
+impl fmt::Display for SequenceSet {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, (range, &descending)) in self.ranges.iter().zip(&self.descending).enumerate() {
+            if i > 0 {
+                f.write_str(",")?;
+            }
+            let (start, end) = (range.start(), range.end());
+            if start == end {
+                write!(f, "{}", start)?;
+            } else if descending {
+                write!(f, "{}:{}", end, start)?;
+            } else {
+                write!(f, "{}:{}", start, end)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn len_does_not_overflow() {
+        let all = SequenceSet::from(vec![1..=u32::MAX]);
+        assert_eq!(all.len() as u64, u64::from(u32::MAX));
+        // On 32-bit targets, the count saturates.
+        let twice = SequenceSet::from(vec![1..=u32::MAX, 1..=u32::MAX]);
+        assert_eq!(
+            twice.len() as u64,
+            (2 * u64::from(u32::MAX)).min(usize::MAX as u64)
+        );
+    }
+
+    #[test]
+    fn in_order() {
+        let set = SequenceSet::in_order(vec![RangeInclusive::new(5, 3), 7..=7, 8..=9]);
+        assert_eq!(set.ranges(), &[3..=5, 7..=7, 8..=9]);
+        assert_eq!(set.to_string(), "5:3,7,8:9");
+        assert_eq!(set.iter().collect::<Vec<_>>(), vec![5, 4, 3, 7, 8, 9]);
+        assert_eq!(set.clone().into_ascending().to_string(), "3:5,7,8:9");
+        assert_eq!(
+            set.into_ascending(),
+            SequenceSet::from(vec![RangeInclusive::new(5, 3), 7..=7, 8..=9])
+        );
+    }
+}
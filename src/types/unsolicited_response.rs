@@ -1,9 +1,10 @@
-use super::{Flag, Seq};
+use super::{Flag, Mailbox, Name, Seq};
+use crate::parse::status_from_attributes;
+use crate::utf7;
 
 /// re-exported from imap_proto;
 pub use imap_proto::AttributeValue;
 pub use imap_proto::ResponseCode;
-pub use imap_proto::StatusAttribute;
 use imap_proto::{MailboxDatum, Response, Status};
 
 /// Responses that the server sends that are not related to the current command.
@@ -75,6 +76,14 @@ pub enum UnsolicitedResponse {
     // TODO: the spec doesn't seem to say anything about when these may be received as unsolicited?
     Flags(Vec<Flag<'static>>),
 
+    /// An unsolicited [`LIST` response](https://tools.ietf.org/html/rfc3501#section-7.2.2), which
+    /// servers with the [NOTIFY](https://tools.ietf.org/html/rfc5465#section-5.4) extension send
+    /// when a mailbox is created, deleted or renamed, or its subscription changes.
+    ///
+    /// Like the other mailbox names in unsolicited responses, the name is converted from
+    /// [modified UTF-7](crate::utf7) into UTF-8, as for [`Session::list`](crate::Session::list).
+    List(Name<'static>),
+
     /// An unsolicited [METADATA response](https://tools.ietf.org/html/rfc5464#section-4.4.2)
     /// that reports a change in a server or mailbox annotation.
     Metadata {
@@ -114,12 +123,16 @@ pub enum UnsolicitedResponse {
     /// > set, or to do a `SEARCH RECENT`.
     Recent(u32),
 
-    /// An unsolicited [`STATUS response`](https://tools.ietf.org/html/rfc3501#section-7.2.4).
-    Status {
+    /// An unsolicited [`STATUS response`](https://tools.ietf.org/html/rfc3501#section-7.2.4),
+    /// including status data items of extensions such as `SIZE` (see [RFC
+    /// 9208](https://tools.ietf.org/html/rfc9208)) or `MAILBOXID` (see [RFC
+    /// 8474](https://tools.ietf.org/html/rfc8474)).
+    MailboxStatus {
         /// The mailbox that this status response is for.
         mailbox: String,
-        /// The attributes of this mailbox.
-        attributes: Vec<StatusAttribute>,
+        /// The status of this mailbox, with the fields the server sent, as returned by
+        /// [`Session::status`](crate::Session::status).
+        status: Mailbox,
     },
 
     /// An unsolicited [`VANISHED` response](https://tools.ietf.org/html/rfc7162#section-3.2.10)
@@ -149,6 +162,18 @@ pub enum UnsolicitedResponse {
     },
 }
 
+impl UnsolicitedResponse {
+    /// Converts the mailbox names sent by the server from modified UTF-7 into UTF-8.
+    pub(crate) fn decode_utf7(&mut self) {
+        match self {
+            UnsolicitedResponse::List(name) => name.decode_utf7(),
+            UnsolicitedResponse::MailboxStatus { mailbox, .. }
+            | UnsolicitedResponse::Metadata { mailbox, .. } => utf7::decode_lossless_owned(mailbox),
+            _ => {}
+        }
+    }
+}
+
 /// Try to convert from a `imap_proto::Response`.
 ///
 /// Not all `Response` variants are supported - only those which
@@ -192,11 +217,25 @@ impl<'a> TryFrom<Response<'a>> for UnsolicitedResponse {
                     metadata_entries: values.iter().map(|s| s.to_string()).collect(),
                 })
             }
+            Response::MailboxData(MailboxDatum::List {
+                name_attributes,
+                delimiter,
+                name,
+            }) => Ok(UnsolicitedResponse::List(
+                Name {
+                    attributes: name_attributes,
+                    delimiter,
+                    name,
+                    child_info: Vec::new(),
+                    old_name: None,
+                }
+                .into_owned(),
+            )),
             Response::MailboxData(MailboxDatum::Recent(n)) => Ok(UnsolicitedResponse::Recent(n)),
             Response::MailboxData(MailboxDatum::Status { mailbox, status }) => {
-                Ok(UnsolicitedResponse::Status {
+                Ok(UnsolicitedResponse::MailboxStatus {
                     mailbox: mailbox.into(),
-                    attributes: status,
+                    status: status_from_attributes(status),
                 })
             }
             Response::Vanished { earlier, uids } => {
@@ -0,0 +1,214 @@
+//! Encoding and decoding of mailbox names in the modified UTF-7 encoding specified in [RFC 3501,
+//! section 5.1.3](https://tools.ietf.org/html/rfc3501#section-5.1.3).
+//!
+//! IMAP4rev1 servers expect mailbox names with characters outside of printable US-ASCII to be
+//! sent in this encoding, and send them back encoded the same way. [`Session`](crate::Session)
+//! takes care of this for every method that takes or returns a mailbox name, so these functions
+//! are only needed when building commands by hand, for instance with
+//! [`Session::run_command_and_read_response`](crate::Session::run_command_and_read_response).
+//!
+//! Once `UTF8=ACCEPT` ([RFC 6855](https://tools.ietf.org/html/rfc6855)) has been enabled through
+//! [`Session::enable`](crate::Session::enable), mailbox names are exchanged as plain UTF-8
+//! instead, and `Session` no longer converts them.
+//!
+//! ```
+//! assert_eq!(imap::utf7::encode("Entwürfe"), "Entw&APw-rfe");
+//! assert_eq!(imap::utf7::decode("Entw&APw-rfe").unwrap(), "Entwürfe");
+//! ```
+
+use base64::{
+    alphabet,
+    engine::{DecodePaddingMode, GeneralPurpose, GeneralPurposeConfig},
+    Engine as _,
+};
+use std::borrow::Cow;
+use std::error::Error as StdError;
+use std::fmt;
+
+/// Base64 as modified by RFC 3501: `,` is used instead of `/`, and there is no padding.
+const MUTF7: GeneralPurpose = GeneralPurpose::new(
+    &alphabet::IMAP_MUTF7,
+    GeneralPurposeConfig::new()
+        .with_encode_padding(false)
+        .with_decode_padding_mode(DecodePaddingMode::RequireNone),
+);
+
+/// The given mailbox name is not valid modified UTF-7.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub struct DecodeError {
+    position: usize,
+}
+
+impl DecodeError {
+    /// The byte offset of the first invalid or incomplete sequence in the input.
+    pub fn position(&self) -> usize {
+        self.position
+    }
+}
+
+impl fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Invalid modified UTF-7 in mailbox name at byte {}",
+            self.position
+        )
+    }
+}
+
+impl StdError for DecodeError {}
+
+/// Returns true if `c` is represented as itself in modified UTF-7.
+fn is_direct(c: char) -> bool {
+    (' '..='~').contains(&c) && c != '&'
+}
+
+/// Encode a mailbox name in modified UTF-7.
+///
+/// Printable US-ASCII characters other than `&` are left as they are, so names that consist of
+/// those only are returned unchanged.
+pub fn encode(name: &str) -> String {
+    let mut encoded = String::with_capacity(name.len());
+    let mut pending = Vec::new();
+    for c in name.chars() {
+        if is_direct(c) {
+            flush(&mut encoded, &mut pending);
+            encoded.push(c);
+        } else if c == '&' {
+            flush(&mut encoded, &mut pending);
+            encoded.push_str("&-");
+        } else {
+            let mut units = [0; 2];
+            for unit in c.encode_utf16(&mut units) {
+                pending.extend_from_slice(&unit.to_be_bytes());
+            }
+        }
+    }
+    flush(&mut encoded, &mut pending);
+    encoded
+}
+
+/// Writes the base64 encoding of the UTF-16BE data in `pending`, if any.
+fn flush(encoded: &mut String, pending: &mut Vec<u8>) {
+    if !pending.is_empty() {
+        encoded.push('&');
+        MUTF7.encode_string(&pending, encoded);
+        encoded.push('-');
+        pending.clear();
+    }
+}
+
+/// Decode a mailbox name from modified UTF-7.
+///
+/// Besides malformed base64, this rejects everything RFC 3501 does not allow an encoder to
+/// produce, such as raw non-ASCII characters, or base64 used for characters that have to be
+/// represented as themselves.
+pub fn decode(name: &str) -> Result<String, DecodeError> {
+    let mut decoded = String::with_capacity(name.len());
+    let mut rest = name;
+    while let Some(start) = rest.find(|c: char| c == '&' || !is_direct(c)) {
+        let position = name.len() - rest.len() + start;
+        if !rest[start..].starts_with('&') {
+            return Err(DecodeError { position });
+        }
+        decoded.push_str(&rest[..start]);
+        let len = rest[start + 1..]
+            .find('-')
+            .ok_or(DecodeError { position })?;
+        let shifted = &rest[start + 1..start + 1 + len];
+        if shifted.is_empty() {
+            decoded.push('&');
+        } else {
+            decoded.push_str(&decode_shifted(shifted).ok_or(DecodeError { position })?);
+        }
+        rest = &rest[start + len + 2..];
+    }
+    decoded.push_str(rest);
+    Ok(decoded)
+}
+
+/// Decodes the base64 part of a shifted sequence, that is, the text between `&` and `-`.
+fn decode_shifted(shifted: &str) -> Option<String> {
+    let bytes = MUTF7.decode(shifted).ok()?;
+    if bytes.len() % 2 != 0 {
+        return None;
+    }
+    let units = bytes
+        .chunks(2)
+        .map(|pair| u16::from_be_bytes([pair[0], pair[1]]));
+    let text = char::decode_utf16(units)
+        .collect::<Result<String, _>>()
+        .ok()?;
+    if text.chars().any(|c| is_direct(c) || c == '&') {
+        return None;
+    }
+    Some(text)
+}
+
+/// Decode a mailbox name sent by the server, keeping it as it is if it is not valid modified
+/// UTF-7; some servers do not encode names that other clients created with raw 8-bit characters.
+pub(crate) fn decode_lossless(name: &mut Cow<'_, str>) {
+    if name.contains('&') {
+        if let Ok(decoded) = decode(name) {
+            *name = Cow::Owned(decoded);
+        }
+    }
+}
+
+/// Like [`decode_lossless`], for a name that is already owned.
+pub(crate) fn decode_lossless_owned(name: &mut String) {
+    if name.contains('&') {
+        if let Ok(decoded) = decode(name) {
+            *name = decoded;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trip() {
+        let names = [
+            ("INBOX", "INBOX"),
+            ("Entwürfe", "Entw&APw-rfe"),
+            ("R&D", "R&-D"),
+            ("~peter/mail/台北/日本語", "~peter/mail/&U,BTFw-/&ZeVnLIqe-"),
+            ("Gelöschte Elemente", "Gel&APY-schte Elemente"),
+            ("Éléments envoyés", "&AMk-l&AOk-ments envoy&AOk-s"),
+            ("😀", "&2D3eAA-"),
+            ("tab\there", "tab&AAk-here"),
+        ];
+        for (name, encoded) in names {
+            assert_eq!(encode(name), encoded);
+            assert_eq!(decode(encoded).unwrap(), name);
+        }
+    }
+
+    #[test]
+    fn decode_invalid() {
+        // raw 8-bit
+        assert_eq!(decode("Entwürfe").unwrap_err().position(), 4);
+        // unterminated
+        assert_eq!(decode("Entw&APw").unwrap_err().position(), 4);
+        // bad base64
+        assert!(decode("a&A?w-").is_err());
+        // odd number of bytes
+        assert!(decode("&AP-").is_err());
+        // printable ASCII must not be encoded
+        assert!(decode("&AGE-").is_err());
+        // unpaired surrogate
+        assert!(decode("&2D0-").is_err());
+    }
+
+    #[test]
+    fn decode_lossless_keeps_invalid() {
+        let mut name = Cow::Borrowed("Drafts & Templates");
+        decode_lossless(&mut name);
+        assert_eq!(name, "Drafts & Templates");
+        let mut name = Cow::Borrowed("Entw&APw-rfe");
+        decode_lossless(&mut name);
+        assert_eq!(name, "Entwürfe");
+    }
+}
@@ -670,7 +670,10 @@ fn qresync() {
     let mut s = session(to);
 
     // Enable extension
-    s.run_command_and_read_response("ENABLE QRESYNC").unwrap();
+    let enabled = s
+        .enable(&[imap_proto::Capability::Atom("QRESYNC".into())])
+        .unwrap();
+    assert!(enabled.has_str("QRESYNC"));
 
     // Add an email and get its UID
     let mbox = "INBOX";